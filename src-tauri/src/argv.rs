@@ -0,0 +1,147 @@
+// コマンドプレフィックスや引数文字列をシェル風に分割する
+//
+// - 空白で区切る
+// - '...' の中はそのまま (エスケープなし)
+// - "..." の中は \" と \\ だけをエスケープとして扱う
+// - クォートの外の \ は、空白とクォートの前にあるときだけエスケープ
+//   (C:\Users\me\model.py や \\server\share のような Windows のパスをそのまま書けるようにするため)
+
+fn is_escapable(c: char, in_double: bool) -> bool {
+    if in_double {
+        matches!(c, '"' | '\\')
+    } else {
+        c.is_whitespace() || matches!(c, '"' | '\'')
+    }
+}
+
+pub fn split(input: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    // クォートで空文字列を渡した場合 ("") も1つの引数として扱う
+    let mut has_token = false;
+    let mut chars = input.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' => {
+                has_token = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => current.push(c),
+                        None => return Err("シングルクォートが閉じられていません。".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                has_token = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') if chars.peek().is_some_and(|&n| is_escapable(n, true)) => {
+                            current.push(chars.next().unwrap_or('\\'));
+                        }
+                        Some(c) => current.push(c),
+                        None => return Err("ダブルクォートが閉じられていません。".to_string()),
+                    }
+                }
+            }
+            '\\' if chars.peek().is_some_and(|&n| is_escapable(n, false)) => {
+                has_token = true;
+                current.push(chars.next().unwrap_or('\\'));
+            }
+            c if c.is_whitespace() => {
+                if has_token {
+                    args.push(std::mem::take(&mut current));
+                    has_token = false;
+                }
+            }
+            c => {
+                has_token = true;
+                current.push(c);
+            }
+        }
+    }
+    if has_token {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// split で元に戻せる形にクォートする (履歴への保存や表示用)
+pub fn quote(arg: &str) -> String {
+    // 末尾の \ は次の区切りの空白をエスケープしてしまうのでクォートする
+    let needs_quote = arg.is_empty()
+        || arg.ends_with('\\')
+        || arg
+            .chars()
+            .any(|c| c.is_whitespace() || matches!(c, '"' | '\''));
+    if !needs_quote {
+        return arg.to_string();
+    }
+    if !arg.contains('\'') {
+        return format!("'{}'", arg);
+    }
+    format!("\"{}\"", arg.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+pub fn join(args: &[String]) -> String {
+    args.iter().map(|a| quote(a)).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn splits_on_whitespace_and_quotes() {
+        assert_eq!(
+            split("uv run  python -u").unwrap(),
+            args(&["uv", "run", "python", "-u"])
+        );
+        assert_eq!(
+            split(r#"--name "base case" --tag 'a b' --empty """#).unwrap(),
+            args(&["--name", "base case", "--tag", "a b", "--empty", ""])
+        );
+        assert_eq!(split(r#""say \"hi\"""#).unwrap(), args(&[r#"say "hi""#]));
+        assert!(split("it's").is_err());
+        assert_eq!(split(r"a\ b \'c").unwrap(), args(&["a b", "'c"]));
+        assert!(split("\"open").is_err());
+        assert!(split("   ").unwrap().is_empty());
+    }
+
+    #[test]
+    fn keeps_windows_paths() {
+        assert_eq!(
+            split(r"C:\Users\me\model.py \\server\share\data.lp").unwrap(),
+            args(&[r"C:\Users\me\model.py", r"\\server\share\data.lp"])
+        );
+        assert_eq!(
+            split(r#""C:\Program Files\Python\python.exe" -u"#).unwrap(),
+            args(&[r"C:\Program Files\Python\python.exe", "-u"])
+        );
+        assert_eq!(
+            split(r#""C:\data\\" next"#).unwrap(),
+            args(&[r"C:\data\", "next"])
+        );
+    }
+
+    #[test]
+    fn quote_round_trips() {
+        let cases = args(&[
+            "plain",
+            "",
+            "two words",
+            r"C:\Program Files\x.py",
+            r"C:\dir\",
+            "it's",
+            r#"say "hi""#,
+            r#"it's "both""#,
+        ]);
+        assert_eq!(split(&join(&cases)).unwrap(), cases);
+    }
+}
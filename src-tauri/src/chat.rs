@@ -0,0 +1,173 @@
+use crate::history::ChatMessageRecord;
+use crate::llm::{ChatMessage, ChatRole};
+use crate::prompt::TokenCounter;
+
+// 実行ごとの追加質問のスレッドを、LLM に送るメッセージ列に組み立てる
+// 最初のメッセージ (context) に解析と同じ形のログを入れ、以降は質問と回答を交互に積む
+
+// 新しい質問に添える指示
+const FOLLOW_UP_INSTRUCTION: &str =
+    "上のログとこれまでのやり取りを踏まえて、次の質問に答えてください。ログから読み取れない事柄は推測であることを明記すること。";
+
+// ログに回す予算の割合 (残りを質問と回答のやり取りに使う)
+pub fn context_budget(total: usize) -> usize {
+    total * 2 / 3
+}
+
+fn question_message(question: &str, omitted: usize) -> ChatMessage {
+    let mut content = String::new();
+    if omitted > 0 {
+        content.push_str(&format!(
+            "(予算の都合で、以前のメッセージ {} 件を省略しています)\n",
+            omitted
+        ));
+    }
+    content.push_str(&format!(
+        "[追加の質問]\n{}\n{}",
+        FOLLOW_UP_INSTRUCTION, question
+    ));
+    ChatMessage::new(ChatRole::User, content)
+}
+
+// 予算を超える場合は、ログと新しい質問を残して古いやり取りから省く
+// (ログの直後の回答は最初の解析結果で、ログと組になっている)
+pub fn request_messages(
+    thread: &[ChatMessageRecord],
+    question: &str,
+    counter: &TokenCounter,
+    budget: usize,
+) -> Vec<ChatMessage> {
+    let to_message = |m: &ChatMessageRecord| ChatMessage::new(m.role, m.content.clone());
+    let context: Vec<ChatMessage> = thread
+        .iter()
+        .filter(|m| m.is_context)
+        .map(to_message)
+        .collect();
+    let turns: Vec<ChatMessage> = thread
+        .iter()
+        .filter(|m| !m.is_context)
+        .map(to_message)
+        .collect();
+
+    let tokens = |messages: &[ChatMessage]| -> usize {
+        messages.iter().map(|m| counter.count(&m.content)).sum()
+    };
+    let fixed = tokens(&context) + counter.count(&question_message(question, turns.len()).content);
+
+    // 新しいやり取りから順に、予算に収まるだけ残す
+    let mut used = fixed;
+    let mut start = turns.len();
+    while start > 0 {
+        let cost = counter.count(&turns[start - 1].content);
+        if used + cost > budget {
+            break;
+        }
+        used += cost;
+        start -= 1;
+    }
+    // 省くときは質問と回答の組ごとに省く (質問を省いた回答から始まらないようにする)
+    while start > 0 && start < turns.len() && turns[start].role == ChatRole::Assistant {
+        start += 1;
+    }
+
+    let mut messages = context;
+    messages.extend_from_slice(&turns[start..]);
+    messages.push(question_message(question, start));
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const QUESTION: &str = "Why is the gap large?";
+
+    fn record(role: ChatRole, is_context: bool, content: String) -> ChatMessageRecord {
+        ChatMessageRecord {
+            id: 0,
+            created_at: 0,
+            role,
+            is_context,
+            model: None,
+            content,
+        }
+    }
+
+    // ログ・最初の解析結果と、2組の質問と回答 (ログ以外はそれぞれ 10 トークン)
+    fn thread() -> Vec<ChatMessageRecord> {
+        let turn = |role, name: &str| record(role, false, format!("{:<40}", name));
+        vec![
+            record(ChatRole::User, true, "log ".repeat(100)),
+            turn(ChatRole::Assistant, "analysis"),
+            turn(ChatRole::User, "q1"),
+            turn(ChatRole::Assistant, "a1"),
+            turn(ChatRole::User, "q2"),
+            turn(ChatRole::Assistant, "a2"),
+        ]
+    }
+
+    fn counter() -> TokenCounter {
+        TokenCounter::for_model("gemini-2.5-flash")
+    }
+
+    // ログと新しい質問だけの分
+    fn base_budget() -> usize {
+        let counter = counter();
+        counter.count(&thread()[0].content) + counter.count(&question_message(QUESTION, 5).content)
+    }
+
+    fn contents(messages: &[ChatMessage]) -> Vec<String> {
+        messages[1..messages.len() - 1]
+            .iter()
+            .map(|m| m.content.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_everything_within_budget() {
+        let messages = request_messages(&thread(), QUESTION, &counter(), base_budget() + 100);
+        assert_eq!(messages[0].content, thread()[0].content);
+        assert_eq!(
+            contents(&messages),
+            vec!["analysis", "q1", "a1", "q2", "a2"]
+        );
+        let last = messages.last().unwrap();
+        assert_eq!(last.role, ChatRole::User);
+        assert!(last.content.ends_with(QUESTION));
+        assert!(!last.content.contains("省略"));
+    }
+
+    #[test]
+    fn trims_whole_pairs_from_the_oldest() {
+        // q2 と a2 だけが収まる
+        let messages = request_messages(&thread(), QUESTION, &counter(), base_budget() + 25);
+        assert_eq!(contents(&messages), vec!["q2", "a2"]);
+        assert!(messages
+            .last()
+            .unwrap()
+            .content
+            .contains("以前のメッセージ 3 件"));
+
+        // a1 までは収まるが、q1 を省くので a1 も省く
+        let messages = request_messages(&thread(), QUESTION, &counter(), base_budget() + 35);
+        assert_eq!(contents(&messages), vec!["q2", "a2"]);
+        assert_eq!(messages[1].role, ChatRole::User);
+
+        // 最初の解析結果だけを省く
+        let messages = request_messages(&thread(), QUESTION, &counter(), base_budget() + 45);
+        assert_eq!(contents(&messages), vec!["q1", "a1", "q2", "a2"]);
+        assert!(messages
+            .last()
+            .unwrap()
+            .content
+            .contains("以前のメッセージ 1 件"));
+    }
+
+    #[test]
+    fn keeps_log_and_question_when_nothing_else_fits() {
+        let messages = request_messages(&thread(), QUESTION, &counter(), base_budget());
+        assert_eq!(messages.len(), 2);
+        assert!(messages[1].content.contains("以前のメッセージ 5 件"));
+        assert!(messages[1].content.ends_with(QUESTION));
+    }
+}
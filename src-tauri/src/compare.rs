@@ -0,0 +1,379 @@
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::history::RunRecord;
+use crate::log_parser::{self, MipProgress, ProgressEvent};
+use crate::summary::{self, SolveSummary};
+
+// 複数の実行 (変更前/変更後など) を並べて比較する
+
+// 時間軸の既定の分割数
+pub const DEFAULT_CURVE_POINTS: usize = 200;
+const MAX_CURVE_POINTS: usize = 2000;
+pub const MAX_COMPARED_RUNS: usize = 20;
+
+// 比較対象の1実行
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedRun {
+    pub id: i64,
+    pub script_path: String,
+    pub args: String,
+    pub started_at: i64,
+    pub summary: SolveSummary,
+    // 既定値から変更されたパラメータ
+    pub parameters: BTreeMap<String, String>,
+    // 先頭の実行 (基準) との差
+    pub objective_delta: Option<f64>,
+    pub runtime_ratio: Option<f64>,
+    pub nodes_ratio: Option<f64>,
+}
+
+// 実行ごとに値が異なるパラメータ (None は未設定 = 既定値)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParameterDiff {
+    pub name: String,
+    pub values: Vec<Option<String>>,
+}
+
+// 共通の時間軸に揃えた推移 (実行が終わった後や最初の行より前は None)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlignedCurves {
+    pub run_id: i64,
+    pub gap: Vec<Option<f64>>,
+    pub incumbent: Vec<Option<f64>>,
+    pub best_bound: Vec<Option<f64>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunComparison {
+    pub runs: Vec<ComparedRun>,
+    pub parameter_diffs: Vec<ParameterDiff>,
+    // 秒
+    pub time_axis: Vec<f64>,
+    pub curves: Vec<AlignedCurves>,
+}
+
+// 最後に行われた最適化の分枝限定法の行だけを取り出す (経過時間が巻き戻ったら別の最適化)
+fn last_mip_segment(log: &str) -> Vec<MipProgress> {
+    let mut segment: Vec<MipProgress> = Vec::new();
+    for event in log_parser::parse_progress(log) {
+        if let ProgressEvent::Mip(p) = event {
+            if segment.last().is_some_and(|last| p.time < last.time) {
+                segment.clear();
+            }
+            segment.push(p);
+        }
+    }
+    segment
+}
+
+// 時刻 t 時点の値 (それ以前で最後に観測された値) を返す
+fn step_value(points: &[(f64, Option<f64>)], t: f64) -> Option<f64> {
+    let idx = points.partition_point(|(time, _)| *time <= t);
+    if idx == 0 {
+        return None;
+    }
+    // 値の無い行 (まだ解が無いなど) は直前の値を引き継がない
+    points[idx - 1].1
+}
+
+fn resample(points: &[(f64, Option<f64>)], end: f64, axis: &[f64]) -> Vec<Option<f64>> {
+    axis.iter()
+        .map(|&t| if t > end { None } else { step_value(points, t) })
+        .collect()
+}
+
+fn ratio(value: Option<f64>, base: Option<f64>) -> Option<f64> {
+    match (value, base) {
+        (Some(v), Some(b)) if b > 0.0 => Some(v / b),
+        _ => None,
+    }
+}
+
+pub fn compare_runs(records: &[RunRecord], points: Option<usize>) -> Result<RunComparison, String> {
+    if records.len() < 2 {
+        return Err("比較には2件以上の実行を選択してください。".to_string());
+    }
+    if records.len() > MAX_COMPARED_RUNS {
+        return Err(format!(
+            "一度に比較できるのは {} 件までです。",
+            MAX_COMPARED_RUNS
+        ));
+    }
+    let points = points
+        .unwrap_or(DEFAULT_CURVE_POINTS)
+        .clamp(2, MAX_CURVE_POINTS);
+
+    let mut runs = Vec::new();
+    let mut segments = Vec::new();
+    for record in records {
+        let summary = record
+            .summary
+            .clone()
+            .unwrap_or_else(|| summary::parse_solve_summary(&record.raw_log));
+        runs.push(ComparedRun {
+            id: record.id,
+            script_path: record.script_path.clone(),
+            args: record.args.clone(),
+            started_at: record.started_at,
+            parameters: summary::parse_parameter_changes(&record.raw_log),
+            summary,
+            objective_delta: None,
+            runtime_ratio: None,
+            nodes_ratio: None,
+        });
+        segments.push(last_mip_segment(&record.raw_log));
+    }
+
+    // 基準 (先頭) との差
+    let base = runs[0].summary.clone();
+    for run in runs.iter_mut().skip(1) {
+        let s = &run.summary;
+        run.objective_delta = s
+            .best_objective
+            .zip(base.best_objective)
+            .map(|(v, b)| v - b);
+        run.runtime_ratio = ratio(s.runtime, base.runtime);
+        run.nodes_ratio = ratio(
+            s.explored_nodes.map(|n| n as f64),
+            base.explored_nodes.map(|n| n as f64),
+        );
+    }
+
+    // どれか1つでも値が異なるパラメータだけを残す
+    let names: BTreeSet<&String> = runs.iter().flat_map(|r| r.parameters.keys()).collect();
+    let parameter_diffs = names
+        .into_iter()
+        .filter_map(|name| {
+            let values: Vec<Option<String>> = runs
+                .iter()
+                .map(|r| r.parameters.get(name).cloned())
+                .collect();
+            if values.windows(2).all(|w| w[0] == w[1]) {
+                None
+            } else {
+                Some(ParameterDiff {
+                    name: name.clone(),
+                    values,
+                })
+            }
+        })
+        .collect();
+
+    // 各実行の終了時刻 (サマリの Runtime が無ければ最後の行の時刻)
+    let ends: Vec<f64> = runs
+        .iter()
+        .zip(&segments)
+        .map(|(run, seg)| {
+            let last = seg.last().map(|p| p.time).unwrap_or(0.0);
+            run.summary.runtime.unwrap_or(last).max(last)
+        })
+        .collect();
+    let max_end = ends.iter().copied().fold(0.0, f64::max);
+    let time_axis: Vec<f64> = (0..points)
+        .map(|i| max_end * i as f64 / (points - 1) as f64)
+        .collect();
+
+    let curves = runs
+        .iter()
+        .zip(&segments)
+        .zip(&ends)
+        .map(|((run, seg), &end)| {
+            let mut gap: Vec<(f64, Option<f64>)> = seg.iter().map(|p| (p.time, p.gap)).collect();
+            let mut incumbent: Vec<(f64, Option<f64>)> =
+                seg.iter().map(|p| (p.time, p.incumbent)).collect();
+            let mut best_bound: Vec<(f64, Option<f64>)> =
+                seg.iter().map(|p| (p.time, p.best_bound)).collect();
+            // 最終サマリの値を終了時刻の点として加える
+            let s = &run.summary;
+            if s.gap.is_some() || s.best_objective.is_some() || s.best_bound.is_some() {
+                gap.push((end, s.gap.or(gap.last().and_then(|p| p.1))));
+                incumbent.push((end, s.best_objective.or(incumbent.last().and_then(|p| p.1))));
+                best_bound.push((end, s.best_bound.or(best_bound.last().and_then(|p| p.1))));
+            }
+            AlignedCurves {
+                run_id: run.id,
+                gap: resample(&gap, end, &time_axis),
+                incumbent: resample(&incumbent, end, &time_axis),
+                best_bound: resample(&best_bound, end, &time_axis),
+            }
+        })
+        .collect();
+
+    Ok(RunComparison {
+        runs,
+        parameter_diffs,
+        time_axis,
+        curves,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIP_HEADER: &str = "\
+    Nodes    |    Current Node    |     Objective Bounds      |     Work
+ Expl Unexpl |  Obj  Depth IntInf | Incumbent    BestBd   Gap | It/Node Time
+
+";
+
+    // 分枝限定法の表 (時刻, 暫定解, 境界)
+    fn table(rows: &[(u32, Option<f64>, f64)]) -> String {
+        let mut log = String::from(MIP_HEADER);
+        for (time, incumbent, bound) in rows {
+            let (incumbent, gap) = match incumbent {
+                Some(v) => (
+                    format!("{:.5}", v),
+                    format!("{:.2}%", (v - bound) / v * 100.0),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            log.push_str(&format!(
+                "{:>6} {:>5} {:.5}   10   5 {:>10} {:.5} {:>6}  10.0 {:>4}s\n",
+                0, 50, bound, incumbent, bound, gap, time
+            ));
+        }
+        log
+    }
+
+    fn record(id: i64, raw_log: String) -> RunRecord {
+        RunRecord {
+            id,
+            script_path: "model.py".to_string(),
+            args: String::new(),
+            command_prefix: "python".to_string(),
+            exit_code: Some(0),
+            started_at: id * 1000,
+            finished_at: id * 1000 + 500,
+            raw_log,
+            stderr_log: String::new(),
+            json_payload: None,
+            results: Vec::new(),
+            summary: None,
+            tags: Vec::new(),
+            analyses: Vec::new(),
+            chat: Vec::new(),
+            working_dir: None,
+            env: BTreeMap::new(),
+            parameters: BTreeMap::new(),
+            mode: Default::default(),
+            result_file: None,
+            solutions: Vec::new(),
+            iis_path: None,
+        }
+    }
+
+    // 10 秒で終わった基準の実行
+    fn base_run() -> RunRecord {
+        let mut log = String::from("Set parameter MIPGap to value 0.01\n");
+        log.push_str(&table(&[
+            (0, Some(1500.0), 1000.0),
+            (5, Some(1300.0), 1100.0),
+            (10, Some(1200.0), 1150.0),
+        ]));
+        log.push_str("\nExplored 100 nodes (1000 simplex iterations) in 10.00 seconds\n");
+        log.push_str("Optimal solution found (tolerance 1.00e-02)\n");
+        log.push_str(
+            "Best objective 1.200000000000e+03, best bound 1.150000000000e+03, gap 4.1667%\n",
+        );
+        record(1, log)
+    }
+
+    // 20 秒掛かった実行 (前半に別の最適化の表がある)
+    fn slow_run() -> RunRecord {
+        let mut log =
+            String::from("Set parameter MIPGap to value 0.05\nSet parameter Threads to value 4\n");
+        log.push_str(&table(&[(0, None, 900.0), (30, Some(1000.0), 950.0)]));
+        log.push_str(&table(&[
+            (0, None, 1000.0),
+            (10, Some(1250.0), 1100.0),
+            (20, Some(1210.0), 1160.0),
+        ]));
+        log.push_str("\nExplored 300 nodes (4000 simplex iterations) in 20.00 seconds\n");
+        log.push_str("Optimal solution found (tolerance 5.00e-02)\n");
+        log.push_str(
+            "Best objective 1.210000000000e+03, best bound 1.160000000000e+03, gap 4.1322%\n",
+        );
+        record(2, log)
+    }
+
+    #[test]
+    fn compares_against_the_first_run() {
+        let comparison = compare_runs(&[base_run(), slow_run()], Some(5)).unwrap();
+        let slow = &comparison.runs[1];
+        assert_eq!(slow.objective_delta, Some(10.0));
+        assert_eq!(slow.runtime_ratio, Some(2.0));
+        assert_eq!(slow.nodes_ratio, Some(3.0));
+        assert_eq!(comparison.runs[0].objective_delta, None);
+
+        let diffs: Vec<(&str, Vec<Option<&str>>)> = comparison
+            .parameter_diffs
+            .iter()
+            .map(|d| {
+                (
+                    d.name.as_str(),
+                    d.values.iter().map(|v| v.as_deref()).collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            diffs,
+            vec![
+                ("MIPGap", vec![Some("0.01"), Some("0.05")]),
+                ("Threads", vec![None, Some("4")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn aligns_runs_of_different_lengths() {
+        let comparison = compare_runs(&[base_run(), slow_run()], Some(5)).unwrap();
+        // 前半の別の最適化 (30 秒まで) は時間軸に含めない
+        assert_eq!(comparison.time_axis, vec![0.0, 5.0, 10.0, 15.0, 20.0]);
+
+        let base = &comparison.curves[0];
+        assert_eq!(base.run_id, 1);
+        // 終了後は None
+        assert_eq!(
+            base.incumbent,
+            vec![Some(1500.0), Some(1300.0), Some(1200.0), None, None]
+        );
+        assert_eq!(base.gap[2], Some(4.1667));
+
+        let slow = &comparison.curves[1];
+        // 暫定解が無い間は None、以降は直前の値を引き継ぐ
+        assert_eq!(
+            slow.incumbent,
+            vec![None, None, Some(1250.0), Some(1250.0), Some(1210.0)]
+        );
+        assert_eq!(
+            slow.best_bound,
+            vec![
+                Some(1000.0),
+                Some(1000.0),
+                Some(1100.0),
+                Some(1100.0),
+                Some(1160.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn validates_selection_and_points() {
+        assert!(compare_runs(&[base_run()], None).is_err());
+        let many: Vec<RunRecord> = (0..=MAX_COMPARED_RUNS as i64)
+            .map(|id| record(id, String::new()))
+            .collect();
+        assert!(compare_runs(&many, None).is_err());
+
+        let comparison = compare_runs(&[base_run(), slow_run()], Some(1)).unwrap();
+        assert_eq!(comparison.time_axis, vec![0.0, 20.0]);
+        let comparison = compare_runs(&[base_run(), slow_run()], None).unwrap();
+        assert_eq!(comparison.time_axis.len(), DEFAULT_CURVE_POINTS);
+    }
+}
@@ -0,0 +1,384 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::log_parser::{LogParser, ProgressEvent};
+
+// AI に渡す前に、進捗表 (分枝限定法・バリア法・単体法) の行と結果JSONを間引く
+// 新しい暫定解が見つかった行 (H / * や Incumbent の変化) は、どの方式でも必ず残す
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "kind",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum CompressionStrategy {
+    // 最初の head 行と、以降 every 行おき
+    FixedSampling { head: usize, every: usize },
+    // 暫定解が変わった行だけ
+    IncumbentChanges,
+    // seconds 秒ごとに最後の1行
+    TimeBuckets { seconds: f64 },
+    // ギャップが min_improvement ポイント (%) 以上縮んだ行
+    GapImprovement { min_improvement: f64 },
+    // 各表の最初の head 行と最後の tail 行
+    HeadTail { head: usize, tail: usize },
+}
+
+impl Default for CompressionStrategy {
+    fn default() -> Self {
+        CompressionStrategy::FixedSampling {
+            head: 15,
+            every: 15,
+        }
+    }
+}
+
+impl CompressionStrategy {
+    // 名前から既定値つきで作る (ヘッドレスモードの --compression 用)
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        match name {
+            "fixed" => Ok(Self::default()),
+            "incumbent" => Ok(CompressionStrategy::IncumbentChanges),
+            "time" => Ok(CompressionStrategy::TimeBuckets { seconds: 10.0 }),
+            "gap" => Ok(CompressionStrategy::GapImprovement {
+                min_improvement: 1.0,
+            }),
+            "head-tail" => Ok(CompressionStrategy::HeadTail { head: 50, tail: 50 }),
+            other => Err(format!(
+                "不明な間引き方式です: {} (fixed / incumbent / time / gap / head-tail)",
+                other
+            )),
+        }
+    }
+
+    // 結果JSONで残す配列の要素数と深さの既定値
+    // 表を大きく削る方式では、その分JSONを多めに残す
+    fn json_limits(&self) -> (usize, usize) {
+        match self {
+            CompressionStrategy::IncumbentChanges | CompressionStrategy::GapImprovement { .. } => {
+                (10, 6)
+            }
+            _ => (3, 4),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CompressionConfig {
+    pub strategy: CompressionStrategy,
+    // 省略時は方式ごとの既定値
+    pub json_max_items: Option<usize>,
+    pub json_max_depth: Option<usize>,
+}
+
+impl CompressionConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.strategy {
+            CompressionStrategy::FixedSampling { every, .. } if *every == 0 => {
+                Err("every は 1 以上にしてください".to_string())
+            }
+            CompressionStrategy::TimeBuckets { seconds }
+                if !seconds.is_finite() || *seconds <= 0.0 =>
+            {
+                Err("seconds は正の値にしてください".to_string())
+            }
+            CompressionStrategy::GapImprovement { min_improvement } if *min_improvement < 0.0 => {
+                Err("minImprovement は 0 以上にしてください".to_string())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn json_max_items(&self) -> usize {
+        self.json_max_items
+            .unwrap_or_else(|| self.strategy.json_limits().0)
+    }
+
+    fn json_max_depth(&self) -> usize {
+        self.json_max_depth
+            .unwrap_or_else(|| self.strategy.json_limits().1)
+    }
+
+    // 行ごとに残すかどうか (進捗表の行以外はすべて残す)
+    pub fn table_mask(&self, lines: &[String]) -> Vec<bool> {
+        let mut parser = LogParser::new();
+        let events: Vec<Option<ProgressEvent>> = lines.iter().map(|l| parser.feed(l)).collect();
+        let mut keep = vec![true; lines.len()];
+
+        // 連続する同じ種類の行を1つの表として扱う
+        let mut start = 0;
+        while start < events.len() {
+            let Some(first) = &events[start] else {
+                start += 1;
+                continue;
+            };
+            let mut end = start + 1;
+            while end < events.len()
+                && events[end]
+                    .as_ref()
+                    .is_some_and(|e| std::mem::discriminant(e) == std::mem::discriminant(first))
+            {
+                end += 1;
+            }
+            let rows: Vec<&ProgressEvent> = events[start..end].iter().flatten().collect();
+            for (i, k) in self.keep_rows(&rows).into_iter().enumerate() {
+                keep[start + i] = k;
+            }
+            start = end;
+        }
+        keep
+    }
+
+    fn keep_rows(&self, rows: &[&ProgressEvent]) -> Vec<bool> {
+        let n = rows.len();
+        let mut keep: Vec<bool> = match &self.strategy {
+            CompressionStrategy::FixedSampling { head, every } => (0..n)
+                .map(|i| i < *head || (i + 1) % (*every).max(1) == 0)
+                .collect(),
+            CompressionStrategy::IncumbentChanges => vec![false; n],
+            CompressionStrategy::TimeBuckets { seconds } => {
+                // バケットが変わる直前の行 (= 各バケットの最後の行)
+                let bucket = |i: usize| (time_of(rows[i]) / seconds).floor() as i64;
+                (0..n)
+                    .map(|i| i + 1 == n || bucket(i) != bucket(i + 1))
+                    .collect()
+            }
+            CompressionStrategy::GapImprovement { min_improvement } => {
+                let mut last_gap: Option<f64> = None;
+                rows.iter()
+                    .map(|row| match gap_of(row) {
+                        Some(gap) if last_gap.is_none_or(|last| last - gap >= *min_improvement) => {
+                            last_gap = Some(gap);
+                            true
+                        }
+                        _ => false,
+                    })
+                    .collect()
+            }
+            CompressionStrategy::HeadTail { head, tail } => {
+                (0..n).map(|i| i < *head || i + *tail >= n).collect()
+            }
+        };
+
+        // 暫定解が変わった行と、表の最初と最後の行は必ず残す
+        let mut last_incumbent: Option<f64> = None;
+        for (i, row) in rows.iter().enumerate() {
+            if let ProgressEvent::Mip(mip) = row {
+                if mip.marker.is_some_and(|m| m == 'H' || m == '*')
+                    || (mip.incumbent.is_some() && mip.incumbent != last_incumbent)
+                {
+                    keep[i] = true;
+                }
+                last_incumbent = mip.incumbent.or(last_incumbent);
+            }
+        }
+        if n > 0 {
+            keep[0] = true;
+            keep[n - 1] = true;
+        }
+        keep
+    }
+
+    // 長い配列と深い入れ子を省略する
+    pub fn prune_json(&self, v: &mut Value) {
+        prune_json_recursively(v, self.json_max_items(), self.json_max_depth(), 0);
+    }
+}
+
+fn time_of(event: &ProgressEvent) -> f64 {
+    match event {
+        ProgressEvent::Mip(p) => p.time,
+        ProgressEvent::Barrier(p) => p.time,
+        ProgressEvent::Simplex(p) => p.time,
+    }
+}
+
+// 単体法・バリア法の表にはギャップが無いので、最初と最後の行だけになる
+fn gap_of(event: &ProgressEvent) -> Option<f64> {
+    match event {
+        ProgressEvent::Mip(p) => p.gap,
+        _ => None,
+    }
+}
+
+// JSONの中身を再帰的に探索して、長い配列と深い入れ子をカットする関数
+fn prune_json_recursively(v: &mut Value, max_items: usize, max_depth: usize, depth: usize) {
+    if depth >= max_depth {
+        // これより深い所は要素数だけ残す
+        let summary = match v {
+            Value::Array(arr) if !arr.is_empty() => Some(format!("[... {} items]", arr.len())),
+            Value::Object(map) if !map.is_empty() => Some(format!("{{... {} keys}}", map.len())),
+            _ => None,
+        };
+        if let Some(summary) = summary {
+            *v = json!(summary);
+        }
+        return;
+    }
+    match v {
+        Value::Array(arr) => {
+            if arr.len() > max_items {
+                let original_len = arr.len();
+                arr.truncate(max_items);
+                // 末尾に「省略しました」という情報を追加
+                arr.push(json!(format!(
+                    "... (truncated {} items) ...",
+                    original_len - max_items
+                )));
+            }
+            for item in arr {
+                prune_json_recursively(item, max_items, max_depth, depth + 1);
+            }
+        }
+        Value::Object(map) => {
+            for (_, val) in map {
+                prune_json_recursively(val, max_items, max_depth, depth + 1);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: [&str; 3] = [
+        "    Nodes    |    Current Node    |     Objective Bounds      |     Work",
+        " Expl Unexpl |  Obj  Depth IntInf | Incumbent    BestBd   Gap | It/Node Time",
+        "",
+    ];
+    const ROWS: usize = 30;
+
+    // 30行の分枝限定法の表 (i 行目は i 秒・ギャップ 30-i %)
+    // 10 行目にヒューリスティック解 (H)、20 行目に分枝で見つかった解 (*)
+    fn mip_log() -> Vec<String> {
+        let mut lines: Vec<String> = HEADER.iter().map(|l| l.to_string()).collect();
+        for i in 0..ROWS {
+            let (marker, incumbent) = match i {
+                0..10 => (' ', 1500.0),
+                10 => ('H', 1400.0),
+                11..20 => (' ', 1400.0),
+                20 => ('*', 1300.0),
+                _ => (' ', 1300.0),
+            };
+            lines.push(format!(
+                "{}{:>5} {:>5} 1240.00000   {:>3}   12 {:.5} 1240.00000 {:>5.2}%  20.0 {:>4}s",
+                marker,
+                i * 100,
+                50,
+                i,
+                incumbent,
+                (30 - i) as f64,
+                i
+            ));
+        }
+        lines.push(String::new());
+        lines.push("Cutting planes:".to_string());
+        lines
+    }
+
+    // 残した表の行番号 (表の外の行はすべて残っていること)
+    fn kept_rows(strategy: CompressionStrategy) -> Vec<usize> {
+        let config = CompressionConfig {
+            strategy,
+            ..Default::default()
+        };
+        let lines = mip_log();
+        let mask = config.table_mask(&lines);
+        assert_eq!(mask.len(), lines.len());
+        let offset = HEADER.len();
+        assert!(mask[..offset].iter().all(|k| *k));
+        assert!(mask[offset + ROWS..].iter().all(|k| *k));
+        (0..ROWS).filter(|i| mask[offset + i]).collect()
+    }
+
+    #[test]
+    fn fixed_sampling_keeps_head_and_every_nth_row() {
+        assert_eq!(
+            kept_rows(CompressionStrategy::FixedSampling { head: 3, every: 10 }),
+            vec![0, 1, 2, 9, 10, 19, 20, 29]
+        );
+    }
+
+    #[test]
+    fn incumbent_changes_keep_new_solutions_only() {
+        assert_eq!(
+            kept_rows(CompressionStrategy::IncumbentChanges),
+            vec![0, 10, 20, 29]
+        );
+    }
+
+    #[test]
+    fn time_buckets_keep_the_last_row_of_each_bucket() {
+        assert_eq!(
+            kept_rows(CompressionStrategy::TimeBuckets { seconds: 10.0 }),
+            vec![0, 9, 10, 19, 20, 29]
+        );
+    }
+
+    #[test]
+    fn gap_improvement_keeps_rows_that_close_the_gap() {
+        assert_eq!(
+            kept_rows(CompressionStrategy::GapImprovement {
+                min_improvement: 5.0
+            }),
+            vec![0, 5, 10, 15, 20, 25, 29]
+        );
+    }
+
+    #[test]
+    fn head_tail_keeps_both_ends() {
+        assert_eq!(
+            kept_rows(CompressionStrategy::HeadTail { head: 2, tail: 3 }),
+            vec![0, 1, 10, 20, 27, 28, 29]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_degenerate_settings() {
+        let config = |strategy| CompressionConfig {
+            strategy,
+            ..Default::default()
+        };
+        assert!(
+            config(CompressionStrategy::FixedSampling { head: 5, every: 0 })
+                .validate()
+                .is_err()
+        );
+        for seconds in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(config(CompressionStrategy::TimeBuckets { seconds })
+                .validate()
+                .is_err());
+        }
+        assert!(config(CompressionStrategy::GapImprovement {
+            min_improvement: -0.5
+        })
+        .validate()
+        .is_err());
+        assert!(config(CompressionStrategy::TimeBuckets { seconds: 0.5 })
+            .validate()
+            .is_ok());
+        assert!(CompressionConfig::default().validate().is_ok());
+        assert!(CompressionStrategy::from_name("sometimes").is_err());
+    }
+
+    #[test]
+    fn prunes_long_arrays_and_deep_nesting() {
+        let config = CompressionConfig::default();
+        let mut v = json!({
+            "x": [1, 2, 3, 4, 5],
+            "deep": { "a": { "b": { "c": [1, 2], "d": {} } } },
+        });
+        config.prune_json(&mut v);
+        assert_eq!(
+            v,
+            json!({
+                "x": [1, 2, 3, "... (truncated 2 items) ..."],
+                "deep": { "a": { "b": { "c": "[... 2 items]", "d": {} } } },
+            })
+        );
+    }
+}
@@ -0,0 +1,221 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::settings::backup_path;
+
+// プロジェクト (フォルダ) ごとに保存する環境変数・作業ディレクトリの組
+// アプリのデータディレクトリ内の JSON ファイルに保存する
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnvProfile {
+    // このフォルダ以下のスクリプトで使える
+    pub project: String,
+    pub name: String,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+pub struct EnvProfileStore {
+    path: PathBuf,
+    profiles: Vec<EnvProfile>,
+}
+
+impl EnvProfileStore {
+    // 壊れたファイルでは起動を止めず、.bak に退避して空から始める
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let profiles = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(profiles) => profiles,
+                Err(e) => {
+                    let backup = backup_path(path);
+                    eprintln!(
+                        "環境プロファイル {} を読み込めません: {}\n{} に退避し、空の状態で起動します。",
+                        path.display(),
+                        e,
+                        backup.display()
+                    );
+                    if let Err(e) = std::fs::rename(path, &backup) {
+                        eprintln!("環境プロファイルを退避できません: {}", e);
+                    }
+                    Vec::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(EnvProfileStore {
+            path: path.to_path_buf(),
+            profiles,
+        })
+    }
+
+    fn save(&self) -> Result<(), String> {
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(&self.profiles).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+
+    // project が None ならすべて
+    pub fn list(&self, project: Option<&str>) -> Vec<EnvProfile> {
+        self.profiles
+            .iter()
+            .filter(|p| project.is_none_or(|dir| p.project == dir))
+            .cloned()
+            .collect()
+    }
+
+    // 同じプロジェクト・同じ名前のものは置き換える
+    pub fn upsert(&mut self, profile: EnvProfile) -> Result<(), String> {
+        if profile.name.trim().is_empty() {
+            return Err("プロファイル名を入力してください。".to_string());
+        }
+        if profile.project.trim().is_empty() {
+            return Err("プロジェクトのフォルダを指定してください。".to_string());
+        }
+        match self
+            .profiles
+            .iter_mut()
+            .find(|p| p.project == profile.project && p.name == profile.name)
+        {
+            Some(existing) => *existing = profile,
+            None => self.profiles.push(profile),
+        }
+        self.save()
+    }
+
+    pub fn delete(&mut self, project: &str, name: &str) -> Result<bool, String> {
+        let before = self.profiles.len();
+        self.profiles
+            .retain(|p| !(p.project == project && p.name == name));
+        let removed = self.profiles.len() != before;
+        if removed {
+            self.save()?;
+        }
+        Ok(removed)
+    }
+
+    // スクリプトを含むプロジェクトのうち最も内側のものから、名前の一致するプロファイルを探す
+    pub fn find_for_script(&self, script_path: &str, name: &str) -> Option<&EnvProfile> {
+        let script = Path::new(script_path);
+        self.profiles
+            .iter()
+            .filter(|p| p.name == name && script.starts_with(&p.project))
+            .max_by_key(|p| Path::new(&p.project).components().count())
+    }
+}
+
+// プロファイルの内容を反映する (実行時に直接指定した値を優先)
+// プロファイルの相対パスの作業ディレクトリはプロジェクトのフォルダからの位置
+pub fn apply(
+    profile: &EnvProfile,
+    env: &mut BTreeMap<String, String>,
+    working_dir: &mut Option<String>,
+) {
+    for (key, value) in &profile.env {
+        env.entry(key.clone()).or_insert_with(|| value.clone());
+    }
+    if working_dir.as_deref().is_none_or(|d| d.trim().is_empty()) {
+        *working_dir = profile.working_dir.as_ref().map(|dir| {
+            Path::new(&profile.project)
+                .join(dir)
+                .to_string_lossy()
+                .to_string()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(project: &str, name: &str, working_dir: Option<&str>) -> EnvProfile {
+        EnvProfile {
+            project: project.to_string(),
+            name: name.to_string(),
+            working_dir: working_dir.map(str::to_string),
+            env: BTreeMap::from([("GRB_THREADS".to_string(), project.to_string())]),
+        }
+    }
+
+    fn store(profiles: Vec<EnvProfile>) -> EnvProfileStore {
+        EnvProfileStore {
+            path: PathBuf::new(),
+            profiles,
+        }
+    }
+
+    #[test]
+    fn find_for_script_prefers_the_innermost_project() {
+        let store = store(vec![
+            profile("/work", "dev", None),
+            profile("/work/routing", "dev", None),
+            profile("/work/routing", "prod", None),
+            profile("/work/other", "dev", None),
+        ]);
+        let found = store
+            .find_for_script("/work/routing/main.py", "dev")
+            .unwrap();
+        assert_eq!(found.project, "/work/routing");
+        let found = store
+            .find_for_script("/work/scheduling/main.py", "dev")
+            .unwrap();
+        assert_eq!(found.project, "/work");
+        // パスの途中が一致するだけのフォルダは対象外
+        assert!(store.find_for_script("/workspace/main.py", "dev").is_none());
+        assert!(store.find_for_script("/work/main.py", "test").is_none());
+    }
+
+    #[test]
+    fn apply_keeps_explicit_values() {
+        let mut p = profile("/work", "dev", Some("data"));
+        p.env.insert(
+            "GRB_LICENSE_FILE".to_string(),
+            "/opt/gurobi.lic".to_string(),
+        );
+
+        let mut env = BTreeMap::from([("GRB_THREADS".to_string(), "4".to_string())]);
+        let mut working_dir = None;
+        apply(&p, &mut env, &mut working_dir);
+        assert_eq!(env["GRB_THREADS"], "4");
+        assert_eq!(env["GRB_LICENSE_FILE"], "/opt/gurobi.lic");
+        assert_eq!(
+            working_dir.map(PathBuf::from),
+            Some(Path::new("/work").join("data"))
+        );
+
+        let mut working_dir = Some("/tmp".to_string());
+        apply(&p, &mut env, &mut working_dir);
+        assert_eq!(working_dir.as_deref(), Some("/tmp"));
+
+        // 空欄は未指定として扱う
+        let mut working_dir = Some(" ".to_string());
+        apply(&profile("/work", "dev", None), &mut env, &mut working_dir);
+        assert_eq!(working_dir, None);
+    }
+
+    #[test]
+    fn open_moves_corrupt_file_aside() {
+        let dir = std::env::temp_dir().join(format!("gurobilab-profiles-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("env_profiles.json");
+        std::fs::write(&path, "[{\"project\": ").unwrap();
+
+        let mut store = EnvProfileStore::open(&path).unwrap();
+        assert!(store.list(None).is_empty());
+        assert!(!path.exists());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("env_profiles.json.bak")).unwrap(),
+            "[{\"project\": "
+        );
+
+        store.upsert(profile("/work", "dev", None)).unwrap();
+        assert_eq!(EnvProfileStore::open(&path).unwrap().list(None).len(), 1);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::BTreeMap;
+use std::path::Path;
+
+// Python のラッパーなしでモデルファイル (.mps / .lp など) を gurobi_cl で直接解く
+//
+//   gurobi_cl TimeLimit=60 ResultFile=model.sol [追加の引数] model.mps.gz
+
+pub const DEFAULT_PROGRAM: &str = "gurobi_cl";
+
+// gurobi_cl が読めるモデルファイルの拡張子
+const MODEL_EXTENSIONS: &[&str] = &["mps", "rew", "lp", "rlp", "dua", "dlp", "ilp", "opb"];
+// 圧縮ファイルはそのまま渡せる
+const COMPRESSED_EXTENSIONS: &[&str] = &["gz", "bz2", "zip", "7z"];
+
+fn lower_ext(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|e| e.to_string_lossy().to_ascii_lowercase())
+}
+
+// 圧縮拡張子を除いたファイル名 (model.mps.gz -> model.mps)
+fn strip_compression(path: &Path) -> &Path {
+    match lower_ext(path) {
+        Some(ext) if COMPRESSED_EXTENSIONS.contains(&ext.as_str()) => {
+            path.file_stem().map(Path::new).unwrap_or(path)
+        }
+        _ => path,
+    }
+}
+
+pub fn is_model_file(path: &str) -> bool {
+    lower_ext(strip_compression(Path::new(path)))
+        .is_some_and(|ext| MODEL_EXTENSIONS.contains(&ext.as_str()))
+}
+
+// モデル名に拡張子を付けたファイル名 (model.mps.gz -> model.<ext>)
+fn output_file(model_path: &str, ext: &str) -> String {
+    let name = strip_compression(Path::new(model_path));
+    let stem = name
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "model".to_string());
+    format!("{}.{}", stem, ext)
+}
+
+// 既定の解ファイル名 (model.mps.gz -> model.sol)
+pub fn default_result_file(model_path: &str) -> String {
+    output_file(model_path, "sol")
+}
+
+// IIS の出力先 (ResultFile に .ilp を指定すると、実行不可能なときに IIS が書き出される)
+pub fn default_iis_file(model_path: &str) -> String {
+    output_file(model_path, "ilp")
+}
+
+// "Name=value" の並び + 追加の引数 + モデルファイル
+pub fn build_args(
+    params: &BTreeMap<String, String>,
+    extra_args: Vec<String>,
+    model_path: String,
+) -> Result<Vec<String>, String> {
+    if !is_model_file(&model_path) {
+        return Err(format!(
+            "gurobi_cl で読めるモデルファイルではありません: {}\n(対応形式: {})",
+            model_path,
+            MODEL_EXTENSIONS.join(", ")
+        ));
+    }
+    let mut args: Vec<String> = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect();
+    args.extend(extra_args);
+    args.push(model_path);
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_model_files() {
+        for path in [
+            "model.lp",
+            "model.mps",
+            "dir/model.MPS",
+            "model.mps.gz",
+            "model.lp.bz2",
+            "C:\\models\\big.rew.zip",
+            "model.ilp",
+        ] {
+            assert!(is_model_file(path), "{}", path);
+        }
+        for path in [
+            "model.py",
+            "model.gz",
+            "model.sol",
+            "model",
+            "lp",
+            "model.mps.txt",
+        ] {
+            assert!(!is_model_file(path), "{}", path);
+        }
+    }
+
+    #[test]
+    fn output_files_drop_compression() {
+        assert_eq!(default_result_file("/data/model.mps.gz"), "model.sol");
+        assert_eq!(default_result_file("model.lp"), "model.sol");
+        assert_eq!(default_iis_file("/data/model.v2.mps.bz2"), "model.v2.ilp");
+    }
+
+    #[test]
+    fn parameters_come_before_the_model() {
+        let params = BTreeMap::from([
+            ("TimeLimit".to_string(), "60".to_string()),
+            ("MIPGap".to_string(), "0.01".to_string()),
+        ]);
+        let args = build_args(
+            &params,
+            vec!["Threads=4".to_string()],
+            "model.mps.gz".to_string(),
+        )
+        .unwrap();
+        assert_eq!(
+            args,
+            vec!["MIPGap=0.01", "TimeLimit=60", "Threads=4", "model.mps.gz"]
+        );
+        assert_eq!(
+            build_args(&BTreeMap::new(), Vec::new(), "m.lp".to_string()).unwrap(),
+            vec!["m.lp"]
+        );
+        assert!(build_args(&params, Vec::new(), "model.py".to_string()).is_err());
+    }
+}
@@ -0,0 +1,565 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+// スクリプトを編集せずに Gurobi のパラメータを渡す
+// 作業ディレクトリに gurobi.env を一時的に書き、実行が終わったら元に戻す
+// (gurobipy は Env の作成時にカレントディレクトリの gurobi.env を読む)
+
+pub const ENV_FILE_NAME: &str = "gurobi.env";
+
+const BLOCK_BEGIN: &str = "# --- GurobiLab: begin (この範囲は実行終了時に削除されます) ---";
+const BLOCK_END: &str = "# --- GurobiLab: end ---";
+
+// Gurobi では 1e100 以上が無限大として扱われる
+const GRB_INFINITY: f64 = 1e100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ParamKind {
+    Int { min: i64, max: i64 },
+    Double { min: f64, max: f64 },
+    String,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamSpec {
+    pub name: &'static str,
+    pub kind: ParamKind,
+    pub default: &'static str,
+}
+
+const fn int(name: &'static str, min: i64, max: i64, default: &'static str) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind: ParamKind::Int { min, max },
+        default,
+    }
+}
+
+const fn double(name: &'static str, min: f64, max: f64, default: &'static str) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind: ParamKind::Double { min, max },
+        default,
+    }
+}
+
+const fn string(name: &'static str) -> ParamSpec {
+    ParamSpec {
+        name,
+        kind: ParamKind::String,
+        default: "",
+    }
+}
+
+const MAX_INT: i64 = 2_000_000_000;
+const INF: f64 = f64::INFINITY;
+
+// エディタで扱うパラメータ (よく使うもののみ)
+pub const PARAMS: &[ParamSpec] = &[
+    // 終了条件
+    double("TimeLimit", 0.0, INF, "inf"),
+    double("WorkLimit", 0.0, INF, "inf"),
+    double("MemLimit", 0.0, INF, "inf"),
+    double("SoftMemLimit", 0.0, INF, "inf"),
+    double("NodeLimit", 0.0, INF, "inf"),
+    double("IterationLimit", 0.0, INF, "inf"),
+    int("SolutionLimit", 1, MAX_INT, "2000000000"),
+    double("MIPGap", 0.0, INF, "1e-4"),
+    double("MIPGapAbs", 0.0, INF, "1e-10"),
+    double("BestObjStop", -INF, INF, "-inf"),
+    double("BestBdStop", -INF, INF, "inf"),
+    double("Cutoff", -INF, INF, "inf"),
+    // 許容誤差
+    double("FeasibilityTol", 1e-9, 1e-2, "1e-6"),
+    double("OptimalityTol", 1e-9, 1e-2, "1e-6"),
+    double("IntFeasTol", 1e-9, 1e-1, "1e-5"),
+    double("MarkowitzTol", 1e-4, 0.999, "0.0078125"),
+    double("BarConvTol", 0.0, 1.0, "1e-8"),
+    // アルゴリズム
+    int("Method", -1, 5, "-1"),
+    int("Crossover", -1, 4, "-1"),
+    int("NumericFocus", 0, 3, "0"),
+    int("ScaleFlag", -1, 3, "-1"),
+    int("NonConvex", -1, 2, "-1"),
+    // MIP
+    int("MIPFocus", 0, 3, "0"),
+    int("Cuts", -1, 3, "-1"),
+    double("Heuristics", 0.0, 1.0, "0.05"),
+    int("RINS", -1, MAX_INT, "-1"),
+    int("Symmetry", -1, 2, "-1"),
+    int("VarBranch", -1, 3, "-1"),
+    int("BranchDir", -1, 1, "0"),
+    double("ImproveStartTime", 0.0, INF, "inf"),
+    double("ImproveStartGap", 0.0, INF, "0"),
+    double("NodefileStart", 0.0, INF, "inf"),
+    // Presolve
+    int("Presolve", -1, 2, "-1"),
+    int("Aggregate", 0, 2, "1"),
+    int("DualReductions", 0, 1, "1"),
+    int("InfUnbdInfo", 0, 1, "0"),
+    // 解プール
+    int("PoolSolutions", 1, MAX_INT, "10"),
+    int("PoolSearchMode", 0, 2, "0"),
+    double("PoolGap", 0.0, INF, "inf"),
+    // その他
+    int("Threads", 0, 1024, "0"),
+    int("Seed", 0, MAX_INT, "0"),
+    int("OutputFlag", 0, 1, "1"),
+    int("LogToConsole", 0, 1, "1"),
+    int("DisplayInterval", 1, MAX_INT, "5"),
+    string("LogFile"),
+    string("ResultFile"),
+];
+
+// Gurobi のパラメータ名は大文字小文字を区別しない
+pub fn find_spec(name: &str) -> Option<&'static ParamSpec> {
+    PARAMS
+        .iter()
+        .find(|p| p.name.eq_ignore_ascii_case(name.trim()))
+}
+
+// 入力の問題点 (フォーム上の該当欄に表示する)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamIssue {
+    pub name: String,
+    pub message: String,
+}
+
+fn parse_double(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => match s.trim().to_ascii_lowercase().as_str() {
+            "inf" | "infinity" | "+inf" => Some(INF),
+            "-inf" | "-infinity" => Some(-INF),
+            t => t.parse().ok(),
+        },
+        _ => None,
+    }
+}
+
+fn parse_int(value: &Value) -> Option<i64> {
+    match value {
+        Value::Number(n) => n
+            .as_i64()
+            .or_else(|| n.as_f64().filter(|f| f.fract() == 0.0).map(|f| f as i64)),
+        Value::String(s) => s.trim().parse().ok(),
+        Value::Bool(b) => Some(*b as i64),
+        _ => None,
+    }
+}
+
+fn format_double(v: f64) -> String {
+    if v >= GRB_INFINITY {
+        "1e100".to_string()
+    } else if v <= -GRB_INFINITY {
+        "-1e100".to_string()
+    } else {
+        v.to_string()
+    }
+}
+
+fn check_value(spec: &ParamSpec, value: &Value) -> Result<String, String> {
+    match spec.kind {
+        ParamKind::Int { min, max } => {
+            let v = parse_int(value).ok_or("整数で指定してください")?;
+            if v < min || v > max {
+                return Err(format!("{} 以上 {} 以下で指定してください", min, max));
+            }
+            Ok(v.to_string())
+        }
+        ParamKind::Double { min, max } => {
+            let v = parse_double(value).ok_or("数値で指定してください")?;
+            if v.is_nan() || v < min || v > max {
+                return Err(format!(
+                    "{} 以上 {} 以下で指定してください",
+                    format_double(min),
+                    format_double(max)
+                ));
+            }
+            Ok(format_double(v))
+        }
+        ParamKind::String => {
+            let v = match value {
+                Value::String(s) => s.trim().to_string(),
+                Value::Number(n) => n.to_string(),
+                _ => return Err("文字列で指定してください".to_string()),
+            };
+            // gurobi.env は1行1パラメータのため改行は不可
+            if v.contains(['\n', '\r']) {
+                return Err("改行を含めることはできません".to_string());
+            }
+            Ok(v)
+        }
+    }
+}
+
+// パラメータ名を正式な表記に揃え、値を gurobi.env に書く文字列にする
+pub fn validate(
+    params: &BTreeMap<String, Value>,
+) -> Result<BTreeMap<String, String>, Vec<ParamIssue>> {
+    let mut out = BTreeMap::new();
+    let mut issues = Vec::new();
+    for (name, value) in params {
+        // 空欄は「指定なし」
+        if value.is_null() || value.as_str().is_some_and(|s| s.trim().is_empty()) {
+            continue;
+        }
+        let Some(spec) = find_spec(name) else {
+            issues.push(ParamIssue {
+                name: name.clone(),
+                message: "不明なパラメータです".to_string(),
+            });
+            continue;
+        };
+        match check_value(spec, value) {
+            Ok(v) => {
+                if out.insert(spec.name.to_string(), v).is_some() {
+                    issues.push(ParamIssue {
+                        name: name.clone(),
+                        message: "同じパラメータが複数回指定されています".to_string(),
+                    });
+                }
+            }
+            Err(message) => issues.push(ParamIssue {
+                name: spec.name.to_string(),
+                message,
+            }),
+        }
+    }
+    if issues.is_empty() {
+        Ok(out)
+    } else {
+        Err(issues)
+    }
+}
+
+pub fn issues_to_string(issues: &[ParamIssue]) -> String {
+    issues
+        .iter()
+        .map(|i| format!("{}: {}", i.name, i.message))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// 指定したパラメータが実際に適用されたか (ログの "Set parameter" 行と照合した結果)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ParamCheckStatus {
+    Applied,
+    // ログに現れなかった (Env を作る前にスクリプトが終了した、LogToConsole=0 など)
+    Missing,
+    // スクリプト側の setParam などで別の値に上書きされた
+    Overridden,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParamCheck {
+    pub name: String,
+    pub requested: String,
+    pub applied: Option<String>,
+    pub status: ParamCheckStatus,
+}
+
+fn same_value(a: &str, b: &str) -> bool {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(x), Ok(y)) => x == y || (x >= GRB_INFINITY && y >= GRB_INFINITY),
+        _ => a == b,
+    }
+}
+
+pub fn check_applied(
+    requested: &BTreeMap<String, String>,
+    applied: &BTreeMap<String, String>,
+) -> Vec<ParamCheck> {
+    requested
+        .iter()
+        .map(|(name, value)| {
+            let actual = applied
+                .iter()
+                .find(|(k, _)| k.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.clone());
+            let status = match &actual {
+                None => ParamCheckStatus::Missing,
+                Some(a) if same_value(a, value) => ParamCheckStatus::Applied,
+                Some(_) => ParamCheckStatus::Overridden,
+            };
+            ParamCheck {
+                name: name.clone(),
+                requested: value.clone(),
+                applied: actual,
+                status,
+            }
+        })
+        .collect()
+}
+
+// 現在 gurobi.env を書き換えているディレクトリ (同じ場所での同時実行を防ぐ)
+static DIRS_IN_USE: Mutex<BTreeSet<PathBuf>> = Mutex::new(BTreeSet::new());
+
+fn release_dir(dir: &Path) {
+    if let Ok(mut in_use) = DIRS_IN_USE.lock() {
+        in_use.remove(dir);
+    }
+}
+
+// 前回異常終了した場合に残ったブロックを取り除く
+fn strip_block(text: &str) -> String {
+    if !text.contains(BLOCK_BEGIN) {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut in_block = false;
+    for line in text.lines() {
+        if line.trim() == BLOCK_BEGIN {
+            in_block = true;
+        } else if line.trim() == BLOCK_END {
+            in_block = false;
+        } else if !in_block {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+// 書き換えた gurobi.env を drop 時に元に戻す
+pub struct EnvFileGuard {
+    dir: PathBuf,
+    path: PathBuf,
+    original: Option<String>,
+}
+
+impl EnvFileGuard {
+    // params が空なら何もしない
+    pub fn write(dir: &Path, params: &BTreeMap<String, String>) -> Result<Option<Self>, String> {
+        if params.is_empty() {
+            return Ok(None);
+        }
+        let dir = std::path::absolute(dir).unwrap_or_else(|_| dir.to_path_buf());
+        if !DIRS_IN_USE
+            .lock()
+            .map_err(|e| e.to_string())?
+            .insert(dir.clone())
+        {
+            return Err(format!(
+                "{} ではパラメータを指定した別の実行が進行中です。",
+                dir.display()
+            ));
+        }
+        let path = dir.join(ENV_FILE_NAME);
+        match Self::write_file(&path, params) {
+            Ok(original) => Ok(Some(EnvFileGuard {
+                dir,
+                path,
+                original,
+            })),
+            Err(e) => {
+                release_dir(&dir);
+                Err(e)
+            }
+        }
+    }
+
+    // 既存の gurobi.env は残し、その後ろに追記する (後に書いた値が優先される)
+    // 戻り値は元の内容 (ファイルが無かった場合は None)
+    fn write_file(
+        path: &Path,
+        params: &BTreeMap<String, String>,
+    ) -> Result<Option<String>, String> {
+        let original = match std::fs::read_to_string(path) {
+            Ok(text) => Some(strip_block(&text)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(format!("{} を読み込めません: {}", path.display(), e)),
+        };
+        let mut text = original.clone().unwrap_or_default();
+        if !text.is_empty() && !text.ends_with('\n') {
+            text.push('\n');
+        }
+        text.push_str(BLOCK_BEGIN);
+        text.push('\n');
+        for (name, value) in params {
+            text.push_str(&format!("{} {}\n", name, value));
+        }
+        text.push_str(BLOCK_END);
+        text.push('\n');
+        std::fs::write(path, text)
+            .map_err(|e| format!("{} に書き込めません: {}", path.display(), e))?;
+        Ok(original)
+    }
+}
+
+impl Drop for EnvFileGuard {
+    fn drop(&mut self) {
+        let _ = match &self.original {
+            Some(text) => std::fs::write(&self.path, text),
+            None => std::fs::remove_file(&self.path),
+        };
+        release_dir(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn params(value: Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn issue_names(value: Value) -> Vec<String> {
+        validate(&params(value))
+            .unwrap_err()
+            .into_iter()
+            .map(|i| i.name)
+            .collect()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("gurobilab-params-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn names_are_case_insensitive() {
+        let out = validate(&params(
+            json!({"mipgap": "0.01", " threads ": 4, "LOGFILE": "a.log"}),
+        ))
+        .unwrap();
+        assert_eq!(out["MIPGap"], "0.01");
+        assert_eq!(out["Threads"], "4");
+        assert_eq!(out["LogFile"], "a.log");
+        assert_eq!(find_spec("timelimit").unwrap().name, "TimeLimit");
+        assert!(find_spec("TimeLimits").is_none());
+
+        // 表記違いの同じパラメータは重複として扱う
+        assert_eq!(
+            issue_names(json!({"TimeLimit": 1, "timelimit": 2})),
+            vec!["timelimit"]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_names_and_bad_values() {
+        assert_eq!(issue_names(json!({"NoSuchParam": 1})), vec!["NoSuchParam"]);
+        // 整数パラメータの範囲外・小数
+        assert_eq!(issue_names(json!({"MIPFocus": 4})), vec!["MIPFocus"]);
+        assert_eq!(issue_names(json!({"Method": -2})), vec!["Method"]);
+        assert_eq!(issue_names(json!({"Threads": 1.5})), vec!["Threads"]);
+        // 実数パラメータの範囲外・数値でない値
+        assert_eq!(
+            issue_names(json!({"FeasibilityTol": 0.1})),
+            vec!["FeasibilityTol"]
+        );
+        assert_eq!(issue_names(json!({"MIPGap": -0.1})), vec!["MIPGap"]);
+        assert_eq!(issue_names(json!({"MIPGap": "nan"})), vec!["MIPGap"]);
+        assert_eq!(issue_names(json!({"MIPGap": "small"})), vec!["MIPGap"]);
+        assert_eq!(issue_names(json!({"LogFile": "a\nb"})), vec!["LogFile"]);
+
+        // 空欄は指定なし
+        assert!(validate(&params(json!({"TimeLimit": "", "MIPGap": null})))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn infinities_are_written_as_gurobi_infinity() {
+        let out = validate(&params(json!({
+            "TimeLimit": "inf",
+            "BestObjStop": "-inf",
+            "Cutoff": "+Infinity",
+            "MIPGapAbs": 1e300,
+        })))
+        .unwrap();
+        assert_eq!(out["TimeLimit"], "1e100");
+        assert_eq!(out["BestObjStop"], "-1e100");
+        assert_eq!(out["Cutoff"], "1e100");
+        assert_eq!(out["MIPGapAbs"], "1e100");
+        assert_eq!(issue_names(json!({"TimeLimit": "-inf"})), vec!["TimeLimit"]);
+    }
+
+    #[test]
+    fn check_applied_compares_values() {
+        let requested = BTreeMap::from([
+            ("MIPGap".to_string(), "0.01".to_string()),
+            ("TimeLimit".to_string(), "1e100".to_string()),
+            ("Threads".to_string(), "4".to_string()),
+            ("Seed".to_string(), "1".to_string()),
+        ]);
+        let applied = BTreeMap::from([
+            ("MIPGap".to_string(), "1e-02".to_string()),
+            ("timelimit".to_string(), "inf".to_string()),
+            ("Threads".to_string(), "8".to_string()),
+        ]);
+        let statuses: Vec<_> = check_applied(&requested, &applied)
+            .into_iter()
+            .map(|c| (c.name, c.status))
+            .collect();
+        assert_eq!(
+            statuses,
+            vec![
+                ("MIPGap".to_string(), ParamCheckStatus::Applied),
+                ("Seed".to_string(), ParamCheckStatus::Missing),
+                ("Threads".to_string(), ParamCheckStatus::Overridden),
+                ("TimeLimit".to_string(), ParamCheckStatus::Applied),
+            ]
+        );
+    }
+
+    #[test]
+    fn env_file_guard_restores_existing_file() {
+        let dir = temp_dir("restore");
+        let path = dir.join(ENV_FILE_NAME);
+        let original = "Threads 2\n";
+        // 前回異常終了したときのブロックが残っている
+        std::fs::write(
+            &path,
+            format!("{}{}\nMIPGap 0.5\n{}\n", original, BLOCK_BEGIN, BLOCK_END),
+        )
+        .unwrap();
+
+        let params = BTreeMap::from([("MIPGap".to_string(), "0.01".to_string())]);
+        let guard = EnvFileGuard::write(&dir, &params).unwrap().unwrap();
+        let written = std::fs::read_to_string(&path).unwrap();
+        assert!(written.starts_with(original));
+        assert!(written.contains("MIPGap 0.01\n"));
+        assert!(!written.contains("MIPGap 0.5"));
+
+        // 同じディレクトリでの同時実行は拒否する
+        assert!(EnvFileGuard::write(&dir, &params).is_err());
+
+        drop(guard);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        // 解放後は再び書ける
+        drop(EnvFileGuard::write(&dir, &params).unwrap());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), original);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn env_file_guard_removes_created_file() {
+        let dir = temp_dir("create");
+        let path = dir.join(ENV_FILE_NAME);
+        assert!(EnvFileGuard::write(&dir, &BTreeMap::new())
+            .unwrap()
+            .is_none());
+        assert!(!path.exists());
+
+        let params = BTreeMap::from([("TimeLimit".to_string(), "60".to_string())]);
+        let guard = EnvFileGuard::write(&dir, &params).unwrap();
+        assert!(path.exists());
+        drop(guard);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,741 @@
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use std::process::Child;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::compression::{CompressionConfig, CompressionStrategy};
+use crate::gurobi_params::ParamCheckStatus;
+use crate::history::HistoryStore;
+use crate::iis;
+use crate::llm::{self, ProviderConfig, ProviderKind};
+use crate::model_stats;
+use crate::numerics::{self, Severity};
+use crate::process::{self, StopSignal};
+use crate::prompt::PromptOptions;
+use crate::report;
+use crate::runner::{self, RunMode, RunOutcome, RunRequest, RunSink};
+use crate::secrets::SecretStore;
+use crate::settings::DEFAULT_MODEL;
+use crate::sweep::{self, SweepControl, SweepDefinition, SweepJobStatus, SweepObserver, SweepRow};
+use crate::workspace::Workspace;
+
+// GUI を起動せずに「実行 → サマリ/結果JSON → AI解析 → レポート保存」を行うモード
+// (SSH 先の計算サーバーや夜間バッチ向け)
+//
+// 例:
+//   gurobi-optimizer-desktop --headless --script model.py --args "--n 100" \
+//       --summary-out summary.json --analyze --report-out report.md
+
+const USAGE: &str = "\
+使い方: gurobi-optimizer-desktop --headless --script <PATH> [オプション]
+
+実行:
+  --script <PATH>          実行するスクリプト (必須)
+  --workspace <PATH>       gurobilab.toml (またはそれを含むフォルダ) のワークスペースを使う
+  --run <NAME>             ワークスペースで宣言したスクリプト名 (--script の代わり)
+  --preset <NAME>          ワークスペースのパラメータのプリセット (省略時はスクリプトの既定)
+  --model-file <PATH>      スクリプトの代わりにモデルファイル (.mps/.lp など) を gurobi_cl で解く
+  --result-file <PATH>     gurobi_cl の解ファイル (既定: <モデル名>.sol)
+  --args <ARGS>            スクリプトに渡す引数 (シェル風に分割、クォート可)
+  -- <ARG>...              以降をそのままスクリプトへの引数として渡す (--args の代わり)
+  --prefix <CMD>           コマンドプレフィックス (既定: \"uv run python -u\"、--model-file では gurobi_cl)
+  --cwd <DIR>              作業ディレクトリ (既定: スクリプトのあるフォルダ)
+  --env <KEY=VALUE>        環境変数を追加する (複数指定可)
+  --param <NAME=VALUE>     Gurobi パラメータを gurobi.env 経由で渡す (複数指定可)
+
+出力:
+  --summary-out <PATH>     サマリ (状態・目的関数値など) をJSONで保存
+  --results-out <PATH>     結果JSONブロック・パラメータの照合・数値的な問題をJSONで保存
+  --history-db <PATH>      実行履歴を指定のSQLiteファイルに記録
+  --iis-out <PATH>         実行不可能だった場合、作業ディレクトリに書き出された IIS (.ilp) の内容をJSONで保存
+
+AI解析:
+  --analyze                実行後にAI解析を行う
+  --provider <NAME>        gemini (既定) | openai-compatible | offline
+                           (offline、または gemini で APIキーが無い場合はルールに基づくレポート)
+  --model <NAME>           モデル名 (既定: gemini-2.5-flash)
+  --base-url <URL>         APIのベースURL
+  --api-key <KEY>          APIキー (非推奨: 他のユーザーからコマンドラインが見えるため、
+                           環境変数 GUROBILAB_API_KEY か OS のキーチェーンを推奨。省略時はその順に探す)
+  --focus <TEXT>           重点的に考察してほしい点
+  --system-prompt <TEXT>   システム指示
+  --token-budget <N>       プロンプトのトークン数の上限 (既定はモデルごと)
+  --compression <NAME|PATH>
+                           進捗表の間引き方: fixed (既定) | incumbent | time | gap | head-tail、
+                           または CompressionConfig のJSONファイル
+  --report-out <PATH>      解析レポートの保存先 (省略時は標準出力)
+
+モデル統計:
+  --model-stats <PATH>     .lp / .mps (gzip 可) を解かずに読み、規模と係数の範囲をJSONで出力する
+
+スイープ:
+  --sweep <PATH>           スイープ定義 (JSON) に従って複数回実行する (--script の代わり)
+  --sweep-out <PATH>       結果表をCSVで保存 (省略時は標準出力)
+
+終了コードはスクリプトの終了コード (起動や解析に失敗した場合は 2、
+スイープでは全件成功なら 0、失敗や中断を含めば 1)。";
+
+const DEFAULT_PREFIX: &str = "uv run python -u";
+const API_KEY_ENV: &str = "GUROBILAB_API_KEY";
+
+#[derive(Debug, Default)]
+struct HeadlessOptions {
+    request: RunRequest,
+    summary_out: Option<PathBuf>,
+    results_out: Option<PathBuf>,
+    history_db: Option<PathBuf>,
+    iis_out: Option<PathBuf>,
+    analyze: bool,
+    provider: ProviderKind,
+    model: Option<String>,
+    base_url: Option<String>,
+    api_key: Option<String>,
+    focus: String,
+    system_prompt: String,
+    token_budget: Option<usize>,
+    compression: CompressionConfig,
+    report_out: Option<PathBuf>,
+    sweep: Option<PathBuf>,
+    sweep_out: Option<PathBuf>,
+    model_stats: Option<String>,
+    workspace: Option<PathBuf>,
+    workspace_script: Option<String>,
+    preset: Option<String>,
+}
+
+// 名前なら既定値で、.json ならファイルから読む
+fn parse_compression(value: &str) -> Result<CompressionConfig, String> {
+    let config = if value.ends_with(".json") {
+        let text =
+            std::fs::read_to_string(value).map_err(|e| format!("{} を読めません: {}", value, e))?;
+        serde_json::from_str(&text).map_err(|e| format!("{} の形式が不正です: {}", value, e))?
+    } else {
+        CompressionConfig {
+            strategy: CompressionStrategy::from_name(value)?,
+            ..Default::default()
+        }
+    };
+    config.validate()?;
+    Ok(config)
+}
+
+// main.rs から呼ぶ判定用
+pub fn is_headless(args: &[String]) -> bool {
+    args.iter().any(|a| a == "--headless")
+}
+
+fn parse_args(args: &[String]) -> Result<HeadlessOptions, String> {
+    let mut opts = HeadlessOptions::default();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let mut value = || {
+            iter.next()
+                .cloned()
+                .ok_or_else(|| format!("{} には値が必要です", arg))
+        };
+        match arg.as_str() {
+            "--headless" => {}
+            // 以降はすべてスクリプトへの引数としてそのまま渡す
+            "--" => {
+                opts.request.args = Some(iter.by_ref().cloned().collect());
+                break;
+            }
+            "--script" => opts.request.script_path = value()?,
+            "--model-file" => {
+                opts.request.script_path = value()?;
+                opts.request.mode = RunMode::GurobiCl;
+            }
+            "--result-file" => opts.request.result_file = Some(value()?),
+            "--args" => opts.request.args_str = value()?,
+            "--prefix" => opts.request.command_prefix = value()?,
+            "--cwd" => opts.request.working_dir = Some(value()?),
+            "--env" => {
+                let pair = value()?;
+                let (key, val) = pair.split_once('=').ok_or_else(|| {
+                    format!("--env は KEY=VALUE の形で指定してください: {}", pair)
+                })?;
+                opts.request.env.insert(key.to_string(), val.to_string());
+            }
+            "--param" => {
+                let pair = value()?;
+                let (name, val) = pair.split_once('=').ok_or_else(|| {
+                    format!("--param は NAME=VALUE の形で指定してください: {}", pair)
+                })?;
+                opts.request
+                    .gurobi_params
+                    .insert(name.to_string(), Value::String(val.to_string()));
+            }
+            "--summary-out" => opts.summary_out = Some(value()?.into()),
+            "--results-out" => opts.results_out = Some(value()?.into()),
+            "--history-db" => opts.history_db = Some(value()?.into()),
+            "--iis-out" => opts.iis_out = Some(value()?.into()),
+            "--analyze" => opts.analyze = true,
+            "--provider" => {
+                opts.provider = match value()?.as_str() {
+                    "gemini" => ProviderKind::Gemini,
+                    "openai-compatible" | "openai" => ProviderKind::OpenAiCompatible,
+                    "offline" => ProviderKind::Offline,
+                    other => return Err(format!("不明なプロバイダです: {}", other)),
+                }
+            }
+            "--model" => opts.model = Some(value()?),
+            "--base-url" => opts.base_url = Some(value()?),
+            "--api-key" => {
+                // コマンドラインは同じマシンの他のユーザーからも見える
+                eprintln!(
+                    "警告: --api-key のキーはプロセスの一覧から他のユーザーにも見えます。環境変数 {} か OS のキーチェーンを使ってください。",
+                    API_KEY_ENV
+                );
+                opts.api_key = Some(value()?);
+            }
+            "--focus" => opts.focus = value()?,
+            "--system-prompt" => opts.system_prompt = value()?,
+            "--token-budget" => {
+                let v = value()?;
+                opts.token_budget = Some(
+                    v.parse()
+                        .map_err(|_| format!("--token-budget の値が不正です: {}", v))?,
+                );
+            }
+            "--compression" => opts.compression = parse_compression(&value()?)?,
+            "--report-out" => opts.report_out = Some(value()?.into()),
+            "--sweep" => opts.sweep = Some(value()?.into()),
+            "--sweep-out" => opts.sweep_out = Some(value()?.into()),
+            "--model-stats" => opts.model_stats = Some(value()?),
+            "--workspace" => opts.workspace = Some(value()?.into()),
+            "--run" => opts.workspace_script = Some(value()?),
+            "--preset" => opts.preset = Some(value()?),
+            other => return Err(format!("不明なオプションです: {}", other)),
+        }
+    }
+
+    if let Some(path) = opts.workspace.clone() {
+        apply_workspace(&mut opts, &path)?;
+    } else if opts.workspace_script.is_some() || opts.preset.is_some() {
+        return Err("--run と --preset には --workspace が必要です".to_string());
+    }
+    if opts.request.script_path.is_empty() && opts.sweep.is_none() && opts.model_stats.is_none() {
+        return Err("--script または --model-file を指定してください".to_string());
+    }
+    if opts.request.command_prefix.is_empty() && opts.request.mode == RunMode::Script {
+        opts.request.command_prefix = DEFAULT_PREFIX.to_string();
+    }
+    Ok(opts)
+}
+
+// ワークスペースのスクリプトを土台に、コマンドラインで指定したものを上書き・追加する
+fn apply_workspace(opts: &mut HeadlessOptions, path: &Path) -> Result<(), String> {
+    let workspace = Workspace::open(path)?;
+    if opts.history_db.is_none() {
+        opts.history_db = Some(workspace.history_path());
+    }
+    if opts.system_prompt.is_empty() {
+        opts.system_prompt = workspace.file.system_prompt.clone().unwrap_or_default();
+    }
+    let Some(script) = &opts.workspace_script else {
+        if opts.preset.is_some() {
+            return Err("--preset には --run が必要です".to_string());
+        }
+        return Ok(());
+    };
+    let cli = std::mem::take(&mut opts.request);
+    let mut request = workspace.run_request(script, opts.preset.as_deref(), DEFAULT_PREFIX)?;
+    if !cli.args_str.is_empty() || cli.args.is_some() {
+        request.args_str = cli.args_str;
+        request.args = cli.args;
+    }
+    if !cli.command_prefix.is_empty() {
+        request.command_prefix = cli.command_prefix;
+    }
+    if cli.working_dir.is_some() {
+        request.working_dir = cli.working_dir;
+    }
+    if cli.result_file.is_some() {
+        request.result_file = cli.result_file;
+    }
+    request.env.extend(cli.env);
+    request.gurobi_params.extend(cli.gurobi_params);
+    opts.request = request;
+    Ok(())
+}
+
+// 標準出力・標準エラーにそのまま流す
+struct ConsoleSink;
+
+impl RunSink for ConsoleSink {
+    fn on_stdout(&self, line: &str) {
+        println!("{}", line);
+    }
+
+    fn on_stderr(&self, line: &str) {
+        eprintln!("{}", line);
+    }
+}
+
+fn write_json(path: &PathBuf, value: &impl serde::Serialize) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(path, text).map_err(|e| format!("{} に書き込めません: {}", path.display(), e))
+}
+
+// Ctrl+C を受けたときに送る段階の案内
+fn stop_message(signal: StopSignal) -> &'static str {
+    match signal {
+        StopSignal::Interrupt => {
+            "中断要求を受け付けました。Gurobi の終了を待っています... (もう一度 Ctrl+C で終了シグナルを送ります)"
+        }
+        StopSignal::Terminate => "終了シグナルを送りました。(もう一度 Ctrl+C で強制終了します)",
+        StopSignal::Kill => "強制終了します。",
+    }
+}
+
+// 子プロセスの終了を待つ
+// Ctrl+C はプロセスグループへ転送し、押されるたびに cancel_optimization と同じ順で強いシグナルに進める
+async fn wait_child(child: &mut Child) -> Result<std::process::ExitStatus, String> {
+    let pid = child.id();
+    let mut stages = process::STOP_SEQUENCE.into_iter();
+    // 待っている間に押された Ctrl+C を取りこぼさないよう、ループの外で作っておく
+    let mut ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+    loop {
+        if let Some(status) = child.try_wait().map_err(|e| e.to_string())? {
+            return Ok(status);
+        }
+        tokio::select! {
+            _ = &mut ctrl_c => {
+                ctrl_c.set(tokio::signal::ctrl_c());
+                if let Some(signal) = stages.next() {
+                    eprintln!("{}", stop_message(signal));
+                    process::signal_process_tree(pid, signal)?;
+                }
+            }
+            _ = tokio::time::sleep(Duration::from_millis(100)) => {}
+        }
+    }
+}
+
+async fn analyze(opts: &HeadlessOptions, outcome: &RunOutcome) -> Result<(String, String), String> {
+    let model = opts
+        .model
+        .clone()
+        .unwrap_or_else(|| DEFAULT_MODEL.to_string());
+    // --api-key、環境変数、OS のキーチェーンの順に探す
+    let api_key = match opts
+        .api_key
+        .clone()
+        .or_else(|| std::env::var(API_KEY_ENV).ok())
+    {
+        Some(key) => key,
+        None => SecretStore::keyring_only()
+            .api_key(opts.provider)
+            .unwrap_or_default(),
+    };
+
+    let config = ProviderConfig {
+        kind: opts.provider,
+        base_url: opts.base_url.clone(),
+        model: model.clone(),
+        api_key,
+    };
+
+    let log = crate::clean_gurobi_log(&outcome.stdout);
+    let iis = outcome.iis_file.as_ref().map(|f| iis::parse(&f.content));
+    if config.is_offline() {
+        if opts.provider != ProviderKind::Offline {
+            eprintln!("APIキーが無いため、オフラインのレポートを作成します。");
+        }
+        let content = report::offline_report(&log, iis.as_ref());
+        return Ok((report::OFFLINE_MODEL.to_string(), content));
+    }
+    let provider = llm::create_provider(&config)?;
+    let prompt = crate::build_prompt_string(
+        &log,
+        &opts.focus,
+        &opts.system_prompt,
+        iis.as_ref(),
+        &PromptOptions {
+            model: model.clone(),
+            token_budget: opts.token_budget,
+            compression: opts.compression.clone(),
+        },
+    );
+    let content = llm::generate(provider.as_ref(), &prompt).await?;
+    Ok((model, content))
+}
+
+async fn run_pipeline(opts: HeadlessOptions) -> Result<i32, String> {
+    eprintln!(
+        "実行: {} Args: [{}] Prefix: [{}]",
+        opts.request.script_path,
+        opts.request.display_args(),
+        opts.request.command_prefix
+    );
+
+    let (mut child, running) = runner::start(&opts.request, Arc::new(ConsoleSink))?;
+    let status = wait_child(&mut child).await?;
+    let outcome = running.finish(status);
+
+    for check in &outcome.parameter_checks {
+        if check.status != ParamCheckStatus::Applied {
+            eprintln!(
+                "警告: パラメータ {} = {} は適用されていません ({:?}, ログ上の値: {})",
+                check.name,
+                check.requested,
+                check.status,
+                check.applied.as_deref().unwrap_or("-")
+            );
+        }
+    }
+
+    let numeric_issues = numerics::detect(&outcome.stdout);
+    for issue in &numeric_issues {
+        if issue.severity >= Severity::Warning {
+            eprintln!("警告: {} ({} 行目)", issue.message, issue.line);
+        }
+    }
+
+    if let Some(path) = &opts.summary_out {
+        write_json(path, &outcome.summary)?;
+    }
+    if let Some(path) = &opts.results_out {
+        let results = json!({
+            "exitCode": outcome.exit_code,
+            "blocks": outcome.results,
+            "parameterChecks": outcome.parameter_checks,
+            "numericIssues": numeric_issues,
+        });
+        write_json(path, &results)?;
+    }
+
+    if iis::is_infeasible(&outcome.summary) {
+        match &outcome.iis_file {
+            Some(file) => eprintln!("IIS ファイル: {}", file.path),
+            None => eprintln!(
+                "モデルは実行不可能です。IIS を調べるには model.computeIIS() と model.write(\"model.ilp\") を実行してください。"
+            ),
+        }
+    }
+    if let (Some(path), Some(file)) = (&opts.iis_out, &outcome.iis_file) {
+        write_json(path, &iis::parse(&file.content))?;
+    }
+
+    let mut store = match &opts.history_db {
+        Some(path) => Some(HistoryStore::open(path)?),
+        None => None,
+    };
+    let run_id = match store.as_mut() {
+        Some(store) => Some(store.insert_run(&outcome.to_new_run(&opts.request))?),
+        None => None,
+    };
+
+    if opts.analyze {
+        if !outcome.success {
+            eprintln!("スクリプトが異常終了しました。ログの範囲で解析を続けます。");
+        }
+        let (model, content) = analyze(&opts, &outcome).await?;
+        match &opts.report_out {
+            Some(path) => std::fs::write(path, &content)
+                .map_err(|e| format!("{} に書き込めません: {}", path.display(), e))?,
+            None => println!("{}", content),
+        }
+        if let (Some(store), Some(run_id)) = (store.as_ref(), run_id) {
+            store.add_analysis(run_id, &model, &opts.focus, &content)?;
+        }
+    }
+
+    Ok(outcome.exit_code.unwrap_or(1))
+}
+
+// スイープの各実行のログを "[番号] 行" の形で流し、履歴DBがあれば記録する
+struct ConsoleSweepObserver {
+    store: Option<Mutex<HistoryStore>>,
+    tag: String,
+}
+
+impl SweepObserver for ConsoleSweepObserver {
+    fn on_update(&self, row: &SweepRow) {
+        match row.status {
+            SweepJobStatus::Queued => {}
+            SweepJobStatus::Running => eprintln!("[{}] 開始: {}", row.index, row.label),
+            status => eprintln!(
+                "[{}] {:?} (exit: {:?}) {}",
+                row.index,
+                status,
+                row.exit_code,
+                row.error.as_deref().unwrap_or("")
+            ),
+        }
+    }
+
+    fn on_line(&self, index: usize, line: &str) {
+        println!("[{}] {}", index, line);
+    }
+
+    fn on_complete(&self, request: &RunRequest, outcome: &RunOutcome) -> Option<i64> {
+        let mut run = outcome.to_new_run(request);
+        run.tags = vec!["sweep".to_string(), self.tag.clone()];
+        let mut store = self.store.as_ref()?.lock().ok()?;
+        store.insert_run(&run).ok()
+    }
+}
+
+async fn run_sweep_pipeline(opts: &HeadlessOptions, spec: &PathBuf) -> Result<i32, String> {
+    let text = std::fs::read_to_string(spec)
+        .map_err(|e| format!("{} を読み込めません: {}", spec.display(), e))?;
+    let definition: SweepDefinition =
+        serde_json::from_str(&text).map_err(|e| format!("スイープ定義が不正です: {}", e))?;
+    let jobs = sweep::expand(&definition)?;
+    eprintln!("スイープ: {} 件", jobs.len());
+
+    let store = match &opts.history_db {
+        Some(path) => Some(Mutex::new(HistoryStore::open(path)?)),
+        None => None,
+    };
+    let observer = Arc::new(ConsoleSweepObserver {
+        store,
+        tag: format!("sweep-{}", crate::history::now_millis()),
+    });
+
+    let control = Arc::new(SweepControl::new());
+    let worker_control = control.clone();
+    let mut task = tokio::task::spawn_blocking(move || {
+        sweep::run_sweep(&definition, jobs, worker_control, observer)
+    });
+    // 待機中のジョブは実行せず、実行中のものは Ctrl+C のたびに強いシグナルへ進める
+    let mut stages = process::STOP_SEQUENCE.into_iter();
+    let mut ctrl_c = std::pin::pin!(tokio::signal::ctrl_c());
+    let rows = loop {
+        tokio::select! {
+            rows = &mut task => break rows.map_err(|e| e.to_string())?,
+            _ = &mut ctrl_c => {
+                ctrl_c.set(tokio::signal::ctrl_c());
+                if let Some(signal) = stages.next() {
+                    eprintln!("{}", stop_message(signal));
+                    control.cancel(signal);
+                }
+            }
+        }
+    };
+
+    let csv = sweep::rows_to_csv(&rows);
+    match &opts.sweep_out {
+        Some(path) => std::fs::write(path, csv)
+            .map_err(|e| format!("{} に書き込めません: {}", path.display(), e))?,
+        None => print!("{}", csv),
+    }
+
+    let all_finished = rows.iter().all(|r| r.status == SweepJobStatus::Finished);
+    Ok(if all_finished { 0 } else { 1 })
+}
+
+// 引数 (プログラム名を除く) を受け取り、プロセスの終了コードを返す
+// 統計は標準出力へ JSON で、警告は標準エラーへ
+fn print_model_stats(path: &str) -> i32 {
+    match model_stats::read_model_stats(path) {
+        Ok(stats) => {
+            for warning in &stats.warnings {
+                eprintln!("警告: {}", warning.message);
+            }
+            match serde_json::to_string_pretty(&stats) {
+                Ok(text) => {
+                    println!("{}", text);
+                    0
+                }
+                Err(e) => {
+                    eprintln!("エラー: {}", e);
+                    2
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("エラー: {}", e);
+            2
+        }
+    }
+}
+
+pub fn run_headless(args: &[String]) -> i32 {
+    dotenv::dotenv().ok();
+
+    if args.iter().any(|a| a == "--help" || a == "-h") {
+        println!("{}", USAGE);
+        return 0;
+    }
+
+    let opts = match parse_args(args) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprintln!("エラー: {}\n\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    if let Some(path) = &opts.model_stats {
+        return print_model_stats(path);
+    }
+
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(rt) => rt,
+        Err(e) => {
+            eprintln!("エラー: {}", e);
+            return 2;
+        }
+    };
+
+    let result = match &opts.sweep {
+        Some(spec) => runtime.block_on(run_sweep_pipeline(&opts, spec)),
+        None => runtime.block_on(run_pipeline(opts)),
+    };
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("エラー: {}", e);
+            2
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<HeadlessOptions, String> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        parse_args(&args)
+    }
+
+    #[test]
+    fn parses_script_options() {
+        let opts = parse(&[
+            "--headless",
+            "--script",
+            "model.py",
+            "--args",
+            "--n 100",
+            "--cwd",
+            "work",
+            "--env",
+            "GRB_THREADS=4",
+            "--env",
+            "EMPTY=",
+            "--param",
+            "MIPGap=0.01",
+            "--summary-out",
+            "summary.json",
+            "--analyze",
+            "--provider",
+            "openai",
+            "--token-budget",
+            "5000",
+            "--compression",
+            "incumbent",
+        ])
+        .unwrap();
+        assert_eq!(opts.request.script_path, "model.py");
+        assert_eq!(opts.request.args_str, "--n 100");
+        assert_eq!(opts.request.command_prefix, DEFAULT_PREFIX);
+        assert_eq!(opts.request.working_dir.as_deref(), Some("work"));
+        assert_eq!(opts.request.env["GRB_THREADS"], "4");
+        assert_eq!(opts.request.env["EMPTY"], "");
+        assert_eq!(opts.request.gurobi_params["MIPGap"], "0.01");
+        assert_eq!(opts.summary_out, Some(PathBuf::from("summary.json")));
+        assert!(opts.analyze);
+        assert_eq!(opts.provider, ProviderKind::OpenAiCompatible);
+        assert_eq!(opts.token_budget, Some(5000));
+        assert_eq!(
+            opts.compression.strategy,
+            CompressionStrategy::IncumbentChanges
+        );
+    }
+
+    #[test]
+    fn passes_arguments_after_double_dash_verbatim() {
+        let opts = parse(&["--script", "m.py", "--", "--param", "a b", "--"]).unwrap();
+        assert_eq!(
+            opts.request.args,
+            Some(vec![
+                "--param".to_string(),
+                "a b".to_string(),
+                "--".to_string()
+            ])
+        );
+        assert!(opts.request.gurobi_params.is_empty());
+    }
+
+    #[test]
+    fn model_file_uses_gurobi_cl() {
+        let opts = parse(&["--model-file", "model.mps.gz", "--result-file", "out.sol"]).unwrap();
+        assert_eq!(opts.request.mode, RunMode::GurobiCl);
+        assert_eq!(opts.request.command_prefix, "");
+        assert_eq!(opts.request.result_file.as_deref(), Some("out.sol"));
+
+        let opts = parse(&["--model-stats", "model.lp"]).unwrap();
+        assert_eq!(opts.model_stats.as_deref(), Some("model.lp"));
+    }
+
+    #[test]
+    fn rejects_invalid_arguments() {
+        let err = |args: &[&str]| parse(args).unwrap_err();
+        assert!(err(&[]).contains("--script"));
+        assert!(err(&["--script"]).contains("--script には値が必要です"));
+        assert!(err(&["--script", "m.py", "--env", "NOVALUE"]).contains("KEY=VALUE"));
+        assert!(err(&["--script", "m.py", "--param", "MIPGap"]).contains("NAME=VALUE"));
+        assert!(err(&["--script", "m.py", "--provider", "claude"]).contains("claude"));
+        assert!(err(&["--script", "m.py", "--token-budget", "many"]).contains("many"));
+        assert!(err(&["--script", "m.py", "--compression", "some"]).contains("some"));
+        assert!(err(&["--script", "m.py", "--verbose"]).contains("--verbose"));
+        assert!(err(&["--run", "main"]).contains("--workspace"));
+    }
+
+    #[test]
+    fn command_line_overrides_workspace() {
+        let dir = std::env::temp_dir().join(format!("gurobilab-headless-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join(crate::workspace::WORKSPACE_FILE),
+            r#"
+            commandPrefix = "python3"
+            systemPrompt = "be brief"
+            [env]
+            A = "1"
+            [[scripts]]
+            name = "main"
+            path = "main.py"
+            args = "--n 10"
+            preset = "quick"
+            [presets.quick]
+            TimeLimit = 60
+            "#,
+        )
+        .unwrap();
+        let workspace = dir.to_string_lossy().to_string();
+
+        let opts = parse(&[
+            "--workspace",
+            &workspace,
+            "--run",
+            "main",
+            "--args",
+            "--n 20",
+            "--env",
+            "B=2",
+            "--param",
+            "MIPGap=0.1",
+        ])
+        .unwrap();
+        assert!(opts.request.script_path.ends_with("main.py"));
+        assert_eq!(opts.request.args_str, "--n 20");
+        assert_eq!(opts.request.command_prefix, "python3");
+        assert_eq!(opts.request.env.len(), 2);
+        assert_eq!(opts.request.gurobi_params["TimeLimit"], 60);
+        assert_eq!(opts.request.gurobi_params["MIPGap"], "0.1");
+        assert_eq!(opts.system_prompt, "be brief");
+        assert!(opts
+            .history_db
+            .unwrap()
+            .ends_with(".gurobilab/history.sqlite3"));
+
+        assert!(parse(&["--workspace", &workspace, "--preset", "quick"]).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,832 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::iis::IisFile;
+use crate::llm::ChatRole;
+use crate::result_json::ResultBlock;
+use crate::runner::RunMode;
+use crate::solution::{SolutionFile, SolutionFormat};
+use crate::summary::SolveSummary;
+
+// 実行履歴をアプリのデータディレクトリ内の SQLite に保存する
+
+// スキーマの変更はここに追記していく (PRAGMA user_version で適用済みの位置を管理)
+const MIGRATIONS: &[&str] = &[
+    r#"
+    CREATE TABLE runs (
+        id             INTEGER PRIMARY KEY AUTOINCREMENT,
+        script_path    TEXT    NOT NULL,
+        args           TEXT    NOT NULL,
+        command_prefix TEXT    NOT NULL,
+        exit_code      INTEGER,
+        started_at     INTEGER NOT NULL,
+        finished_at    INTEGER NOT NULL,
+        raw_log        TEXT    NOT NULL,
+        stderr_log     TEXT    NOT NULL DEFAULT '',
+        json_payload   TEXT
+    );
+    CREATE INDEX runs_started_at ON runs(started_at);
+    CREATE TABLE run_tags (
+        run_id INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+        tag    TEXT    NOT NULL,
+        PRIMARY KEY (run_id, tag)
+    );
+    CREATE TABLE analyses (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id      INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+        created_at  INTEGER NOT NULL,
+        model       TEXT    NOT NULL,
+        focus_point TEXT    NOT NULL,
+        content     TEXT    NOT NULL
+    );
+    "#,
+    r#"
+    ALTER TABLE runs ADD COLUMN summary TEXT;
+    "#,
+    r#"
+    ALTER TABLE runs ADD COLUMN results TEXT;
+    "#,
+    r#"
+    ALTER TABLE runs ADD COLUMN working_dir TEXT;
+    ALTER TABLE runs ADD COLUMN env TEXT;
+    "#,
+    r#"
+    ALTER TABLE runs ADD COLUMN parameters TEXT;
+    "#,
+    r#"
+    ALTER TABLE runs ADD COLUMN mode TEXT NOT NULL DEFAULT 'script';
+    ALTER TABLE runs ADD COLUMN result_file TEXT;
+    "#,
+    r#"
+    CREATE TABLE run_solutions (
+        id          INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id      INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+        attached_at INTEGER NOT NULL,
+        path        TEXT    NOT NULL,
+        format      TEXT    NOT NULL,
+        content     TEXT    NOT NULL
+    );
+    CREATE INDEX run_solutions_run_id ON run_solutions(run_id);
+    "#,
+    r#"
+    CREATE TABLE run_iis (
+        run_id      INTEGER PRIMARY KEY REFERENCES runs(id) ON DELETE CASCADE,
+        attached_at INTEGER NOT NULL,
+        path        TEXT    NOT NULL,
+        content     TEXT    NOT NULL
+    );
+    "#,
+    r#"
+    CREATE TABLE chat_messages (
+        id         INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id     INTEGER NOT NULL REFERENCES runs(id) ON DELETE CASCADE,
+        created_at INTEGER NOT NULL,
+        role       TEXT    NOT NULL,
+        is_context INTEGER NOT NULL DEFAULT 0,
+        model      TEXT,
+        content    TEXT    NOT NULL
+    );
+    CREATE INDEX chat_messages_run_id ON chat_messages(run_id);
+    "#,
+];
+
+// 一覧取得の上限 (1ページあたり)
+pub const MAX_PAGE_SIZE: u32 = 200;
+
+// 現在時刻 (UNIXエポックからのミリ秒)
+pub fn now_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+// 新規登録する実行記録
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewRun {
+    pub script_path: String,
+    pub args: String,
+    pub command_prefix: String,
+    pub exit_code: Option<i32>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub raw_log: String,
+    #[serde(default)]
+    pub stderr_log: String,
+    #[serde(default)]
+    pub json_payload: Option<Value>,
+    // 名前付きを含むすべての結果JSONブロック
+    #[serde(default)]
+    pub results: Vec<ResultBlock>,
+    #[serde(default)]
+    pub summary: Option<SolveSummary>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    // 実行時に追加した環境変数
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // gurobi.env 経由で指定した Gurobi パラメータ
+    #[serde(default)]
+    pub parameters: BTreeMap<String, String>,
+    #[serde(default)]
+    pub mode: RunMode,
+    // 解ファイルの出力先 (ResultFile を指定した場合)
+    #[serde(default)]
+    pub result_file: Option<String>,
+    // 実行と一緒に保存する解ファイル
+    #[serde(default)]
+    pub solution_files: Vec<SolutionFile>,
+    // 実行不可能だったときの IIS ファイル (.ilp)
+    #[serde(default)]
+    pub iis_file: Option<IisFile>,
+}
+
+// 一覧表示用 (ログ本文は含めない)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunSummaryItem {
+    pub id: i64,
+    pub script_path: String,
+    pub args: String,
+    pub command_prefix: String,
+    pub exit_code: Option<i32>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub summary: Option<SolveSummary>,
+    pub tags: Vec<String>,
+    pub analysis_count: i64,
+    pub mode: RunMode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunPage {
+    pub total: i64,
+    pub items: Vec<RunSummaryItem>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AnalysisRecord {
+    pub id: i64,
+    pub created_at: i64,
+    pub model: String,
+    pub focus_point: String,
+    pub content: String,
+}
+
+// 追加質問のスレッドの1メッセージ
+// ログを含む最初のメッセージ (is_context) は大きいので、一覧には含めない
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessageRecord {
+    pub id: i64,
+    pub created_at: i64,
+    pub role: ChatRole,
+    pub is_context: bool,
+    pub model: Option<String>,
+    pub content: String,
+}
+
+// 追加するメッセージ
+pub struct NewChatMessage<'a> {
+    pub role: ChatRole,
+    pub is_context: bool,
+    pub content: &'a str,
+}
+
+// 添付された解ファイル (中身は含めない)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionFileInfo {
+    pub id: i64,
+    pub attached_at: i64,
+    pub path: String,
+    pub format: String,
+    pub size: i64,
+}
+
+// 1件分の詳細
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRecord {
+    pub id: i64,
+    pub script_path: String,
+    pub args: String,
+    pub command_prefix: String,
+    pub exit_code: Option<i32>,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub raw_log: String,
+    pub stderr_log: String,
+    pub json_payload: Option<Value>,
+    pub results: Vec<ResultBlock>,
+    pub summary: Option<SolveSummary>,
+    pub tags: Vec<String>,
+    pub analyses: Vec<AnalysisRecord>,
+    // 追加質問のスレッド (ログを含む最初のメッセージを除く)
+    pub chat: Vec<ChatMessageRecord>,
+    pub working_dir: Option<String>,
+    pub env: BTreeMap<String, String>,
+    pub parameters: BTreeMap<String, String>,
+    pub mode: RunMode,
+    pub result_file: Option<String>,
+    pub solutions: Vec<SolutionFileInfo>,
+    // 保存済みの IIS ファイルのパス
+    pub iis_path: Option<String>,
+}
+
+pub struct HistoryStore {
+    conn: Connection,
+}
+
+fn db_err(e: rusqlite::Error) -> String {
+    format!("履歴DBエラー: {}", e)
+}
+
+// 構造化データは JSON 文字列として列に保存する
+fn to_json_text<T: Serialize>(value: &Option<T>) -> Option<String> {
+    value.as_ref().and_then(|v| serde_json::to_string(v).ok())
+}
+
+fn from_json_text<T: serde::de::DeserializeOwned>(text: Option<String>) -> Option<T> {
+    text.and_then(|s| serde_json::from_str(&s).ok())
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let conn = Connection::open(path).map_err(db_err)?;
+        Self::init(conn)
+    }
+
+    fn init(conn: Connection) -> Result<Self, String> {
+        conn.pragma_update(None, "foreign_keys", "ON")
+            .map_err(db_err)?;
+        let mut store = HistoryStore { conn };
+        store.migrate()?;
+        Ok(store)
+    }
+
+    // 1段ずつ、SQL と user_version の更新を同じトランザクションで適用する
+    // (途中で失敗してもスキーマとバージョンがずれない)
+    fn migrate(&mut self) -> Result<(), String> {
+        let version: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .map_err(db_err)?;
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip(version) {
+            let tx = self.conn.transaction().map_err(db_err)?;
+            tx.execute_batch(sql).map_err(db_err)?;
+            tx.pragma_update(None, "user_version", i + 1)
+                .map_err(db_err)?;
+            tx.commit().map_err(db_err)?;
+        }
+        Ok(())
+    }
+
+    pub fn insert_run(&mut self, run: &NewRun) -> Result<i64, String> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        tx.execute(
+            "INSERT INTO runs (script_path, args, command_prefix, exit_code, started_at,
+                               finished_at, raw_log, stderr_log, json_payload, summary,
+                               results, working_dir, env, parameters, mode, result_file)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)",
+            params![
+                run.script_path,
+                run.args,
+                run.command_prefix,
+                run.exit_code,
+                run.started_at,
+                run.finished_at,
+                run.raw_log,
+                run.stderr_log,
+                run.json_payload.as_ref().map(|v| v.to_string()),
+                to_json_text(&run.summary),
+                to_json_text(&Some(&run.results)),
+                run.working_dir,
+                to_json_text(&Some(&run.env)),
+                to_json_text(&Some(&run.parameters)),
+                run.mode.as_str(),
+                run.result_file,
+            ],
+        )
+        .map_err(db_err)?;
+        let id = tx.last_insert_rowid();
+        for tag in &run.tags {
+            tx.execute(
+                "INSERT OR IGNORE INTO run_tags (run_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )
+            .map_err(db_err)?;
+        }
+        for file in &run.solution_files {
+            Self::insert_solution(&tx, id, file)?;
+        }
+        if let Some(file) = &run.iis_file {
+            Self::upsert_iis(&tx, id, file)?;
+        }
+        tx.commit().map_err(db_err)?;
+        Ok(id)
+    }
+
+    fn insert_solution(conn: &Connection, run_id: i64, file: &SolutionFile) -> Result<i64, String> {
+        let format = SolutionFormat::detect(&file.path, &file.content);
+        conn.execute(
+            "INSERT INTO run_solutions (run_id, attached_at, path, format, content)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                run_id,
+                now_millis(),
+                file.path,
+                format.as_str(),
+                file.content
+            ],
+        )
+        .map_err(db_err)?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    // 既存の実行に解ファイルを添付する
+    pub fn attach_solution(&self, run_id: i64, file: &SolutionFile) -> Result<i64, String> {
+        let exists: bool = self
+            .conn
+            .query_row(
+                "SELECT EXISTS (SELECT 1 FROM runs WHERE id = ?1)",
+                params![run_id],
+                |row| row.get(0),
+            )
+            .map_err(db_err)?;
+        if !exists {
+            return Err(format!("履歴が見つかりません (id={})", run_id));
+        }
+        Self::insert_solution(&self.conn, run_id, file)
+    }
+
+    fn upsert_iis(conn: &Connection, run_id: i64, file: &IisFile) -> Result<(), String> {
+        conn.execute(
+            "INSERT OR REPLACE INTO run_iis (run_id, attached_at, path, content)
+             VALUES (?1, ?2, ?3, ?4)",
+            params![run_id, now_millis(), file.path, file.content],
+        )
+        .map_err(db_err)?;
+        Ok(())
+    }
+
+    // 実行の IIS ファイルを保存する (既にあれば置き換える)
+    pub fn set_iis(&self, run_id: i64, file: &IisFile) -> Result<(), String> {
+        Self::upsert_iis(&self.conn, run_id, file)
+    }
+
+    pub fn get_iis(&self, run_id: i64) -> Result<Option<IisFile>, String> {
+        self.conn
+            .query_row(
+                "SELECT path, content FROM run_iis WHERE run_id = ?1",
+                params![run_id],
+                |row| {
+                    Ok(IisFile {
+                        path: row.get(0)?,
+                        content: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_err)
+    }
+
+    pub fn get_solution(&self, id: i64) -> Result<Option<SolutionFile>, String> {
+        self.conn
+            .query_row(
+                "SELECT path, content FROM run_solutions WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(SolutionFile {
+                        path: row.get(0)?,
+                        content: row.get(1)?,
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_err)
+    }
+
+    // 新しい順に offset 件目から limit 件を返す (tag 指定時はそのタグを持つものだけ)
+    pub fn list_runs(&self, offset: u32, limit: u32, tag: Option<&str>) -> Result<RunPage, String> {
+        let limit = limit.clamp(1, MAX_PAGE_SIZE);
+
+        let total: i64 = self
+            .conn
+            .query_row(
+                "SELECT COUNT(*) FROM runs
+                 WHERE ?1 IS NULL OR id IN (SELECT run_id FROM run_tags WHERE tag = ?1)",
+                params![tag],
+                |row| row.get(0),
+            )
+            .map_err(db_err)?;
+
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, script_path, args, command_prefix, exit_code, started_at, finished_at,
+                        (SELECT COUNT(*) FROM analyses a WHERE a.run_id = runs.id), summary, mode
+                 FROM runs
+                 WHERE ?1 IS NULL OR id IN (SELECT run_id FROM run_tags WHERE tag = ?1)
+                 ORDER BY started_at DESC, id DESC
+                 LIMIT ?2 OFFSET ?3",
+            )
+            .map_err(db_err)?;
+        let rows = stmt
+            .query_map(params![tag, limit, offset], |row| {
+                Ok(RunSummaryItem {
+                    id: row.get(0)?,
+                    script_path: row.get(1)?,
+                    args: row.get(2)?,
+                    command_prefix: row.get(3)?,
+                    exit_code: row.get(4)?,
+                    started_at: row.get(5)?,
+                    finished_at: row.get(6)?,
+                    summary: from_json_text(row.get(8)?),
+                    tags: Vec::new(),
+                    analysis_count: row.get(7)?,
+                    mode: RunMode::parse(&row.get::<_, String>(9)?),
+                })
+            })
+            .map_err(db_err)?;
+
+        let mut items = Vec::new();
+        for row in rows {
+            let mut item = row.map_err(db_err)?;
+            item.tags = self.tags_of(item.id)?;
+            items.push(item);
+        }
+        Ok(RunPage { total, items })
+    }
+
+    pub fn get_run(&self, id: i64) -> Result<Option<RunRecord>, String> {
+        let record = self
+            .conn
+            .query_row(
+                "SELECT id, script_path, args, command_prefix, exit_code, started_at, finished_at,
+                        raw_log, stderr_log, json_payload, summary, results, working_dir, env,
+                        parameters, mode, result_file
+                 FROM runs WHERE id = ?1",
+                params![id],
+                |row| {
+                    Ok(RunRecord {
+                        id: row.get(0)?,
+                        script_path: row.get(1)?,
+                        args: row.get(2)?,
+                        command_prefix: row.get(3)?,
+                        exit_code: row.get(4)?,
+                        started_at: row.get(5)?,
+                        finished_at: row.get(6)?,
+                        raw_log: row.get(7)?,
+                        stderr_log: row.get(8)?,
+                        json_payload: from_json_text(row.get(9)?),
+                        summary: from_json_text(row.get(10)?),
+                        results: from_json_text(row.get(11)?).unwrap_or_default(),
+                        tags: Vec::new(),
+                        analyses: Vec::new(),
+                        chat: Vec::new(),
+                        working_dir: row.get(12)?,
+                        env: from_json_text(row.get(13)?).unwrap_or_default(),
+                        parameters: from_json_text(row.get(14)?).unwrap_or_default(),
+                        mode: RunMode::parse(&row.get::<_, String>(15)?),
+                        result_file: row.get(16)?,
+                        solutions: Vec::new(),
+                        iis_path: None,
+                    })
+                },
+            )
+            .optional()
+            .map_err(db_err)?;
+
+        let Some(mut record) = record else {
+            return Ok(None);
+        };
+        record.tags = self.tags_of(id)?;
+        record.analyses = self.analyses_of(id)?;
+        record.chat = self.chat_of(id, false)?;
+        record.solutions = self.solutions_of(id)?;
+        record.iis_path = self
+            .conn
+            .query_row(
+                "SELECT path FROM run_iis WHERE run_id = ?1",
+                params![id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(db_err)?;
+        Ok(Some(record))
+    }
+
+    // タグを丸ごと置き換える
+    pub fn set_tags(&mut self, id: i64, tags: &[String]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        tx.execute("DELETE FROM run_tags WHERE run_id = ?1", params![id])
+            .map_err(db_err)?;
+        for tag in tags {
+            let tag = tag.trim();
+            if tag.is_empty() {
+                continue;
+            }
+            tx.execute(
+                "INSERT OR IGNORE INTO run_tags (run_id, tag) VALUES (?1, ?2)",
+                params![id, tag],
+            )
+            .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)
+    }
+
+    // 削除した件数を返す (タグと解析結果は外部キーで一緒に消える)
+    pub fn delete_runs(&mut self, ids: &[i64]) -> Result<usize, String> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        let mut deleted = 0;
+        for id in ids {
+            deleted += tx
+                .execute("DELETE FROM runs WHERE id = ?1", params![id])
+                .map_err(db_err)?;
+        }
+        tx.commit().map_err(db_err)?;
+        Ok(deleted)
+    }
+
+    pub fn add_analysis(
+        &self,
+        run_id: i64,
+        model: &str,
+        focus_point: &str,
+        content: &str,
+    ) -> Result<i64, String> {
+        self.conn
+            .execute(
+                "INSERT INTO analyses (run_id, created_at, model, focus_point, content)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![run_id, now_millis(), model, focus_point, content],
+            )
+            .map_err(db_err)?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    // まとめて追加し、追加した分を返す (途中で失敗したら何も残さない)
+    pub fn add_chat_messages(
+        &mut self,
+        run_id: i64,
+        model: Option<&str>,
+        messages: &[NewChatMessage],
+    ) -> Result<Vec<ChatMessageRecord>, String> {
+        let tx = self.conn.transaction().map_err(db_err)?;
+        let mut added = Vec::new();
+        for message in messages {
+            let created_at = now_millis();
+            tx.execute(
+                "INSERT INTO chat_messages (run_id, created_at, role, is_context, model, content)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                params![
+                    run_id,
+                    created_at,
+                    message.role.as_str(),
+                    message.is_context,
+                    model,
+                    message.content
+                ],
+            )
+            .map_err(db_err)?;
+            added.push(ChatMessageRecord {
+                id: tx.last_insert_rowid(),
+                created_at,
+                role: message.role,
+                is_context: message.is_context,
+                model: model.map(str::to_string),
+                content: message.content.to_string(),
+            });
+        }
+        tx.commit().map_err(db_err)?;
+        Ok(added)
+    }
+
+    // with_context が false ならログを含むメッセージを除く
+    pub fn chat_of(
+        &self,
+        run_id: i64,
+        with_context: bool,
+    ) -> Result<Vec<ChatMessageRecord>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, created_at, role, is_context, model, content
+                 FROM chat_messages WHERE run_id = ?1 AND (?2 OR is_context = 0)
+                 ORDER BY id",
+            )
+            .map_err(db_err)?;
+        let messages = stmt
+            .query_map(params![run_id, with_context], |row| {
+                Ok(ChatMessageRecord {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    role: ChatRole::parse(&row.get::<_, String>(2)?),
+                    is_context: row.get(3)?,
+                    model: row.get(4)?,
+                    content: row.get(5)?,
+                })
+            })
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+        Ok(messages)
+    }
+
+    // 削除した件数を返す
+    pub fn clear_chat(&self, run_id: i64) -> Result<usize, String> {
+        self.conn
+            .execute(
+                "DELETE FROM chat_messages WHERE run_id = ?1",
+                params![run_id],
+            )
+            .map_err(db_err)
+    }
+
+    fn tags_of(&self, id: i64) -> Result<Vec<String>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT tag FROM run_tags WHERE run_id = ?1 ORDER BY tag")
+            .map_err(db_err)?;
+        let tags = stmt
+            .query_map(params![id], |row| row.get(0))
+            .map_err(db_err)?
+            .collect::<Result<Vec<String>, _>>()
+            .map_err(db_err)?;
+        Ok(tags)
+    }
+
+    fn solutions_of(&self, id: i64) -> Result<Vec<SolutionFileInfo>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, attached_at, path, format, length(CAST(content AS BLOB))
+                 FROM run_solutions WHERE run_id = ?1 ORDER BY attached_at, id",
+            )
+            .map_err(db_err)?;
+        let solutions = stmt
+            .query_map(params![id], |row| {
+                Ok(SolutionFileInfo {
+                    id: row.get(0)?,
+                    attached_at: row.get(1)?,
+                    path: row.get(2)?,
+                    format: row.get(3)?,
+                    size: row.get(4)?,
+                })
+            })
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+        Ok(solutions)
+    }
+
+    fn analyses_of(&self, id: i64) -> Result<Vec<AnalysisRecord>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT id, created_at, model, focus_point, content
+                 FROM analyses WHERE run_id = ?1 ORDER BY created_at, id",
+            )
+            .map_err(db_err)?;
+        let analyses = stmt
+            .query_map(params![id], |row| {
+                Ok(AnalysisRecord {
+                    id: row.get(0)?,
+                    created_at: row.get(1)?,
+                    model: row.get(2)?,
+                    focus_point: row.get(3)?,
+                    content: row.get(4)?,
+                })
+            })
+            .map_err(db_err)?
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(db_err)?;
+        Ok(analyses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn memory_store() -> HistoryStore {
+        HistoryStore::init(Connection::open_in_memory().unwrap()).unwrap()
+    }
+
+    fn new_run(tags: &[&str]) -> NewRun {
+        serde_json::from_value(serde_json::json!({
+            "scriptPath": "model.py",
+            "args": "--fast",
+            "commandPrefix": "python",
+            "exitCode": 0,
+            "startedAt": 1000,
+            "finishedAt": 2000,
+            "rawLog": "Optimal objective 1.0",
+            "tags": tags,
+        }))
+        .unwrap()
+    }
+
+    fn count(store: &HistoryStore, table: &str) -> i64 {
+        store
+            .conn
+            .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| {
+                row.get(0)
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_from_zero_to_latest() {
+        let store = memory_store();
+        let version: usize = store
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+
+        // 2回目は何も適用しない
+        let mut store = store;
+        store.migrate().unwrap();
+        assert_eq!(count(&store, "runs"), 0);
+    }
+
+    #[test]
+    fn inserts_runs_and_chat_messages() {
+        let mut store = memory_store();
+        let id = store.insert_run(&new_run(&["b", "a", "a"])).unwrap();
+
+        let record = store.get_run(id).unwrap().unwrap();
+        assert_eq!(record.script_path, "model.py");
+        assert_eq!(record.exit_code, Some(0));
+        assert_eq!(record.tags, vec!["a", "b"]);
+        assert_eq!(store.list_runs(0, 10, Some("a")).unwrap().total, 1);
+        assert_eq!(store.list_runs(0, 10, Some("c")).unwrap().total, 0);
+
+        let added = store
+            .add_chat_messages(
+                id,
+                Some("model-x"),
+                &[
+                    NewChatMessage {
+                        role: ChatRole::User,
+                        is_context: true,
+                        content: "log",
+                    },
+                    NewChatMessage {
+                        role: ChatRole::Assistant,
+                        is_context: false,
+                        content: "answer",
+                    },
+                ],
+            )
+            .unwrap();
+        assert_eq!(added.len(), 2);
+        assert_eq!(store.chat_of(id, true).unwrap().len(), 2);
+
+        // 詳細にはログを含むメッセージを載せない
+        let chat = store.get_run(id).unwrap().unwrap().chat;
+        assert_eq!(chat.len(), 1);
+        assert_eq!(chat[0].role, ChatRole::Assistant);
+        assert_eq!(chat[0].model.as_deref(), Some("model-x"));
+    }
+
+    #[test]
+    fn deleting_a_run_cascades() {
+        let mut store = memory_store();
+        let id = store.insert_run(&new_run(&["tag"])).unwrap();
+        let other = store.insert_run(&new_run(&["tag"])).unwrap();
+        store.add_analysis(id, "model-x", "", "analysis").unwrap();
+        store
+            .add_chat_messages(
+                id,
+                None,
+                &[NewChatMessage {
+                    role: ChatRole::User,
+                    is_context: false,
+                    content: "question",
+                }],
+            )
+            .unwrap();
+
+        assert_eq!(store.delete_runs(&[id]).unwrap(), 1);
+        assert!(store.get_run(id).unwrap().is_none());
+        assert!(store.get_run(other).unwrap().is_some());
+        assert_eq!(count(&store, "run_tags"), 1);
+        assert_eq!(count(&store, "analyses"), 0);
+        assert_eq!(count(&store, "chat_messages"), 0);
+    }
+}
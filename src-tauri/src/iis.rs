@@ -0,0 +1,466 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::summary::{SolveStatus, SolveSummary};
+
+// 実行不可能なモデルの IIS (Irreducible Inconsistent Subsystem) ファイル (.ilp) を読む
+//
+// model.computeIIS(); model.write("model.ilp") や gurobi_cl ResultFile=model.ilp で書き出される LP 形式:
+//   Subject To
+//    c0: x + y >= 10
+//    c1: x + y <= 5
+//   Bounds
+//    x <= 3
+//    y free
+//   End
+
+// 履歴に保存する IIS ファイルの大きさの上限
+const MAX_IIS_BYTES: u64 = 16 * 1024 * 1024;
+// AI プロンプトに載せる IIS の文字数の上限
+const MAX_PROMPT_CHARS: usize = 6000;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IisFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IisConstraint {
+    pub name: Option<String>,
+    // 左辺 (複数行に分かれていたものは1行にまとめる)
+    pub expression: String,
+    pub sense: Option<String>,
+    pub rhs: Option<f64>,
+    pub variables: Vec<String>,
+}
+
+// IIS に含まれる変数の上下限 (None はその側の制限なし)
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IisBound {
+    pub variable: String,
+    pub lower: Option<f64>,
+    pub upper: Option<f64>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IisReport {
+    pub constraints: Vec<IisConstraint>,
+    pub bounds: Vec<IisBound>,
+    // 指示制約・SOS などは行のまま
+    pub general_constraints: Vec<String>,
+    pub sos: Vec<String>,
+    pub integers: Vec<String>,
+    pub binaries: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IisSource {
+    // 履歴に保存済み
+    Stored,
+    // ファイルを指定した
+    Provided,
+    // 作業ディレクトリで見つけた
+    Found,
+    // gurobi_cl で計算し直した
+    Computed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IisDiagnosis {
+    pub run_id: i64,
+    pub path: String,
+    pub source: IisSource,
+    pub report: IisReport,
+}
+
+pub fn is_infeasible(summary: &SolveSummary) -> bool {
+    matches!(
+        summary.status,
+        Some(SolveStatus::Infeasible | SolveStatus::InfeasibleOrUnbounded)
+    )
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Section {
+    Objective,
+    Constraints,
+    Bounds,
+    Integers,
+    Binaries,
+    Semi,
+    Sos,
+    GeneralConstraints,
+}
+
+fn section_of(line: &str) -> Option<Section> {
+    let key = line.trim().to_ascii_lowercase();
+    Some(match key.as_str() {
+        "minimize" | "maximize" | "minimum" | "maximum" | "min" | "max" => Section::Objective,
+        "subject to" | "such that" | "st" | "s.t." | "lazy constraints" | "user cuts" => {
+            Section::Constraints
+        }
+        "bounds" | "bound" => Section::Bounds,
+        "generals" | "general" | "gen" | "integers" => Section::Integers,
+        "binaries" | "binary" | "bin" => Section::Binaries,
+        "semi-continuous" | "semis" | "semi" => Section::Semi,
+        "sos" => Section::Sos,
+        "general constraints" | "general constraint" | "gencons" => Section::GeneralConstraints,
+        _ => return None,
+    })
+}
+
+// "-infinity" などを含めて数値として読む (無限大は None)
+fn parse_bound_value(s: &str) -> Option<Option<f64>> {
+    let lower = s.to_ascii_lowercase();
+    let body = lower.trim_start_matches(['+', '-']);
+    if matches!(body, "inf" | "infinity") {
+        return Some(None);
+    }
+    s.parse::<f64>().ok().map(Some)
+}
+
+const SENSES: &[&str] = &["<=", ">=", "=<", "=>", "<", ">", "="];
+
+// 末尾の "<= 右辺" を切り出す
+fn split_sense(text: &str) -> (String, Option<String>, Option<f64>) {
+    let text = text.trim();
+    if let Some((lhs, rhs)) = text.rsplit_once(char::is_whitespace) {
+        let lhs = lhs.trim_end();
+        for sense in SENSES {
+            if let Some(expr) = lhs.strip_suffix(sense) {
+                if let Some(rhs) = parse_bound_value(rhs) {
+                    return (expr.trim().to_string(), Some(sense.to_string()), rhs);
+                }
+            }
+        }
+    }
+    (text.to_string(), None, None)
+}
+
+fn variables_of(expression: &str) -> Vec<String> {
+    let mut vars: Vec<String> = Vec::new();
+    for token in expression.split(|c: char| c.is_whitespace() || matches!(c, '+' | '*' | '^')) {
+        let token = token.trim_start_matches('-');
+        if token.is_empty()
+            || token.parse::<f64>().is_ok()
+            || token.starts_with(['[', ']'])
+            || token.contains(['<', '>', '='])
+        {
+            continue;
+        }
+        if !vars.iter().any(|v| v == token) {
+            vars.push(token.to_string());
+        }
+    }
+    vars
+}
+
+fn parse_constraint(text: &str) -> IisConstraint {
+    // "名前: 式" (名前の無い制約もある)
+    let (name, body) = match text.split_once(':') {
+        Some((name, body)) if !name.trim().is_empty() && !name.contains(char::is_whitespace) => {
+            (Some(name.trim().to_string()), body)
+        }
+        _ => (None, text),
+    };
+    let (expression, sense, rhs) = split_sense(body);
+    IisConstraint {
+        name,
+        variables: variables_of(&expression),
+        expression,
+        sense,
+        rhs,
+    }
+}
+
+// "x <= 3" / "-infinity <= y <= 4" / "z = 1" / "w free"
+fn parse_bound(line: &str) -> Option<IisBound> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    let text = line.trim().to_string();
+    let (variable, lower, upper) = match tokens.as_slice() {
+        [var, free] if free.eq_ignore_ascii_case("free") => (*var, None, None),
+        [lo, "<=" | "=<", var, "<=" | "=<", hi] => {
+            (*var, parse_bound_value(lo)?, parse_bound_value(hi)?)
+        }
+        [a, op, b] => match (parse_bound_value(a), parse_bound_value(b)) {
+            // 数値 op 変数
+            (Some(value), None) => match *op {
+                "<=" | "=<" | "<" => (*b, value, None),
+                ">=" | "=>" | ">" => (*b, None, value),
+                "=" => (*b, value, value),
+                _ => return None,
+            },
+            // 変数 op 数値
+            (None, Some(value)) => match *op {
+                "<=" | "=<" | "<" => (*a, None, value),
+                ">=" | "=>" | ">" => (*a, value, None),
+                "=" => (*a, value, value),
+                _ => return None,
+            },
+            _ => return None,
+        },
+        _ => return None,
+    };
+    // IIS に含まれない上下限は free や無限大として書かれるので除く
+    if lower.is_none() && upper.is_none() {
+        return None;
+    }
+    Some(IisBound {
+        variable: variable.to_string(),
+        lower,
+        upper,
+        text,
+    })
+}
+
+pub fn parse(content: &str) -> IisReport {
+    let mut report = IisReport::default();
+    let mut section = None;
+    // 複数行にわたる制約を1つにまとめる
+    let mut pending: Option<String> = None;
+
+    let flush = |pending: &mut Option<String>, report: &mut IisReport| {
+        if let Some(text) = pending.take() {
+            report.constraints.push(parse_constraint(&text));
+        }
+    };
+
+    for raw in content.lines() {
+        let line = raw.trim();
+        if line.is_empty() || line.starts_with('\\') {
+            continue;
+        }
+        if line.eq_ignore_ascii_case("end") {
+            break;
+        }
+        if let Some(next) = section_of(line) {
+            flush(&mut pending, &mut report);
+            section = Some(next);
+            continue;
+        }
+        match section {
+            Some(Section::Constraints) => {
+                // "名前:" で始まらず、前の行がまだ右辺まで来ていなければ続き
+                let named = line.split_once(':').is_some_and(|(name, _)| {
+                    !name.is_empty() && !name.contains(char::is_whitespace)
+                });
+                let continues = !named
+                    && pending
+                        .as_deref()
+                        .is_some_and(|text| split_sense(text).1.is_none());
+                match pending.as_mut() {
+                    Some(text) if continues => {
+                        text.push(' ');
+                        text.push_str(line);
+                    }
+                    _ => {
+                        flush(&mut pending, &mut report);
+                        pending = Some(line.to_string());
+                    }
+                }
+            }
+            Some(Section::Bounds) => report.bounds.extend(parse_bound(line)),
+            Some(Section::Integers) => report
+                .integers
+                .extend(line.split_whitespace().map(str::to_string)),
+            Some(Section::Binaries) => report
+                .binaries
+                .extend(line.split_whitespace().map(str::to_string)),
+            Some(Section::Sos) => report.sos.push(line.to_string()),
+            Some(Section::GeneralConstraints) => report.general_constraints.push(line.to_string()),
+            Some(Section::Objective | Section::Semi) | None => {}
+        }
+    }
+    flush(&mut pending, &mut report);
+    report
+}
+
+fn format_value(value: Option<f64>) -> String {
+    value.map_or("-".to_string(), |v| v.to_string())
+}
+
+// AI プロンプトに加える [IIS] セクション
+pub fn prompt_section(report: &IisReport) -> String {
+    let mut lines = vec![format!(
+        "[IIS] 実行不可能の原因となる最小の矛盾集合 (制約 {} 件, 変数の上下限 {} 件)",
+        report.constraints.len() + report.general_constraints.len() + report.sos.len(),
+        report.bounds.len()
+    )];
+    for c in &report.constraints {
+        let name = c.name.as_deref().unwrap_or("(名前なし)");
+        match &c.sense {
+            Some(sense) => lines.push(format!(
+                "{}: {} {} {}",
+                name,
+                c.expression,
+                sense,
+                format_value(c.rhs)
+            )),
+            None => lines.push(format!("{}: {}", name, c.expression)),
+        }
+    }
+    lines.extend(report.general_constraints.iter().cloned());
+    lines.extend(report.sos.iter().cloned());
+    if !report.bounds.is_empty() {
+        lines.push("Bounds:".to_string());
+        lines.extend(report.bounds.iter().map(|b| b.text.clone()));
+    }
+
+    let mut section = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if section.len() + line.len() + 1 > MAX_PROMPT_CHARS {
+            section.push_str(&format!("... (残り {} 行を省略)\n", lines.len() - i));
+            break;
+        }
+        section.push_str(line);
+        section.push('\n');
+    }
+    section
+}
+
+fn modified_millis(path: &Path) -> Option<i64> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64)
+}
+
+pub fn read_file(path: &Path) -> Result<IisFile, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("IIS ファイルを開けません: {} ({})", path.display(), e))?
+        .len();
+    if size > MAX_IIS_BYTES {
+        return Err(format!(
+            "IIS ファイルが大きすぎます ({} MB まで): {}",
+            MAX_IIS_BYTES / 1024 / 1024,
+            path.display()
+        ));
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("IIS ファイルを読めません: {} ({})", path.display(), e))?;
+    Ok(IisFile {
+        path: path.to_string_lossy().to_string(),
+        content,
+    })
+}
+
+// 実行の開始後に書き出されていれば読む
+pub fn read_if_written_since(path: &Path, since_millis: i64) -> Option<IisFile> {
+    // ファイルシステムの時刻の粒度 (秒単位のものもある) を見込む
+    if modified_millis(path)? + 1000 < since_millis {
+        return None;
+    }
+    read_file(path).ok()
+}
+
+// 作業ディレクトリにある、実行中 (since..until) に書き出された .ilp のうち最新のもの
+pub fn find_written_between(
+    dir: &Path,
+    since_millis: i64,
+    until_millis: Option<i64>,
+) -> Option<IisFile> {
+    // ファイルシステムの時刻の粒度 (秒単位のものもある) を見込む
+    let in_range = |modified: i64| {
+        modified + 1000 >= since_millis && until_millis.is_none_or(|until| modified <= until + 1000)
+    };
+    let newest: PathBuf = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("ilp")))
+        .filter_map(|p| Some((modified_millis(&p)?, p)))
+        .filter(|(modified, _)| in_range(*modified))
+        .max_by_key(|(modified, _)| *modified)?
+        .1;
+    read_file(&newest).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ILP: &str = "\
+\\ Model diet_copy
+\\ LP format - for model browsing. Use MPS format to capture full model detail.
+Minimize
+ 
+Subject To
+ calories_min: 100 bread + 250 milk + 80 cheese
+   + 120 potato >= 2000
+ budget: 2 bread + 3.5 milk + 8 cheese + 1.5 potato <= 10
+ R3: - bread + milk = 0
+Bounds
+ -infinity <= bread <= 3
+ milk <= 2
+ cheese >= 1
+ potato free
+ x = 4
+Generals
+ bread
+Binaries
+ pick_a pick_b
+General Constraints
+ GC0: pick_a = 1 -> cheese >= 2
+End
+";
+
+    #[test]
+    fn parses_ilp_file() {
+        let report = parse(ILP);
+        assert_eq!(report.constraints.len(), 3);
+
+        let calories = &report.constraints[0];
+        assert_eq!(calories.name.as_deref(), Some("calories_min"));
+        assert_eq!(calories.sense.as_deref(), Some(">="));
+        assert_eq!(calories.rhs, Some(2000.0));
+        assert_eq!(
+            calories.variables,
+            vec!["bread", "milk", "cheese", "potato"]
+        );
+        assert!(calories.expression.ends_with("+ 120 potato"));
+
+        assert_eq!(report.constraints[1].rhs, Some(10.0));
+        assert_eq!(report.constraints[2].sense.as_deref(), Some("="));
+        assert_eq!(report.constraints[2].variables, vec!["bread", "milk"]);
+
+        let bounds: Vec<(&str, Option<f64>, Option<f64>)> = report
+            .bounds
+            .iter()
+            .map(|b| (b.variable.as_str(), b.lower, b.upper))
+            .collect();
+        assert_eq!(
+            bounds,
+            vec![
+                ("bread", None, Some(3.0)),
+                ("milk", None, Some(2.0)),
+                ("cheese", Some(1.0), None),
+                ("x", Some(4.0), Some(4.0)),
+            ]
+        );
+        assert_eq!(report.integers, vec!["bread"]);
+        assert_eq!(report.binaries, vec!["pick_a", "pick_b"]);
+        assert_eq!(report.general_constraints.len(), 1);
+    }
+
+    #[test]
+    fn prompt_section_lists_constraints_and_bounds() {
+        let section = prompt_section(&parse(ILP));
+        assert!(section.starts_with("[IIS]"));
+        assert!(section.contains("budget"));
+        assert!(section.contains("cheese"));
+    }
+
+    #[test]
+    fn detects_infeasible_status() {
+        let mut summary = SolveSummary::default();
+        assert!(!is_infeasible(&summary));
+        summary.status = Some(SolveStatus::InfeasibleOrUnbounded);
+        assert!(is_infeasible(&summary));
+    }
+}
@@ -1,23 +1,171 @@
-use regex::Regex;
-use reqwest::Client;
-use serde_json::{json, Value};
-use std::io::{BufRead, BufReader};
-use std::process::{Child, Command, Stdio};
-use std::sync::Mutex;
-use std::thread;
-use tauri::{command, Emitter, State, Window};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::process::{Child, ExitStatus};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{command, Emitter, Manager, State, Window};
+use tokio::sync::Notify;
+
+mod argv;
+mod chat;
+mod compare;
+mod compression;
+mod env_profiles;
+mod gurobi_cl;
+mod gurobi_params;
+mod headless;
+mod history;
+mod iis;
+mod llm;
+mod log_parser;
+mod model_stats;
+mod numerics;
+mod process;
+mod prompt;
+mod report;
+mod result_json;
+mod runner;
+mod secrets;
+mod settings;
+mod solution;
+mod summary;
+mod sweep;
+mod workspace;
+
+use compare::RunComparison;
+use compression::CompressionConfig;
+use env_profiles::{EnvProfile, EnvProfileStore};
+use gurobi_params::{ParamCheck, ParamIssue, ParamSpec};
+use history::{ChatMessageRecord, HistoryStore, NewChatMessage, NewRun, RunPage, RunRecord};
+use iis::{IisDiagnosis, IisReport, IisSource};
+use llm::{ChatRole, ProviderConfig, ProviderKind, TokenUsage};
+use log_parser::ProgressEvent;
+use model_stats::ModelStats;
+use numerics::NumericIssue;
+use prompt::{BuiltPrompt, PromptOptions, SectionKind, TokenCounter};
+use result_json::ResultBlock;
+use runner::{RunRequest, RunSink};
+use secrets::{SecretStatus, SecretStore};
+use settings::{Settings, SettingsStore};
+use solution::{SolutionQuery, SolutionView};
+use summary::{SolveStatus, SolveSummary};
+use sweep::{SweepControl, SweepDefinition, SweepJob, SweepObserver, SweepRow};
+use workspace::{RecentWorkspaces, Workspace};
 
 struct OptimizationState {
     child: Mutex<Option<Child>>,
 }
 
+struct HistoryState {
+    store: Mutex<HistoryStore>,
+    // 終わったら履歴に書き込む処理の数 (0 でなければワークスペースを切り替えない)
+    jobs: AtomicUsize,
+}
+
+impl HistoryState {
+    // 切り替えと同時に始まらないよう、履歴のロックを取ってから数える
+    fn begin_job(&self) -> Result<HistoryJob<'_>, String> {
+        let _store = self.store.lock().map_err(|e| e.to_string())?;
+        self.jobs.fetch_add(1, Ordering::SeqCst);
+        Ok(HistoryJob(&self.jobs))
+    }
+}
+
+// drop されると処理の数を戻す
+struct HistoryJob<'a>(&'a AtomicUsize);
+
+impl Drop for HistoryJob<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+struct EnvProfileState {
+    store: Mutex<EnvProfileStore>,
+}
+
+// 開いているワークスペース (開いている間は履歴もワークスペースのものに切り替える)
+struct WorkspaceState {
+    current: Mutex<Option<Workspace>>,
+    recent: Mutex<RecentWorkspaces>,
+    // ワークスペースを閉じたときに戻す、アプリ全体の履歴
+    global_history: PathBuf,
+}
+
+struct SettingsState {
+    store: Mutex<SettingsStore>,
+}
+
+// プロバイダごとの APIキー (フロントエンドには渡さない)
+struct SecretState {
+    store: Mutex<SecretStore>,
+}
+
+// 実行中のパラメータスイープ (同時に1つまで)
+struct SweepState {
+    control: Mutex<Option<Arc<SweepControl>>>,
+}
+
+// ストリーミング解析の中断用
+struct AnalysisState {
+    cancel: Mutex<Option<Arc<Notify>>>,
+}
+
+impl AnalysisState {
+    // 前の解析が残っていれば止めてから、新しい中断通知を登録する
+    fn begin(&self) -> Result<Arc<Notify>, String> {
+        let cancel = Arc::new(Notify::new());
+        if let Some(prev) = self
+            .cancel
+            .lock()
+            .map_err(|e| e.to_string())?
+            .replace(cancel.clone())
+        {
+            prev.notify_one();
+        }
+        Ok(cancel)
+    }
+
+    // 自分の登録だけを外す (後から始まった解析のものは残す)
+    fn end(&self, cancel: &Arc<Notify>) {
+        if let Ok(mut guard) = self.cancel.lock() {
+            if guard.as_ref().is_some_and(|c| Arc::ptr_eq(c, cancel)) {
+                *guard = None;
+            }
+        }
+    }
+}
+
+// "analysis-done" イベントの中身
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AnalysisDone {
+    usage: Option<TokenUsage>,
+    cancelled: bool,
+}
+
+// run_optimization の戻り値 (履歴に保存されたIDと表示用ログ)
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RunOutput {
+    run_id: i64,
+    log: String,
+    summary: SolveSummary,
+    results: Vec<ResultBlock>,
+    parameter_checks: Vec<ParamCheck>,
+    numeric_issues: Vec<NumericIssue>,
+}
+
 // ユーザー表示用（ノイズ除去のみ、スペースは残す）
 fn clean_gurobi_log(raw_log: &str) -> String {
     raw_log
         .lines()
         .filter(|line| {
-            !line.contains("Set parameter")
-                && !line.contains("Academic license")
+            // "Set parameter" 行は実際に適用されたパラメータの確認のため残す
+            !line.contains("Academic license")
                 && !line.contains("Gurobi Optimizer version")
                 && !line.contains("CPU model")
                 && !line.contains("Thread count")
@@ -27,215 +175,380 @@ fn clean_gurobi_log(raw_log: &str) -> String {
         .join("\n")
 }
 
-// JSONの中身を再帰的に探索して、長い配列をカットする関数
-fn prune_json_recursively(v: &mut Value) {
-    match v {
-        Value::Array(arr) => {
-            // 配列の長さが「3」を超えていたらカットする
-            const MAX_ITEMS: usize = 3;
-            if arr.len() > MAX_ITEMS {
-                let original_len = arr.len();
-                // 最初の3個だけ残す
-                arr.truncate(MAX_ITEMS);
-                // 末尾に「省略しました」という情報を追加
-                arr.push(json!(format!(
-                    "... (truncated {} items) ...",
-                    original_len - MAX_ITEMS
-                )));
-            }
-            // 配列の中身もさらにチェック（ネスト対応）
-            for item in arr {
-                prune_json_recursively(item);
-            }
-        }
-        Value::Object(map) => {
-            // オブジェクトの場合は、すべての値をチェック
-            for (_, val) in map {
-                prune_json_recursively(val);
-            }
-        }
-        _ => {} // 数値、文字列、Booleanは何もしない（そのまま残す）
+// GUI 用の出力先: 各行をウィンドウへのイベントとして送る
+struct WindowSink(Window);
+
+impl RunSink for WindowSink {
+    fn on_stdout(&self, line: &str) {
+        let _ = self.0.emit("log-output", line);
     }
-}
 
-// ログの間引き機能を追加した圧縮関数
-fn compress_log_for_ai(full_log: &str) -> String {
-    let parts: Vec<&str> = full_log.split("---JSON_START---").collect();
+    fn on_stderr(&self, line: &str) {
+        let _ = self.0.emit("log-output", line);
+    }
 
-    let mut log_part = parts[0].to_string();
-    let mut json_part = String::new();
+    fn on_progress(&self, progress: &ProgressEvent) {
+        let _ = self.0.emit("progress", progress);
+    }
 
-    if parts.len() > 1 {
-        let raw_json = parts[1].split("---JSON_END---").next().unwrap_or("{}");
-        if let Ok(mut parsed) = serde_json::from_str::<Value>(raw_json) {
-            prune_json_recursively(&mut parsed);
-            json_part = parsed.to_string();
-        } else {
-            json_part = raw_json.to_string();
-        }
+    fn on_result(&self, block: &ResultBlock) {
+        let _ = self.0.emit("result-json", block);
     }
+}
 
-    // 1. まずスペースを詰める
-    let re = Regex::new(r" +").unwrap();
-    log_part = re.replace_all(&log_part, " ").to_string();
+// 名前で指定された環境プロファイルを環境変数・作業ディレクトリに反映する
+fn apply_env_profile(
+    profiles: &EnvProfileState,
+    script_path: &str,
+    name: Option<&str>,
+    env: &mut BTreeMap<String, String>,
+    working_dir: &mut Option<String>,
+) -> Result<(), String> {
+    let Some(name) = name.filter(|n| !n.trim().is_empty()) else {
+        return Ok(());
+    };
+    let store = profiles.store.lock().map_err(|e| e.to_string())?;
+    let profile = store
+        .find_for_script(script_path, name)
+        .ok_or_else(|| format!("環境プロファイル \"{}\" が見つかりません。", name))?;
+    env_profiles::apply(profile, env, working_dir);
+    Ok(())
+}
 
-    // 2. 行ごとの間引き処理 (Sampling)
-    let mut numeric_row_count = 0;
+// ★修正: コマンド実行部分（cmdのハードコードを廃止、stdinを閉じる処理を追加）
+#[command]
+async fn run_optimization(
+    window: Window,
+    state: State<'_, OptimizationState>,
+    history: State<'_, HistoryState>,
+    profiles: State<'_, EnvProfileState>,
+    mut request: RunRequest,
+) -> Result<RunOutput, String> {
+    apply_env_profile(
+        &profiles,
+        &request.script_path,
+        request.env_profile.as_deref(),
+        &mut request.env,
+        &mut request.working_dir,
+    )?;
 
-    log_part = log_part
-        .lines()
-        .filter(|line| {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                return false;
-            }
+    println!(
+        "実行: {} Args: [{}] Prefix: [{}]",
+        request.script_path,
+        request.display_args(),
+        request.command_prefix
+    );
+
+    let _job = history.begin_job()?;
+    let running = start_registered(&state, &window, &request)?;
 
-            // 最初の文字を確認
-            let first_char = trimmed.chars().next().unwrap();
+    let status = wait_registered_child(&state).await?;
 
-            // 条件分岐
-            if first_char.is_ascii_digit() {
-                // 数字で始まる行（通常のログ行）
-                numeric_row_count += 1;
-                // 最初の15行、以降は15行おきに残す
-                if numeric_row_count < 15 || numeric_row_count % 15 == 0 {
-                    return true;
+    let outcome = running.finish(status);
+
+    // 成否にかかわらず履歴に記録する
+    let run_id = history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert_run(&outcome.to_new_run(&request))?;
+
+    if outcome.success {
+        Ok(RunOutput {
+            run_id,
+            log: clean_gurobi_log(&outcome.stdout),
+            summary: outcome.summary,
+            results: outcome.results,
+            parameter_checks: outcome.parameter_checks,
+            numeric_issues: numerics::detect(&outcome.stdout),
+        })
+    } else {
+        Err(format!(
+            "Exit Code: {:?}\n{}",
+            outcome.exit_code, outcome.stderr
+        ))
+    }
+}
+
+// 子プロセスを起動し、停止コマンドから参照できるよう状態に登録する
+// 同時に実行できるのは1つだけなので、確認から登録までロックを保持する
+fn start_registered(
+    state: &OptimizationState,
+    window: &Window,
+    request: &RunRequest,
+) -> Result<runner::RunningProcess, String> {
+    let mut slot = state.child.lock().map_err(|e| e.to_string())?;
+    if slot.is_some() {
+        return Err("別の最適化が実行中です。".to_string());
+    }
+    let (child, running) = runner::start(request, Arc::new(WindowSink(window.clone())))?;
+    window.emit("process-pid", child.id()).unwrap_or(());
+    *slot = Some(child);
+    Ok(running)
+}
+
+// 状態に登録した子プロセスの終了を待つ
+// ロックを保持し続けないよう、try_wait でポーリングする
+async fn wait_registered_child(state: &OptimizationState) -> Result<ExitStatus, String> {
+    loop {
+        {
+            let mut guard = state.child.lock().map_err(|e| e.to_string())?;
+            let child = guard.as_mut().ok_or("子プロセスが見つかりません")?;
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    *guard = None;
+                    return Ok(status);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    *guard = None;
+                    return Err(format!("{}", e));
                 }
-                return false; // それ以外は捨てる
-            } else {
-                // 'H' (Heuristic), '*' (New solution), 文字列ヘッダーなどは全て残す
-                return true;
             }
-        })
-        .collect::<Vec<&str>>()
-        .join("\n");
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+}
 
-    if json_part.is_empty() {
-        log_part
-    } else {
-        format!("{}\n[JSON_DATA]:{}", log_part, json_part)
+// 実行中のプロセスが終了するまで最大 timeout 待つ (終了したら true)
+async fn wait_for_exit(state: &OptimizationState, pid: u32, timeout: Duration) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let running = match state.child.lock() {
+            Ok(guard) => guard.as_ref().map(|c| c.id()) == Some(pid),
+            Err(_) => false,
+        };
+        if !running {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
     }
 }
 
-// ★修正: コマンド実行部分（cmdのハードコードを廃止、stdinを閉じる処理を追加）
+// 実行中の最適化を停止する
+// まず SIGINT を送り Gurobi に最終サマリと解の書き出しをさせ、
+// grace_period_secs 以内に終わらなければ SIGTERM、さらに SIGKILL を送る
 #[command]
-async fn run_optimization(
-    window: Window,
-    _state: State<'_, OptimizationState>,
-    script_path: String,
-    args_str: String,
-    command_prefix: String,
-) -> Result<String, String> {
-    println!(
-        "実行: {} Args: [{}] Prefix: [{}]",
-        script_path, args_str, command_prefix
+async fn cancel_optimization(
+    state: State<'_, OptimizationState>,
+    grace_period_secs: Option<f64>,
+) -> Result<(), String> {
+    let pid = state
+        .child
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .map(|c| c.id())
+        .ok_or("実行中のプロセスがありません。")?;
+
+    let grace = Duration::from_secs_f64(
+        grace_period_secs
+            .filter(|s| s.is_finite() && *s >= 0.0)
+            .unwrap_or(process::DEFAULT_GRACE_PERIOD_SECS),
     );
 
-    // 1. プレフィックスを空白で分割
-    let mut parts = command_prefix.split_whitespace();
+    for signal in process::STOP_SEQUENCE {
+        process::signal_process_tree(pid, signal)?;
+        if wait_for_exit(&state, pid, grace).await {
+            break;
+        }
+    }
+    Ok(())
+}
 
-    // 2. 最初の単語をプログラム名として取得 (例: "uv" や "python")
-    let program = parts.next().ok_or("Command prefix is empty")?;
+// "sweep-log" イベントの中身
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SweepLogLine {
+    index: usize,
+    line: String,
+}
 
-    // 3. 残りの単語を引数として収集
-    let mut cmd_args: Vec<&str> = parts.collect();
+// スイープ全体の結果
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SweepReport {
+    // 履歴上でこのスイープの実行をまとめるタグ
+    tag: String,
+    rows: Vec<SweepRow>,
+    cancelled: bool,
+}
 
-    // 4. スクリプトパスを追加
-    cmd_args.push(&script_path);
+// スイープの進行を "sweep-update" / "sweep-log" イベントで送り、各実行を履歴に残す
+struct WindowSweepObserver {
+    window: Window,
+    tag: String,
+}
 
-    // 5. ユーザー引数を追加
-    for arg in args_str.split_whitespace() {
-        cmd_args.push(arg);
+impl SweepObserver for WindowSweepObserver {
+    fn on_update(&self, row: &SweepRow) {
+        let _ = self.window.emit("sweep-update", row);
     }
 
-    // ★重要: program変数を使い、stdinをnullにする
-    let mut child = Command::new(program)
-        .args(&cmd_args)
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .stdin(Stdio::null()) // ★追加: 入力待ちフリーズを防止
-        .spawn()
-        .map_err(|e| {
-            format!(
-                "コマンド起動エラー: {}\n(設定のCommand Prefixを確認してください)",
-                e
-            )
-        })?;
+    fn on_line(&self, index: usize, line: &str) {
+        let _ = self.window.emit(
+            "sweep-log",
+            SweepLogLine {
+                index,
+                line: line.to_string(),
+            },
+        );
+    }
 
-    let stdout = child.stdout.take().ok_or("stdout取得失敗")?;
-    let stderr = child.stderr.take().ok_or("stderr取得失敗")?;
-
-    let window_clone = window.clone();
-    let stdout_handle = thread::spawn(move || {
-        let reader = BufReader::new(stdout);
-        let mut full_log = String::new();
-        for line in reader.lines() {
-            if let Ok(l) = line {
-                let _ = window_clone.emit("log-output", &l);
-                full_log.push_str(&l);
-                full_log.push('\n');
-            }
-        }
-        full_log
-    });
+    fn on_complete(&self, request: &RunRequest, outcome: &runner::RunOutcome) -> Option<i64> {
+        let mut run = outcome.to_new_run(request);
+        run.tags = vec!["sweep".to_string(), self.tag.clone()];
+        let history = self.window.state::<HistoryState>();
+        let mut store = history.store.lock().ok()?;
+        store.insert_run(&run).ok()
+    }
+}
 
-    let window_clone2 = window.clone();
-    let stderr_handle = thread::spawn(move || {
-        let reader = BufReader::new(stderr);
-        let mut full_err = String::new();
-        for line in reader.lines() {
-            if let Ok(l) = line {
-                let _ = window_clone2.emit("log-output", &l);
-                full_err.push_str(&l);
-                full_err.push('\n');
-            }
+// 実際に実行される argv を返す (実行はしない)
+#[command]
+fn preview_command(request: RunRequest) -> Result<Vec<String>, String> {
+    runner::build_argv(&request)
+}
+
+// パラメータエディタに表示する一覧 (型・範囲・既定値)
+#[command]
+fn gurobi_param_catalog() -> Vec<ParamSpec> {
+    gurobi_params::PARAMS.to_vec()
+}
+
+// 入力されたパラメータの問題点を返す (問題が無ければ空)
+#[command]
+fn validate_gurobi_params(params: BTreeMap<String, Value>) -> Vec<ParamIssue> {
+    gurobi_params::validate(&params).err().unwrap_or_default()
+}
+
+// 展開結果の確認用 (実行はしない)
+#[command]
+fn preview_sweep(definition: SweepDefinition) -> Result<Vec<SweepJob>, String> {
+    sweep::expand(&definition)
+}
+
+// 引数の組み合わせを順に (または concurrency 件ずつ並列に) 実行し、結果表を返す
+#[command]
+async fn run_sweep(
+    window: Window,
+    sweep_state: State<'_, SweepState>,
+    history: State<'_, HistoryState>,
+    profiles: State<'_, EnvProfileState>,
+    mut definition: SweepDefinition,
+) -> Result<SweepReport, String> {
+    apply_env_profile(
+        &profiles,
+        &definition.script_path,
+        definition.env_profile.as_deref(),
+        &mut definition.env,
+        &mut definition.working_dir,
+    )?;
+    let jobs = sweep::expand(&definition)?;
+
+    let _job = history.begin_job()?;
+    let control = Arc::new(SweepControl::new());
+    {
+        let mut current = sweep_state.control.lock().map_err(|e| e.to_string())?;
+        if current.is_some() {
+            return Err("別のスイープが実行中です。".to_string());
         }
-        full_err
+        *current = Some(control.clone());
+    }
+
+    let tag = format!("sweep-{}", history::now_millis());
+    let observer = Arc::new(WindowSweepObserver {
+        window,
+        tag: tag.clone(),
     });
 
-    let pid = child.id();
-    window.emit("process-pid", pid).unwrap_or(());
+    let worker_control = control.clone();
+    let rows = tokio::task::spawn_blocking(move || {
+        sweep::run_sweep(&definition, jobs, worker_control, observer)
+    })
+    .await;
 
-    let status = child.wait().map_err(|e| format!("{}", e))?;
+    *sweep_state.control.lock().map_err(|e| e.to_string())? = None;
 
-    let full_stdout = stdout_handle.join().unwrap_or_default();
-    let full_stderr = stderr_handle.join().unwrap_or_default();
+    Ok(SweepReport {
+        tag,
+        rows: rows.map_err(|e| e.to_string())?,
+        cancelled: control.is_cancelled(),
+    })
+}
 
-    if status.success() {
-        Ok(clean_gurobi_log(&full_stdout))
-    } else {
-        Err(format!("Exit Code: {:?}\n{}", status.code(), full_stderr))
+// 実行中のスイープを止める (待機中のジョブは実行せず、実行中のものは cancel_optimization と同じ手順で止める)
+#[command]
+async fn cancel_sweep(
+    sweep_state: State<'_, SweepState>,
+    grace_period_secs: Option<f64>,
+) -> Result<(), String> {
+    let control = sweep_state
+        .control
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone()
+        .ok_or("実行中のスイープがありません。")?;
+
+    let grace = Duration::from_secs_f64(
+        grace_period_secs
+            .filter(|s| s.is_finite() && *s >= 0.0)
+            .unwrap_or(process::DEFAULT_GRACE_PERIOD_SECS),
+    );
+
+    for signal in process::STOP_SEQUENCE {
+        control.cancel(signal);
+        let deadline = Instant::now() + grace;
+        while control.has_running() && Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        if !control.has_running() {
+            break;
+        }
     }
+    Ok(())
 }
 
+// 結果表をCSVファイルに保存する
 #[command]
-fn kill_process(pid: u32) -> Result<(), String> {
-    let _ = Command::new("taskkill")
-        .args(["/PID", &pid.to_string(), "/F", "/T"])
-        .output()
-        .map_err(|e| e.to_string())?;
-    Ok(())
+fn export_sweep_csv(rows: Vec<SweepRow>, path: String) -> Result<(), String> {
+    std::fs::write(&path, sweep::rows_to_csv(&rows)).map_err(|e| e.to_string())
 }
 
-// ★修正: 引数を整理 (system_instruction と focus_point を正しく受け取る)
-fn build_prompt_string(log: &str, focus_point: &str, system_instruction: &str) -> String {
-    // 1. 強力圧縮
-    let compressed_log = compress_log_for_ai(log);
-
-    // 2. 長さ制限
-    let final_log = if compressed_log.len() > 15000 {
-        format!(
-            "... (snip) ...\n{}",
-            &compressed_log[compressed_log.len() - 15000..]
-        )
-    } else {
-        compressed_log
-    };
+// 保存済みログから進捗イベントを再構築する (履歴表示時のグラフ用)
+#[command]
+fn parse_log_progress(log: String) -> Vec<ProgressEvent> {
+    log_parser::parse_progress(&log)
+}
+
+// ログから最終結果のサマリを取り出す
+#[command]
+fn summarize_log(log: String) -> SolveSummary {
+    summary::parse_solve_summary(&log)
+}
+
+// ログから結果JSONブロックを取り出す
+#[command]
+fn extract_results(log: String) -> Vec<ResultBlock> {
+    result_json::extract_blocks(&log)
+}
 
-    // 3. 設定されたシステム指示を使用
+// ★修正: 引数を整理 (system_instruction と focus_point を正しく受け取る)
+// ログはモデルごとのトークン予算に合わせて区間ごとに間引く
+fn build_prompt(
+    log: &str,
+    focus_point: &str,
+    system_instruction: &str,
+    iis: Option<&IisReport>,
+    options: &PromptOptions,
+) -> BuiltPrompt {
+    // 設定されたシステム指示を使用
     // 設定が空ならデフォルトを使用する安全策
     let base_prompt = if system_instruction.trim().is_empty() {
-        "あなたはデータサイエンティストです。以下の最適化計算ログを解析し、Markdown形式のレポートを作成してください。\n# 制約\n- 挨拶や前置きは不可。即座に見出し(#)から開始すること。\n- ログの引用は不可。"
+        settings::DEFAULT_SYSTEM_PROMPT
     } else {
         system_instruction
     };
@@ -247,64 +560,952 @@ fn build_prompt_string(log: &str, focus_point: &str, system_instruction: &str) -
             .push_str(format!("追加指示:「{}」について深く考察すること。", focus_point).as_str());
     }
 
-    // 4. 結合
-    format!("{}\n{}\n[LOG]\n{}", base_prompt, user_focus, final_log)
+    // 数値的な問題は間引きの前のログ全体から拾い、必ず渡す
+    let numerics_section = numerics::prompt_section(&numerics::detect(log));
+    if !numerics_section.is_empty() {
+        user_focus
+            .push_str("[NUMERICS] に挙げた数値的な問題があれば、その原因と対策にも触れること。");
+    }
+
+    // 実行不可能なモデルでは IIS を別セクションで渡す (ログだけでは矛盾する制約が分からないため)
+    let iis_section = match iis {
+        Some(report) => {
+            user_focus.push_str(
+                "[IIS] に含まれる制約と上下限がなぜ同時に満たせないかを特定し、修正案を示すこと。",
+            );
+            iis::prompt_section(report)
+        }
+        None => String::new(),
+    };
+
+    let mut fixed = vec![(
+        SectionKind::Instructions,
+        format!("{}\n{}\n", base_prompt, user_focus),
+    )];
+    if !numerics_section.is_empty() {
+        fixed.push((SectionKind::Numerics, numerics_section));
+    }
+    if !iis_section.is_empty() {
+        fixed.push((SectionKind::Iis, iis_section));
+    }
+    prompt::build(&fixed, log, options)
+}
+
+fn build_prompt_string(
+    log: &str,
+    focus_point: &str,
+    system_instruction: &str,
+    iis: Option<&IisReport>,
+    options: &PromptOptions,
+) -> String {
+    build_prompt(log, focus_point, system_instruction, iis, options).prompt
 }
 
-// デバッグ用コマンド
+// コマンドの引数からプロンプトの組み立て方を決める
+fn prompt_options(
+    model_name: &str,
+    token_budget: Option<usize>,
+    compression: Option<CompressionConfig>,
+) -> Result<PromptOptions, String> {
+    let compression = compression.unwrap_or_default();
+    compression.validate()?;
+    Ok(PromptOptions {
+        model: model_name.to_string(),
+        token_budget,
+        compression,
+    })
+}
+
+// デバッグ用コマンド (区間ごとのトークンの割り当ても返す)
 // ★注意: Svelte側が system_instruction を送っていない場合のためにデフォルト値で対応
 #[command]
-fn debug_prompt(log: String, focus_point: String) -> String {
-    // プレビュー用にデフォルトのシステム指示を使用
-    let default_system = "あなたはデータサイエンティストです。(以下略...)";
-    let prompt = build_prompt_string(&log, &focus_point, default_system);
-    prompt
+fn debug_prompt(
+    log: String,
+    focus_point: String,
+    model_name: Option<String>,
+    token_budget: Option<usize>,
+    compression: Option<CompressionConfig>,
+) -> Result<BuiltPrompt, String> {
+    let model = model_name.unwrap_or_else(|| settings::DEFAULT_MODEL.to_string());
+    let options = prompt_options(&model, token_budget, compression)?;
+    Ok(build_prompt(
+        &log,
+        &focus_point,
+        settings::DEFAULT_SYSTEM_PROMPT,
+        None,
+        &options,
+    ))
 }
 
+// 実行に保存済みの IIS (プロンプトに加える)
+fn stored_iis(history: &HistoryState, run_id: Option<i64>) -> Result<Option<IisReport>, String> {
+    let Some(run_id) = run_id else {
+        return Ok(None);
+    };
+    let file = history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_iis(run_id)?;
+    Ok(file.map(|f| iis::parse(&f.content)))
+}
+
+fn stored_api_key(secrets: &SecretState, provider: ProviderKind) -> Result<String, String> {
+    secrets
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .api_key(provider)
+}
+
+// --- ワークスペース ---
+
+// 履歴の保存先を切り替える
+// 実行・スイープ・IIS の計算・AI解析など、終わったら履歴に書き込む処理の途中では切り替えない
+fn switch_workspace(
+    state: &OptimizationState,
+    sweep: &SweepState,
+    history: &HistoryState,
+    workspaces: &WorkspaceState,
+    workspace: Option<Workspace>,
+) -> Result<Option<Workspace>, String> {
+    let path = match &workspace {
+        Some(ws) => ws.history_path(),
+        None => workspaces.global_history.clone(),
+    };
+    let store = HistoryStore::open(&path)?;
+    {
+        let mut current = history.store.lock().map_err(|e| e.to_string())?;
+        let busy = history.jobs.load(Ordering::SeqCst) > 0
+            || state.child.lock().map_err(|e| e.to_string())?.is_some()
+            || sweep.control.lock().map_err(|e| e.to_string())?.is_some();
+        if busy {
+            return Err(
+                "実行・スイープ・解析の途中はワークスペースを切り替えられません。".to_string(),
+            );
+        }
+        *current = store;
+    }
+    if let Some(ws) = &workspace {
+        workspaces
+            .recent
+            .lock()
+            .map_err(|e| e.to_string())?
+            .touch(&ws.root)?;
+    }
+    *workspaces.current.lock().map_err(|e| e.to_string())? = workspace.clone();
+    Ok(workspace)
+}
+
+// gurobilab.toml (またはそれを含むフォルダ) を開く
 #[command]
+fn workspace_open(
+    state: State<'_, OptimizationState>,
+    sweep: State<'_, SweepState>,
+    history: State<'_, HistoryState>,
+    workspaces: State<'_, WorkspaceState>,
+    path: String,
+) -> Result<Option<Workspace>, String> {
+    let workspace = Workspace::open(Path::new(&path))?;
+    switch_workspace(&state, &sweep, &history, &workspaces, Some(workspace))
+}
+
+// フォルダに gurobilab.toml の雛形を作って開く
+#[command]
+fn workspace_init(
+    state: State<'_, OptimizationState>,
+    sweep: State<'_, SweepState>,
+    history: State<'_, HistoryState>,
+    workspaces: State<'_, WorkspaceState>,
+    dir: String,
+    name: String,
+) -> Result<Option<Workspace>, String> {
+    let workspace = Workspace::init(Path::new(&dir), &name)?;
+    switch_workspace(&state, &sweep, &history, &workspaces, Some(workspace))
+}
+
+#[command]
+fn workspace_close(
+    state: State<'_, OptimizationState>,
+    sweep: State<'_, SweepState>,
+    history: State<'_, HistoryState>,
+    workspaces: State<'_, WorkspaceState>,
+) -> Result<Option<Workspace>, String> {
+    switch_workspace(&state, &sweep, &history, &workspaces, None)
+}
+
+#[command]
+fn workspace_current(workspaces: State<'_, WorkspaceState>) -> Result<Option<Workspace>, String> {
+    Ok(workspaces
+        .current
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clone())
+}
+
+#[command]
+fn workspace_recent(workspaces: State<'_, WorkspaceState>) -> Result<Vec<String>, String> {
+    Ok(workspaces.recent.lock().map_err(|e| e.to_string())?.list())
+}
+
+// ワークスペースのスクリプトを実行するリクエストを作る (そのまま run_optimization に渡せる)
+#[command]
+fn workspace_run_request(
+    workspaces: State<'_, WorkspaceState>,
+    settings: State<'_, SettingsState>,
+    script: String,
+    preset: Option<String>,
+) -> Result<RunRequest, String> {
+    let default_prefix = settings
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get()
+        .command_prefix;
+    workspaces
+        .current
+        .lock()
+        .map_err(|e| e.to_string())?
+        .as_ref()
+        .ok_or("ワークスペースが開かれていません。")?
+        .run_request(&script, preset.as_deref(), &default_prefix)
+}
+
+// --- 設定 ---
+
+#[command]
+fn settings_get(settings: State<'_, SettingsState>) -> Result<Settings, String> {
+    Ok(settings.store.lock().map_err(|e| e.to_string())?.get())
+}
+
+// 検証してから保存し、正規化した設定を返す
+#[command]
+fn settings_update(
+    settings: State<'_, SettingsState>,
+    value: Settings,
+) -> Result<Settings, String> {
+    settings
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .update(value)
+}
+
+// チームで共有する設定ファイル (.toml / .json) を読み込み、今の設定を置き換える
+#[command]
+fn settings_import(settings: State<'_, SettingsState>, path: String) -> Result<Settings, String> {
+    settings
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .import_file(Path::new(&path))
+}
+
+#[command]
+fn settings_export(settings: State<'_, SettingsState>, path: String) -> Result<(), String> {
+    settings
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .export_file(Path::new(&path))
+}
+
+// 旧バージョンの localStorage の値を取り込む (設定ファイルがまだ無い場合のみ)
+#[command]
+fn settings_import_legacy(
+    settings: State<'_, SettingsState>,
+    values: BTreeMap<String, String>,
+) -> Result<Settings, String> {
+    settings
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .import_legacy(values)
+}
+
+// --- APIキー ---
+
+// 保存済みかどうかと保存先 (キーそのものは返さない)
+#[command]
+fn secret_status(secrets: State<'_, SecretState>) -> Result<Vec<SecretStatus>, String> {
+    let store = secrets.store.lock().map_err(|e| e.to_string())?;
+    [ProviderKind::Gemini, ProviderKind::OpenAiCompatible]
+        .into_iter()
+        .map(|provider| store.status(provider))
+        .collect()
+}
+
+#[command]
+fn secret_set(
+    secrets: State<'_, SecretState>,
+    provider: ProviderKind,
+    api_key: String,
+) -> Result<SecretStatus, String> {
+    let store = secrets.store.lock().map_err(|e| e.to_string())?;
+    store.set(provider, &api_key)?;
+    store.status(provider)
+}
+
+#[command]
+fn secret_clear(
+    secrets: State<'_, SecretState>,
+    provider: ProviderKind,
+) -> Result<SecretStatus, String> {
+    let store = secrets.store.lock().map_err(|e| e.to_string())?;
+    store.clear(provider)?;
+    store.status(provider)
+}
+
+// 短いリクエストを送ってキーが使えるか確かめる (api_key を省略すると保存済みのキー)
+#[command]
+async fn secret_test(
+    secrets: State<'_, SecretState>,
+    provider: ProviderKind,
+    base_url: Option<String>,
+    model_name: String,
+    api_key: Option<String>,
+) -> Result<String, String> {
+    let api_key = match api_key.filter(|k| !k.trim().is_empty()) {
+        Some(key) => key.trim().to_string(),
+        None => stored_api_key(&secrets, provider)?,
+    };
+    let provider = llm::create_provider(&ProviderConfig {
+        kind: provider,
+        base_url,
+        model: model_name,
+        api_key,
+    })?;
+    let reply = llm::generate(provider.as_ref(), "Reply with the single word OK.").await?;
+    Ok(reply.trim().chars().take(40).collect())
+}
+
+// LLM を使わずにログからレポートを作り、実行履歴に残す
+fn offline_analysis(
+    history: &HistoryState,
+    log: &str,
+    focus_point: &str,
+    run_id: Option<i64>,
+) -> Result<String, String> {
+    let iis = stored_iis(history, run_id)?;
+    let content = report::offline_report(log, iis.as_ref());
+    if let Some(run_id) = run_id {
+        history
+            .store
+            .lock()
+            .map_err(|e| e.to_string())?
+            .add_analysis(run_id, report::OFFLINE_MODEL, focus_point, &content)?;
+    }
+    Ok(content)
+}
+
+#[command]
+#[allow(clippy::too_many_arguments)]
 async fn analyze_log(
+    history: State<'_, HistoryState>,
+    secrets: State<'_, SecretState>,
     log: String,
     focus_point: String,
-    api_key: String,
     model_name: String,
     system_instruction: String, // ←これを受け取る
+    run_id: Option<i64>,
+    provider: Option<ProviderKind>,
+    base_url: Option<String>,
+    token_budget: Option<usize>,
+    compression: Option<CompressionConfig>,
 ) -> Result<String, String> {
-    if api_key.is_empty() {
-        return Err("APIキーが設定されていません。".to_string());
+    let options = prompt_options(&model_name, token_budget, compression)?;
+    let kind = provider.unwrap_or_default();
+    let config = ProviderConfig {
+        kind,
+        base_url,
+        model: model_name.clone(),
+        api_key: stored_api_key(&secrets, kind)?,
+    };
+    if config.is_offline() {
+        return offline_analysis(&history, &log, &focus_point, run_id);
     }
+    let provider = llm::create_provider(&config)?;
+    let _job = history.begin_job()?;
 
     // ★修正: 引数の順番と渡し方を正しく
-    let prompt = build_prompt_string(&log, &focus_point, &system_instruction);
+    let iis = stored_iis(&history, run_id)?;
+    let prompt = build_prompt_string(
+        &log,
+        &focus_point,
+        &system_instruction,
+        iis.as_ref(),
+        &options,
+    );
+
+    let content = llm::generate(provider.as_ref(), &prompt).await?;
+
+    // 実行履歴に紐づけて解析結果を残す
+    if let Some(run_id) = run_id {
+        history
+            .store
+            .lock()
+            .map_err(|e| e.to_string())?
+            .add_analysis(run_id, &model_name, &focus_point, &content)?;
+    }
+    Ok(content)
+}
+
+// 解析結果を逐次 "analysis-chunk" イベントで送り、最後に "analysis-done" を送る
+#[command]
+#[allow(clippy::too_many_arguments)]
+async fn analyze_log_stream(
+    window: Window,
+    history: State<'_, HistoryState>,
+    secrets: State<'_, SecretState>,
+    analysis: State<'_, AnalysisState>,
+    log: String,
+    focus_point: String,
+    model_name: String,
+    system_instruction: String,
+    run_id: Option<i64>,
+    provider: Option<ProviderKind>,
+    base_url: Option<String>,
+    token_budget: Option<usize>,
+    compression: Option<CompressionConfig>,
+) -> Result<String, String> {
+    let options = prompt_options(&model_name, token_budget, compression)?;
+    let kind = provider.unwrap_or_default();
+    let config = ProviderConfig {
+        kind,
+        base_url,
+        model: model_name.clone(),
+        api_key: stored_api_key(&secrets, kind)?,
+    };
+    if config.is_offline() {
+        // レポートは一度に出来上がるので、1つのチャンクとして送る
+        let content = offline_analysis(&history, &log, &focus_point, run_id)?;
+        let _ = window.emit("analysis-chunk", &content);
+        let _ = window.emit(
+            "analysis-done",
+            AnalysisDone {
+                usage: None,
+                cancelled: false,
+            },
+        );
+        return Ok(content);
+    }
+    let provider = llm::create_provider(&config)?;
+    let _job = history.begin_job()?;
 
-    let url = format!(
-        "https://generativelanguage.googleapis.com/v1beta/models/{}:generateContent?key={}",
-        model_name, api_key
+    let iis = stored_iis(&history, run_id)?;
+    let prompt = build_prompt_string(
+        &log,
+        &focus_point,
+        &system_instruction,
+        iis.as_ref(),
+        &options,
     );
 
-    let client = Client::new();
-    let body = json!({ "contents": [{ "parts": [{"text": prompt}] }] });
+    // 前の解析が残っていれば止めてから始める
+    let cancel = analysis.begin()?;
+    let result = llm::generate_stream(provider.as_ref(), &prompt, &cancel, |chunk| {
+        let _ = window.emit("analysis-chunk", chunk);
+    })
+    .await;
+    analysis.end(&cancel);
+
+    let outcome = result?;
+    let _ = window.emit(
+        "analysis-done",
+        AnalysisDone {
+            usage: outcome.usage.clone(),
+            cancelled: outcome.cancelled,
+        },
+    );
+
+    // 最後まで生成できたものだけ履歴に残す
+    if let (Some(run_id), false) = (run_id, outcome.cancelled) {
+        history
+            .store
+            .lock()
+            .map_err(|e| e.to_string())?
+            .add_analysis(run_id, &model_name, &focus_point, &outcome.text)?;
+    }
+    Ok(outcome.text)
+}
+
+// ストリーミング中の解析を打ち切る
+#[command]
+fn cancel_analysis(analysis: State<'_, AnalysisState>) -> Result<bool, String> {
+    match analysis.cancel.lock().map_err(|e| e.to_string())?.take() {
+        Some(cancel) => {
+            cancel.notify_one();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// --- 追加質問 (実行ごとのスレッド) ---
+
+// 実行についての追加質問を送り、回答を "chat-chunk" イベントで逐次送る (最後に "chat-done")
+// 初回はログから最初のメッセージを作り、解析済みならその結果を最初の回答にする
+#[command]
+#[allow(clippy::too_many_arguments)]
+async fn chat_send(
+    window: Window,
+    history: State<'_, HistoryState>,
+    secrets: State<'_, SecretState>,
+    analysis: State<'_, AnalysisState>,
+    run_id: i64,
+    question: String,
+    model_name: String,
+    system_instruction: String,
+    provider: Option<ProviderKind>,
+    base_url: Option<String>,
+    token_budget: Option<usize>,
+    compression: Option<CompressionConfig>,
+) -> Result<Vec<ChatMessageRecord>, String> {
+    let question = question.trim();
+    if question.is_empty() {
+        return Err("質問が空です。".to_string());
+    }
+    let mut options = prompt_options(&model_name, token_budget, compression)?;
+    let budget = options
+        .token_budget
+        .unwrap_or_else(|| prompt::default_budget(&model_name));
+    let kind = provider.unwrap_or_default();
+    let provider = llm::create_provider(&ProviderConfig {
+        kind,
+        base_url,
+        model: model_name.clone(),
+        api_key: stored_api_key(&secrets, kind)?,
+    })?;
+    let _job = history.begin_job()?;
+
+    let thread = {
+        let mut store = history.store.lock().map_err(|e| e.to_string())?;
+        let mut thread = store.chat_of(run_id, true)?;
+        if !thread.iter().any(|m| m.is_context) {
+            let run = store
+                .get_run(run_id)?
+                .ok_or_else(|| format!("実行 {} が見つかりません。", run_id))?;
+            let iis = store.get_iis(run_id)?.map(|f| iis::parse(&f.content));
+            options.token_budget = Some(chat::context_budget(budget));
+            let context = build_prompt_string(
+                &clean_gurobi_log(&run.raw_log),
+                "",
+                &system_instruction,
+                iis.as_ref(),
+                &options,
+            );
+            let mut seed = vec![NewChatMessage {
+                role: ChatRole::User,
+                is_context: true,
+                content: &context,
+            }];
+            if let Some(latest) = run.analyses.last() {
+                seed.push(NewChatMessage {
+                    role: ChatRole::Assistant,
+                    is_context: false,
+                    content: &latest.content,
+                });
+            }
+            store.add_chat_messages(run_id, None, &seed)?;
+            thread = store.chat_of(run_id, true)?;
+        }
+        thread
+    };
+
+    let counter = TokenCounter::for_model(&model_name);
+    let messages = chat::request_messages(&thread, question, &counter, budget);
 
-    let res = client
-        .post(url)
-        .json(&body)
-        .send()
+    let cancel = analysis.begin()?;
+    let result = llm::generate_chat_stream(provider.as_ref(), &messages, &cancel, |chunk| {
+        let _ = window.emit("chat-chunk", chunk);
+    })
+    .await;
+    analysis.end(&cancel);
+
+    let outcome = result?;
+    let _ = window.emit(
+        "chat-done",
+        AnalysisDone {
+            usage: outcome.usage.clone(),
+            cancelled: outcome.cancelled,
+        },
+    );
+    if outcome.cancelled {
+        return Ok(Vec::new());
+    }
+
+    // 最後まで回答できたやり取りだけスレッドに残す
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .add_chat_messages(
+            run_id,
+            Some(&model_name),
+            &[
+                NewChatMessage {
+                    role: ChatRole::User,
+                    is_context: false,
+                    content: question,
+                },
+                NewChatMessage {
+                    role: ChatRole::Assistant,
+                    is_context: false,
+                    content: &outcome.text,
+                },
+            ],
+        )
+}
+
+// スレッドの内容 (ログを含む最初のメッセージを除く)
+#[command]
+fn chat_thread(
+    history: State<'_, HistoryState>,
+    run_id: i64,
+) -> Result<Vec<ChatMessageRecord>, String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .chat_of(run_id, false)
+}
+
+// スレッドを消す (次の質問ではログから作り直す)
+#[command]
+fn chat_clear(history: State<'_, HistoryState>, run_id: i64) -> Result<usize, String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .clear_chat(run_id)
+}
+
+// --- 実行履歴 ---
+
+#[command]
+fn history_list(
+    history: State<'_, HistoryState>,
+    offset: Option<u32>,
+    limit: Option<u32>,
+    tag: Option<String>,
+) -> Result<RunPage, String> {
+    history.store.lock().map_err(|e| e.to_string())?.list_runs(
+        offset.unwrap_or(0),
+        limit.unwrap_or(50),
+        tag.as_deref(),
+    )
+}
+
+#[command]
+fn history_get(history: State<'_, HistoryState>, id: i64) -> Result<RunRecord, String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_run(id)?
+        .ok_or_else(|| format!("履歴が見つかりません (id={})", id))
+}
+
+#[command]
+fn history_insert(history: State<'_, HistoryState>, run: NewRun) -> Result<i64, String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .insert_run(&run)
+}
+
+#[command]
+fn history_set_tags(
+    history: State<'_, HistoryState>,
+    id: i64,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_tags(id, &tags)
+}
+
+#[command]
+fn history_delete(history: State<'_, HistoryState>, ids: Vec<i64>) -> Result<usize, String> {
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .delete_runs(&ids)
+}
+
+// project を指定した場合はそのフォルダのプロファイルだけを返す
+#[command]
+fn env_profiles_list(
+    profiles: State<'_, EnvProfileState>,
+    project: Option<String>,
+) -> Result<Vec<EnvProfile>, String> {
+    Ok(profiles
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .list(project.as_deref()))
+}
+
+#[command]
+fn env_profile_save(
+    profiles: State<'_, EnvProfileState>,
+    profile: EnvProfile,
+) -> Result<(), String> {
+    profiles
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .upsert(profile)
+}
+
+#[command]
+fn env_profile_delete(
+    profiles: State<'_, EnvProfileState>,
+    project: String,
+    name: String,
+) -> Result<bool, String> {
+    profiles
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .delete(&project, &name)
+}
+
+// 保存済みの実行を2件以上並べて比較する (先頭が基準)
+#[command]
+fn compare_runs(
+    history: State<'_, HistoryState>,
+    ids: Vec<i64>,
+    points: Option<usize>,
+) -> Result<RunComparison, String> {
+    let store = history.store.lock().map_err(|e| e.to_string())?;
+    let records = ids
+        .iter()
+        .map(|&id| {
+            store
+                .get_run(id)?
+                .ok_or_else(|| format!("履歴が見つかりません (id: {})", id))
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    compare::compare_runs(&records, points)
+}
+
+// 実行不可能だった実行の IIS を調べる
+// 1. ilp_path を指定すればそのファイル、2. 保存済みのもの、3. 作業ディレクトリに実行中に書き出された .ilp、
+// 4. gurobi_cl で解いた実行なら ResultFile=<モデル名>.ilp で解き直して IIS を書き出させる
+#[command]
+async fn diagnose_infeasibility(
+    window: Window,
+    state: State<'_, OptimizationState>,
+    history: State<'_, HistoryState>,
+    run_id: i64,
+    ilp_path: Option<String>,
+) -> Result<IisDiagnosis, String> {
+    let diagnosis = |file: iis::IisFile, source: IisSource| IisDiagnosis {
+        run_id,
+        report: iis::parse(&file.content),
+        path: file.path,
+        source,
+    };
+
+    let record = {
+        let store = history.store.lock().map_err(|e| e.to_string())?;
+        if let Some(path) = ilp_path.filter(|p| !p.trim().is_empty()) {
+            let file = iis::read_file(Path::new(&path))?;
+            store.set_iis(run_id, &file)?;
+            return Ok(diagnosis(file, IisSource::Provided));
+        }
+        if let Some(file) = store.get_iis(run_id)? {
+            return Ok(diagnosis(file, IisSource::Stored));
+        }
+        store
+            .get_run(run_id)?
+            .ok_or_else(|| format!("履歴が見つかりません (id={})", run_id))?
+    };
+
+    if !record.summary.as_ref().is_some_and(iis::is_infeasible) {
+        return Err("この実行は実行不可能 (Infeasible) と判定されていません。".to_string());
+    }
+    if let Some(dir) = &record.working_dir {
+        let found =
+            iis::find_written_between(Path::new(dir), record.started_at, Some(record.finished_at));
+        if let Some(file) = found {
+            history
+                .store
+                .lock()
+                .map_err(|e| e.to_string())?
+                .set_iis(run_id, &file)?;
+            return Ok(diagnosis(file, IisSource::Found));
+        }
+    }
+    if record.mode != runner::RunMode::GurobiCl {
+        return Err("IIS ファイルが見つかりません。スクリプトで model.computeIIS() と model.write(\"model.ilp\") を実行するか、.ilp ファイルを指定してください。".to_string());
+    }
+
+    // 同じパラメータで ResultFile だけを .ilp に変えて解き直す
+    let mut gurobi_params: BTreeMap<String, Value> = record
+        .parameters
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect();
+    gurobi_params.insert(
+        "ResultFile".to_string(),
+        Value::String(gurobi_cl::default_iis_file(&record.script_path)),
+    );
+    // 実行不可能か非有界かが区別できていない場合、そのままでは IIS が計算されない
+    let undecided = record
+        .summary
+        .as_ref()
+        .is_some_and(|s| s.status == Some(SolveStatus::InfeasibleOrUnbounded));
+    if undecided {
+        gurobi_params.insert("DualReductions".to_string(), Value::from(0));
+    }
+    let request = RunRequest {
+        script_path: record.script_path.clone(),
+        args_str: record.args.clone(),
+        command_prefix: record.command_prefix.clone(),
+        working_dir: record.working_dir.clone(),
+        env: record.env.clone(),
+        gurobi_params,
+        mode: runner::RunMode::GurobiCl,
+        ..Default::default()
+    };
+    let ilp = request
+        .resolved_result_file()
+        .ok_or("IIS の出力先を決められません。")?;
+
+    let _job = history.begin_job()?;
+    let running = start_registered(&state, &window, &request)?;
+    let status = wait_registered_child(&state).await?;
+    let outcome = running.finish(status);
+
+    if outcome.summary.status == Some(SolveStatus::Unbounded) {
+        return Err("DualReductions=0 で解き直した結果、モデルは実行不可能ではなく非有界でした。IIS はありません。".to_string());
+    }
+    let file = iis::read_if_written_since(&ilp, outcome.started_at)
+        .ok_or("IIS を計算できませんでした。gurobi_cl のログを確認してください。")?;
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .set_iis(run_id, &file)?;
+    Ok(diagnosis(file, IisSource::Computed))
+}
+
+// ログ全体から数値的な問題を拾う (保存済みの実行のログにも使う)
+#[command]
+fn detect_numeric_issues(log: String) -> Vec<NumericIssue> {
+    numerics::detect(&log)
+}
+
+// 解く前にモデルファイルの規模と係数の範囲を調べる (ライセンスは使わない)
+#[command]
+async fn model_statistics(path: String) -> Result<ModelStats, String> {
+    tokio::task::spawn_blocking(move || model_stats::read_model_stats(&path))
         .await
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| e.to_string())?
+}
 
-    let res_text = res.text().await.map_err(|e| e.to_string())?;
+// 解ファイルを読んで変数の一覧と集計を返す (履歴には保存しない)
+#[command]
+fn load_solution(path: String, query: Option<SolutionQuery>) -> Result<SolutionView, String> {
+    let file = solution::read_file(&path)?;
+    let parsed = solution::parse(&file.path, &file.content)?;
+    solution::query(&parsed, &query.unwrap_or_default())
+}
 
-    // エラーハンドリング強化
-    let json: serde_json::Value = serde_json::from_str(&res_text)
-        .map_err(|_| format!("Google API returned invalid JSON: {}", res_text))?;
+// 解ファイルを実行記録に添付する (添付の id を返す)
+#[command]
+fn history_attach_solution(
+    history: State<'_, HistoryState>,
+    run_id: i64,
+    path: String,
+) -> Result<i64, String> {
+    let file = solution::read_file(&path)?;
+    // 読めないファイルは添付しない
+    solution::parse(&file.path, &file.content)?;
+    history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .attach_solution(run_id, &file)
+}
 
-    if let Some(content) = json["candidates"][0]["content"]["parts"][0]["text"].as_str() {
-        Ok(content.to_string())
-    } else {
-        // エラー詳細を表示
-        Err(format!("API Error: {}", res_text))
+// 添付済みの解ファイルを表示する
+#[command]
+fn history_solution(
+    history: State<'_, HistoryState>,
+    id: i64,
+    query: Option<SolutionQuery>,
+) -> Result<SolutionView, String> {
+    let file = history
+        .store
+        .lock()
+        .map_err(|e| e.to_string())?
+        .get_solution(id)?
+        .ok_or_else(|| format!("解ファイルが見つかりません (id={})", id))?;
+    let parsed = solution::parse(&file.path, &file.content)?;
+    solution::query(&parsed, &query.unwrap_or_default())
+}
+
+// 旧バージョンで localStorage に保存していた履歴の形式
+#[derive(Deserialize)]
+struct LegacyHistoryItem {
+    #[serde(default)]
+    script: String,
+    #[serde(default)]
+    args: String,
+    #[serde(default)]
+    log: String,
+    #[serde(default)]
+    analysis: String,
+}
+
+// localStorage の履歴をDBへ移行する (新しい順に並んだ配列を受け取る)
+#[command]
+fn history_import_legacy(
+    history: State<'_, HistoryState>,
+    items: Vec<LegacyHistoryItem>,
+) -> Result<usize, String> {
+    let mut store = history.store.lock().map_err(|e| e.to_string())?;
+    // 元の日時は表示用文字列しか無いため、並び順だけを保つ
+    let base = history::now_millis() - items.len() as i64;
+    for (i, item) in items.iter().rev().enumerate() {
+        let results = result_json::extract_blocks(&item.log);
+        let id = store.insert_run(&NewRun {
+            script_path: item.script.clone(),
+            args: item.args.clone(),
+            command_prefix: String::new(),
+            exit_code: None,
+            started_at: base + i as i64,
+            finished_at: base + i as i64,
+            raw_log: item.log.clone(),
+            stderr_log: String::new(),
+            json_payload: result_json::default_payload(&results),
+            results,
+            summary: Some(summary::parse_solve_summary(&item.log)),
+            tags: vec!["legacy".to_string()],
+            working_dir: None,
+            env: Default::default(),
+            parameters: Default::default(),
+            mode: Default::default(),
+            result_file: None,
+            solution_files: Vec::new(),
+            iis_file: None,
+        })?;
+        if !item.analysis.is_empty() {
+            store.add_analysis(id, "", "", &item.analysis)?;
+        }
     }
+    Ok(items.len())
 }
 
+pub use headless::{is_headless, run_headless};
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -313,11 +1514,91 @@ pub fn run() {
         .manage(OptimizationState {
             child: Mutex::new(None),
         })
+        .manage(AnalysisState {
+            cancel: Mutex::new(None),
+        })
+        .manage(SweepState {
+            control: Mutex::new(None),
+        })
+        .setup(|app| {
+            let data_dir = app.path().app_data_dir()?;
+            let global_history = data_dir.join("history.sqlite3");
+            let store = HistoryStore::open(&global_history)?;
+            app.manage(HistoryState {
+                store: Mutex::new(store),
+                jobs: AtomicUsize::new(0),
+            });
+            let recent = RecentWorkspaces::open(&data_dir.join("workspaces.json"))?;
+            app.manage(WorkspaceState {
+                current: Mutex::new(None),
+                recent: Mutex::new(recent),
+                global_history,
+            });
+            let profiles = EnvProfileStore::open(&data_dir.join("env_profiles.json"))?;
+            app.manage(EnvProfileState {
+                store: Mutex::new(profiles),
+            });
+            let config_dir = app.path().app_config_dir()?;
+            let settings = SettingsStore::open(&config_dir.join("settings.toml"))?;
+            app.manage(SettingsState {
+                store: Mutex::new(settings),
+            });
+            app.manage(SecretState {
+                store: Mutex::new(SecretStore::new(&data_dir)),
+            });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             run_optimization,
+            preview_command,
+            gurobi_param_catalog,
+            validate_gurobi_params,
             analyze_log,
-            kill_process,
-            debug_prompt
+            analyze_log_stream,
+            cancel_analysis,
+            chat_send,
+            chat_thread,
+            chat_clear,
+            workspace_open,
+            workspace_init,
+            workspace_close,
+            workspace_current,
+            workspace_recent,
+            workspace_run_request,
+            settings_get,
+            settings_update,
+            settings_import,
+            settings_export,
+            settings_import_legacy,
+            secret_status,
+            secret_set,
+            secret_clear,
+            secret_test,
+            cancel_optimization,
+            preview_sweep,
+            run_sweep,
+            cancel_sweep,
+            export_sweep_csv,
+            debug_prompt,
+            parse_log_progress,
+            summarize_log,
+            extract_results,
+            history_list,
+            history_get,
+            history_insert,
+            history_set_tags,
+            history_delete,
+            compare_runs,
+            diagnose_infeasibility,
+            model_statistics,
+            detect_numeric_issues,
+            load_solution,
+            history_attach_solution,
+            history_solution,
+            env_profiles_list,
+            env_profile_save,
+            env_profile_delete,
+            history_import_legacy
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,773 @@
+use reqwest::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::Notify;
+
+use crate::secrets;
+
+// AI解析に使う LLM の呼び出し先を切り替えるための抽象化
+// (Gemini と、OpenAI 互換の /v1/chat/completions を話すサーバー)
+
+pub const GEMINI_DEFAULT_BASE_URL: &str = "https://generativelanguage.googleapis.com/v1beta";
+pub const OPENAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ProviderKind {
+    #[default]
+    Gemini,
+    // llama.cpp / vLLM / Ollama などのローカルサーバーもこちら
+    OpenAiCompatible,
+    // LLM を使わず、ログからルールに基づくレポートを作る
+    Offline,
+}
+
+#[derive(Debug, Clone)]
+pub struct ProviderConfig {
+    pub kind: ProviderKind,
+    // 空なら各プロバイダの既定URL
+    pub base_url: Option<String>,
+    pub model: String,
+    pub api_key: String,
+}
+
+impl ProviderConfig {
+    // Gemini で APIキーが無い場合も、エラーにせずオフラインのレポートに切り替える
+    pub fn is_offline(&self) -> bool {
+        match self.kind {
+            ProviderKind::Offline => true,
+            ProviderKind::Gemini => self.api_key.trim().is_empty(),
+            ProviderKind::OpenAiCompatible => false,
+        }
+    }
+}
+
+// 使用トークン数 (プロバイダが返した場合のみ)
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TokenUsage {
+    pub prompt_tokens: Option<u64>,
+    pub completion_tokens: Option<u64>,
+    pub total_tokens: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatRole::System => "system",
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "system" => ChatRole::System,
+            "assistant" => ChatRole::Assistant,
+            _ => ChatRole::User,
+        }
+    }
+}
+
+// 会話の1メッセージ
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn new(role: ChatRole, content: impl Into<String>) -> Self {
+        ChatMessage {
+            role,
+            content: content.into(),
+        }
+    }
+}
+
+// 同じ役割が続くメッセージは1つにまとめる (Gemini は user と model の交互を前提にしている)
+fn merge_turns(messages: &[ChatMessage]) -> Vec<ChatMessage> {
+    let mut merged: Vec<ChatMessage> = Vec::new();
+    for message in messages {
+        match merged.last_mut() {
+            Some(last) if last.role == message.role => {
+                last.content.push_str("\n\n");
+                last.content.push_str(&message.content);
+            }
+            _ => merged.push(message.clone()),
+        }
+    }
+    merged
+}
+
+// ストリーミング応答の1イベント分
+#[derive(Debug, Default)]
+pub struct StreamDelta {
+    pub text: Option<String>,
+    pub usage: Option<TokenUsage>,
+}
+
+// ストリーミング生成の結果
+#[derive(Debug)]
+pub struct StreamOutcome {
+    pub text: String,
+    pub usage: Option<TokenUsage>,
+    pub cancelled: bool,
+}
+
+pub trait LlmProvider: Send + Sync {
+    // エラーメッセージ用の表示名
+    fn name(&self) -> &'static str;
+
+    // エラーメッセージから伏せるためのキー
+    fn api_key(&self) -> &str;
+
+    // 1回分の生成リクエストを組み立てる
+    fn build_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder;
+
+    // レスポンスJSONから生成されたテキストを取り出す
+    fn parse_response(&self, body: &Value) -> Option<String>;
+
+    // SSE で逐次返すリクエストを組み立てる
+    fn build_stream_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder;
+
+    // SSE の data 行1つ分のJSONを解釈する
+    fn parse_stream_event(&self, event: &Value) -> StreamDelta;
+}
+
+pub struct GeminiProvider {
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+// system は systemInstruction に、assistant は model の役割にする
+fn gemini_body(messages: &[ChatMessage]) -> Value {
+    let merged = merge_turns(messages);
+    let system: Vec<&str> = merged
+        .iter()
+        .filter(|m| m.role == ChatRole::System)
+        .map(|m| m.content.as_str())
+        .collect();
+    let contents: Vec<Value> = merged
+        .iter()
+        .filter(|m| m.role != ChatRole::System)
+        .map(|m| {
+            let role = if m.role == ChatRole::Assistant {
+                "model"
+            } else {
+                "user"
+            };
+            json!({ "role": role, "parts": [{ "text": m.content }] })
+        })
+        .collect();
+    let mut body = json!({ "contents": contents });
+    if !system.is_empty() {
+        body["systemInstruction"] = json!({ "parts": [{ "text": system.join("\n\n") }] });
+    }
+    body
+}
+
+fn openai_messages(messages: &[ChatMessage]) -> Value {
+    merge_turns(messages)
+        .iter()
+        .map(|m| json!({ "role": m.role.as_str(), "content": m.content }))
+        .collect()
+}
+
+impl LlmProvider for GeminiProvider {
+    fn name(&self) -> &'static str {
+        "Gemini"
+    }
+
+    fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    fn build_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder {
+        // キーは URL に入れるとプロキシのログなどに残るのでヘッダーで送る
+        let url = format!("{}/models/{}:generateContent", self.base_url, self.model);
+        client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&gemini_body(messages))
+    }
+
+    fn parse_response(&self, body: &Value) -> Option<String> {
+        body["candidates"][0]["content"]["parts"][0]["text"]
+            .as_str()
+            .map(|s| s.to_string())
+    }
+
+    fn build_stream_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder {
+        let url = format!(
+            "{}/models/{}:streamGenerateContent?alt=sse",
+            self.base_url, self.model
+        );
+        client
+            .post(url)
+            .header("x-goog-api-key", &self.api_key)
+            .json(&gemini_body(messages))
+    }
+
+    fn parse_stream_event(&self, event: &Value) -> StreamDelta {
+        let text = event["candidates"][0]["content"]["parts"]
+            .as_array()
+            .map(|parts| {
+                parts
+                    .iter()
+                    .filter_map(|p| p["text"].as_str())
+                    .collect::<String>()
+            })
+            .filter(|t| !t.is_empty());
+        // usageMetadata は各チャンクに累計値で入ってくる
+        let usage = event.get("usageMetadata").map(|u| TokenUsage {
+            prompt_tokens: u["promptTokenCount"].as_u64(),
+            completion_tokens: u["candidatesTokenCount"].as_u64(),
+            total_tokens: u["totalTokenCount"].as_u64(),
+        });
+        StreamDelta { text, usage }
+    }
+}
+
+pub struct OpenAiCompatibleProvider {
+    base_url: String,
+    model: String,
+    api_key: String,
+}
+
+impl LlmProvider for OpenAiCompatibleProvider {
+    fn name(&self) -> &'static str {
+        "OpenAI-compatible"
+    }
+
+    fn api_key(&self) -> &str {
+        &self.api_key
+    }
+
+    fn build_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder {
+        let url = format!("{}/chat/completions", self.base_url);
+        let body = json!({
+            "model": self.model,
+            "messages": openai_messages(messages),
+        });
+        let req = client.post(url).json(&body);
+        // ローカルサーバーではキー不要なことが多い
+        if self.api_key.is_empty() {
+            req
+        } else {
+            req.bearer_auth(&self.api_key)
+        }
+    }
+
+    fn parse_response(&self, body: &Value) -> Option<String> {
+        body["choices"][0]["message"]["content"]
+            .as_str()
+            .map(|s| s.to_string())
+    }
+
+    fn build_stream_request(&self, client: &Client, messages: &[ChatMessage]) -> RequestBuilder {
+        let url = format!("{}/chat/completions", self.base_url);
+        let body = json!({
+            "model": self.model,
+            "messages": openai_messages(messages),
+            "stream": true,
+            // 最後のチャンクで使用トークン数を返してもらう
+            "stream_options": { "include_usage": true },
+        });
+        let req = client.post(url).json(&body);
+        if self.api_key.is_empty() {
+            req
+        } else {
+            req.bearer_auth(&self.api_key)
+        }
+    }
+
+    fn parse_stream_event(&self, event: &Value) -> StreamDelta {
+        let text = event["choices"][0]["delta"]["content"]
+            .as_str()
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string());
+        let usage = event
+            .get("usage")
+            .filter(|u| u.is_object())
+            .map(|u| TokenUsage {
+                prompt_tokens: u["prompt_tokens"].as_u64(),
+                completion_tokens: u["completion_tokens"].as_u64(),
+                total_tokens: u["total_tokens"].as_u64(),
+            });
+        StreamDelta { text, usage }
+    }
+}
+
+fn normalize_base_url(base_url: &Option<String>, default: &str) -> String {
+    base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .unwrap_or(default)
+        .trim_end_matches('/')
+        .to_string()
+}
+
+pub fn create_provider(config: &ProviderConfig) -> Result<Box<dyn LlmProvider>, String> {
+    if config.model.trim().is_empty() {
+        return Err("モデル名が設定されていません。".to_string());
+    }
+    match config.kind {
+        ProviderKind::Gemini => {
+            if config.api_key.is_empty() {
+                return Err("APIキーが設定されていません。".to_string());
+            }
+            Ok(Box::new(GeminiProvider {
+                base_url: normalize_base_url(&config.base_url, GEMINI_DEFAULT_BASE_URL),
+                model: config.model.clone(),
+                api_key: config.api_key.clone(),
+            }))
+        }
+        ProviderKind::OpenAiCompatible => Ok(Box::new(OpenAiCompatibleProvider {
+            base_url: normalize_base_url(&config.base_url, OPENAI_DEFAULT_BASE_URL),
+            model: config.model.clone(),
+            api_key: config.api_key.clone(),
+        })),
+        ProviderKind::Offline => Err("オフラインのレポートでは LLM を呼び出せません。".to_string()),
+    }
+}
+
+// プロンプトを送り、生成されたテキストを返す
+pub async fn generate(provider: &dyn LlmProvider, prompt: &str) -> Result<String, String> {
+    generate_chat(provider, &[ChatMessage::new(ChatRole::User, prompt)]).await
+}
+
+// 会話の履歴を送り、次の応答を返す
+pub async fn generate_chat(
+    provider: &dyn LlmProvider,
+    messages: &[ChatMessage],
+) -> Result<String, String> {
+    send_chat(provider, messages)
+        .await
+        .map_err(|e| secrets::redact(&e, &[provider.api_key()]))
+}
+
+async fn send_chat(provider: &dyn LlmProvider, messages: &[ChatMessage]) -> Result<String, String> {
+    let client = Client::new();
+    let res = provider
+        .build_request(&client, messages)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let res_text = res.text().await.map_err(|e| e.to_string())?;
+
+    // エラーハンドリング強化
+    let json: Value = serde_json::from_str(&res_text).map_err(|_| {
+        format!(
+            "{} API returned invalid JSON: {}",
+            provider.name(),
+            res_text
+        )
+    })?;
+
+    provider
+        .parse_response(&json)
+        .ok_or_else(|| format!("API Error: {}", res_text))
+}
+
+// 受け取ったバイト列を行に区切る
+// マルチバイト文字がチャンク境界で分断されても壊れないよう、改行まで揃ってからデコードする
+#[derive(Default)]
+struct LineBuffer {
+    buffer: Vec<u8>,
+}
+
+impl LineBuffer {
+    // 揃った行を返す (改行は含めない)
+    fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(chunk);
+        let mut lines = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            lines.push(String::from_utf8_lossy(&line[..line.len() - 1]).into_owned());
+        }
+        lines
+    }
+
+    // 改行で終わっていない最後の行
+    fn finish(self) -> Option<String> {
+        if self.buffer.is_empty() {
+            None
+        } else {
+            Some(String::from_utf8_lossy(&self.buffer).into_owned())
+        }
+    }
+}
+
+// SSE の1行を処理する ("data: {...}" 以外は無視)
+fn handle_sse_line(
+    provider: &dyn LlmProvider,
+    line: &str,
+    outcome: &mut StreamOutcome,
+    on_chunk: &mut impl FnMut(&str),
+) -> Result<(), String> {
+    let Some(data) = line.trim_end_matches('\r').strip_prefix("data:") else {
+        return Ok(());
+    };
+    let data = data.trim();
+    if data.is_empty() || data == "[DONE]" {
+        return Ok(());
+    }
+    let event: Value = serde_json::from_str(data)
+        .map_err(|_| format!("{} API returned invalid JSON: {}", provider.name(), data))?;
+    if event.get("error").is_some() {
+        return Err(format!("API Error: {}", data));
+    }
+    let delta = provider.parse_stream_event(&event);
+    if let Some(text) = delta.text {
+        on_chunk(&text);
+        outcome.text.push_str(&text);
+    }
+    if delta.usage.is_some() {
+        outcome.usage = delta.usage;
+    }
+    Ok(())
+}
+
+// ストリーミングで生成し、届いた断片ごとに on_chunk を呼ぶ
+// cancel が通知されたらその時点までの内容で打ち切る
+pub async fn generate_stream(
+    provider: &dyn LlmProvider,
+    prompt: &str,
+    cancel: &Notify,
+    on_chunk: impl FnMut(&str),
+) -> Result<StreamOutcome, String> {
+    let messages = [ChatMessage::new(ChatRole::User, prompt)];
+    generate_chat_stream(provider, &messages, cancel, on_chunk).await
+}
+
+// 会話の履歴を送り、次の応答をストリーミングで受け取る
+pub async fn generate_chat_stream(
+    provider: &dyn LlmProvider,
+    messages: &[ChatMessage],
+    cancel: &Notify,
+    on_chunk: impl FnMut(&str),
+) -> Result<StreamOutcome, String> {
+    send_chat_stream(provider, messages, cancel, on_chunk)
+        .await
+        .map_err(|e| secrets::redact(&e, &[provider.api_key()]))
+}
+
+async fn send_chat_stream(
+    provider: &dyn LlmProvider,
+    messages: &[ChatMessage],
+    cancel: &Notify,
+    mut on_chunk: impl FnMut(&str),
+) -> Result<StreamOutcome, String> {
+    let client = Client::new();
+    let mut res = provider
+        .build_stream_request(&client, messages)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if !res.status().is_success() {
+        let res_text = res.text().await.map_err(|e| e.to_string())?;
+        return Err(format!("API Error: {}", res_text));
+    }
+
+    let mut outcome = StreamOutcome {
+        text: String::new(),
+        usage: None,
+        cancelled: false,
+    };
+    let mut lines = LineBuffer::default();
+
+    loop {
+        let chunk = tokio::select! {
+            _ = cancel.notified() => {
+                outcome.cancelled = true;
+                break;
+            }
+            chunk = res.chunk() => chunk.map_err(|e| e.to_string())?,
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        for line in lines.push(&chunk) {
+            handle_sse_line(provider, &line, &mut outcome, &mut on_chunk)?;
+        }
+    }
+
+    if !outcome.cancelled {
+        if let Some(line) = lines.finish() {
+            handle_sse_line(provider, &line, &mut outcome, &mut on_chunk)?;
+        }
+    }
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+
+    fn messages(turns: &[(ChatRole, &str)]) -> Vec<ChatMessage> {
+        turns
+            .iter()
+            .map(|(role, text)| ChatMessage::new(*role, *text))
+            .collect()
+    }
+
+    // 1回だけ応答するHTTPサーバーを立て、そのベースURLを返す
+    fn serve_once(status: &'static str, body: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+            }
+            reader.read_exact(&mut vec![0; length]).unwrap();
+            let response = format!(
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn merges_consecutive_turns_of_the_same_role() {
+        let merged = merge_turns(&messages(&[
+            (ChatRole::System, "s1"),
+            (ChatRole::System, "s2"),
+            (ChatRole::User, "log"),
+            (ChatRole::User, "question"),
+            (ChatRole::Assistant, "answer"),
+            (ChatRole::User, "again"),
+        ]));
+        assert_eq!(
+            merged,
+            messages(&[
+                (ChatRole::System, "s1\n\ns2"),
+                (ChatRole::User, "log\n\nquestion"),
+                (ChatRole::Assistant, "answer"),
+                (ChatRole::User, "again"),
+            ])
+        );
+        assert!(merge_turns(&[]).is_empty());
+    }
+
+    #[test]
+    fn maps_roles_for_each_provider() {
+        let turns = messages(&[
+            (ChatRole::System, "be brief"),
+            (ChatRole::User, "q1"),
+            (ChatRole::Assistant, "a1"),
+            (ChatRole::User, "q2"),
+        ]);
+
+        // Gemini: system は systemInstruction、assistant は model
+        let body = gemini_body(&turns);
+        assert_eq!(
+            body["systemInstruction"],
+            json!({ "parts": [{ "text": "be brief" }] })
+        );
+        let roles: Vec<&str> = body["contents"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["role"].as_str().unwrap())
+            .collect();
+        assert_eq!(roles, vec!["user", "model", "user"]);
+        assert_eq!(body["contents"][1]["parts"][0]["text"], "a1");
+        assert!(gemini_body(&turns[1..2]).get("systemInstruction").is_none());
+
+        // OpenAI 互換: そのままの役割名
+        assert_eq!(
+            openai_messages(&turns),
+            json!([
+                { "role": "system", "content": "be brief" },
+                { "role": "user", "content": "q1" },
+                { "role": "assistant", "content": "a1" },
+                { "role": "user", "content": "q2" },
+            ])
+        );
+    }
+
+    #[test]
+    fn creates_providers_from_config() {
+        let config = |kind, base_url: Option<&str>, api_key: &str| ProviderConfig {
+            kind,
+            base_url: base_url.map(str::to_string),
+            model: "m".to_string(),
+            api_key: api_key.to_string(),
+        };
+        assert!(create_provider(&config(ProviderKind::Gemini, None, "")).is_err());
+        assert!(create_provider(&config(ProviderKind::Offline, None, "")).is_err());
+        assert!(config(ProviderKind::Gemini, None, " ").is_offline());
+        let provider = create_provider(&config(ProviderKind::OpenAiCompatible, None, "")).unwrap();
+        assert_eq!(provider.name(), "OpenAI-compatible");
+        assert_eq!(
+            normalize_base_url(&Some(" http://localhost:8080/v1/ ".to_string()), "x"),
+            "http://localhost:8080/v1"
+        );
+        assert_eq!(normalize_base_url(&Some(String::new()), "x"), "x");
+    }
+
+    #[tokio::test]
+    async fn error_bodies_are_redacted() {
+        let key = "local-secret-key-1234";
+        let base_url = serve_once(
+            "401 Unauthorized",
+            r#"{"error": {"message": "invalid key local-secret-key-1234"}}"#,
+        );
+        let provider = create_provider(&ProviderConfig {
+            kind: ProviderKind::OpenAiCompatible,
+            base_url: Some(base_url),
+            model: "m".to_string(),
+            api_key: key.to_string(),
+        })
+        .unwrap();
+        let err = generate(provider.as_ref(), "hello").await.unwrap_err();
+        assert!(err.starts_with("API Error: "), "{}", err);
+        assert!(err.contains("invalid key ****"), "{}", err);
+        assert!(!err.contains(key));
+    }
+
+    fn gemini() -> GeminiProvider {
+        GeminiProvider {
+            base_url: GEMINI_DEFAULT_BASE_URL.to_string(),
+            model: "m".to_string(),
+            api_key: "k".to_string(),
+        }
+    }
+
+    fn openai() -> OpenAiCompatibleProvider {
+        OpenAiCompatibleProvider {
+            base_url: OPENAI_DEFAULT_BASE_URL.to_string(),
+            model: "m".to_string(),
+            api_key: String::new(),
+        }
+    }
+
+    // SSE の行を順に処理し、結果と on_chunk に渡された断片を返す
+    fn run_sse(
+        provider: &dyn LlmProvider,
+        lines: &[&str],
+    ) -> Result<(StreamOutcome, Vec<String>), String> {
+        let mut outcome = StreamOutcome {
+            text: String::new(),
+            usage: None,
+            cancelled: false,
+        };
+        let mut chunks = Vec::new();
+        for line in lines {
+            handle_sse_line(provider, line, &mut outcome, &mut |t: &str| {
+                chunks.push(t.to_string())
+            })?;
+        }
+        Ok((outcome, chunks))
+    }
+
+    #[test]
+    fn parses_gemini_stream() {
+        let (outcome, chunks) = run_sse(
+            &gemini(),
+            &[
+                r#"data: {"candidates": [{"content": {"parts": [{"text": "最適"}, {"text": "解"}], "role": "model"}}], "usageMetadata": {"promptTokenCount": 10, "totalTokenCount": 10}}"#,
+                "",
+                r#"data: {"candidates": [{"content": {"parts": [{"text": "です。"}], "role": "model"}, "finishReason": "STOP"}], "usageMetadata": {"promptTokenCount": 10, "candidatesTokenCount": 5, "totalTokenCount": 15}}"#,
+                "\r",
+            ],
+        )
+        .unwrap();
+        assert_eq!(chunks, vec!["最適解", "です。"]);
+        assert_eq!(outcome.text, "最適解です。");
+        // 累計値なので最後のものが残る
+        assert_eq!(
+            outcome.usage,
+            Some(TokenUsage {
+                prompt_tokens: Some(10),
+                completion_tokens: Some(5),
+                total_tokens: Some(15),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_openai_stream_with_usage_only_final_chunk() {
+        let (outcome, chunks) = run_sse(
+            &openai(),
+            &[
+                ": keep-alive",
+                r#"data: {"choices": [{"index": 0, "delta": {"role": "assistant", "content": ""}}], "usage": null}"#,
+                r#"data: {"choices": [{"index": 0, "delta": {"content": "Gap"}}], "usage": null}"#,
+                r#"data:{"choices": [{"index": 0, "delta": {"content": " 0.01%"}, "finish_reason": "stop"}]}"#,
+                r#"data: {"choices": [], "usage": {"prompt_tokens": 20, "completion_tokens": 3, "total_tokens": 23}}"#,
+                "data: [DONE]",
+            ],
+        )
+        .unwrap();
+        assert_eq!(chunks, vec!["Gap", " 0.01%"]);
+        assert_eq!(outcome.text, "Gap 0.01%");
+        assert_eq!(outcome.usage.unwrap().total_tokens, Some(23));
+    }
+
+    #[test]
+    fn stream_errors_are_reported() {
+        let err = run_sse(
+            &openai(),
+            &[r#"data: {"error": {"message": "rate limited"}}"#],
+        )
+        .unwrap_err();
+        assert!(err.starts_with("API Error: "));
+        assert!(err.contains("rate limited"));
+        assert!(run_sse(&gemini(), &["data: {not json"]).is_err());
+    }
+
+    #[test]
+    fn line_buffer_keeps_multibyte_chars_split_across_chunks() {
+        let event = "data: {\"choices\": [{\"delta\": {\"content\": \"実行不可能\"}}]}\n";
+        let bytes = event.as_bytes();
+        // 「実」の3バイトの途中で分ける
+        let split = event.find('実').unwrap() + 1;
+
+        let mut lines = LineBuffer::default();
+        assert!(lines.push(&bytes[..split]).is_empty());
+        let received = lines.push(&bytes[split..]);
+        assert_eq!(received, vec![event.trim_end()]);
+        let (outcome, _) = run_sse(&openai(), &[received[0].as_str()]).unwrap();
+        assert_eq!(outcome.text, "実行不可能");
+        assert!(lines.finish().is_none());
+
+        // 改行で終わらない最後の行も取り出せる
+        let mut lines = LineBuffer::default();
+        assert_eq!(lines.push(b"data: a\ndata: [DO"), vec!["data: a"]);
+        assert!(lines.push(b"NE]").is_empty());
+        assert_eq!(lines.finish().as_deref(), Some("data: [DONE]"));
+    }
+}
@@ -0,0 +1,382 @@
+use serde::Serialize;
+
+// Gurobiログの表形式部分（分枝限定法・バリア法・単体法）を解析し、
+// 1行ごとに型付きの進捗イベントへ変換する
+
+// 現在どの表の中にいるか
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Section {
+    None,
+    Mip,
+    Barrier,
+    Simplex,
+}
+
+// 分枝限定法の1行
+// 例: "H  123    45                    -10.0000000  -12.00000  20.0%  12.3    2s"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MipProgress {
+    // 行頭のマーカー ('H': ヒューリスティック解, '*': 分枝で見つかった解)
+    pub marker: Option<char>,
+    pub explored: u64,
+    pub unexplored: u64,
+    // 現在ノードの目的関数値 (数値でない場合は node_status に入る)
+    pub objective: Option<f64>,
+    // "cutoff" / "infeasible" / "postponed" など
+    pub node_status: Option<String>,
+    pub depth: Option<u64>,
+    pub int_inf: Option<u64>,
+    pub incumbent: Option<f64>,
+    pub best_bound: Option<f64>,
+    // 単位は % (例: 1.82% -> 1.82)
+    pub gap: Option<f64>,
+    pub iterations_per_node: Option<f64>,
+    // 経過時間 [s]
+    pub time: f64,
+}
+
+// バリア法の1行
+// 例: "   5   1.23456789e+05 -2.34567890e+04  1.23e+03 4.56e+01  1.23e+02     0s"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BarrierProgress {
+    pub iteration: u64,
+    pub primal_objective: f64,
+    pub dual_objective: f64,
+    pub primal_residual: f64,
+    pub dual_residual: f64,
+    pub complementarity: f64,
+    pub time: f64,
+}
+
+// 単体法の1行
+// 例: "     120   -1.2345000e+02   1.234000e+01   0.000000e+00      0s"
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimplexProgress {
+    pub iteration: u64,
+    pub objective: f64,
+    pub primal_infeasibility: f64,
+    pub dual_infeasibility: f64,
+    pub time: f64,
+}
+
+// フロントエンドへ送る "progress" イベントの中身
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProgressEvent {
+    Mip(MipProgress),
+    Barrier(BarrierProgress),
+    Simplex(SimplexProgress),
+}
+
+// 行を順番に流し込むと、表の見出しを追跡しながら進捗イベントを返す
+pub struct LogParser {
+    section: Section,
+}
+
+impl Default for LogParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogParser {
+    pub fn new() -> Self {
+        LogParser {
+            section: Section::None,
+        }
+    }
+
+    pub fn feed(&mut self, line: &str) -> Option<ProgressEvent> {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return None;
+        }
+
+        // 見出し行で表の種類を切り替える
+        if trimmed.contains("Expl Unexpl") {
+            self.section = Section::Mip;
+            return None;
+        }
+        if trimmed.starts_with("Iter") && trimmed.contains("Compl") {
+            self.section = Section::Barrier;
+            return None;
+        }
+        if trimmed.starts_with("Iteration") && trimmed.contains("Objective") {
+            self.section = Section::Simplex;
+            return None;
+        }
+
+        match self.section {
+            Section::Mip => parse_mip_line(trimmed).map(ProgressEvent::Mip),
+            Section::Barrier => parse_barrier_line(trimmed).map(ProgressEvent::Barrier),
+            Section::Simplex => parse_simplex_line(trimmed).map(ProgressEvent::Simplex),
+            Section::None => None,
+        }
+    }
+}
+
+// ログ全体をまとめて解析する (履歴からのグラフ再構築用)
+pub fn parse_progress(log: &str) -> Vec<ProgressEvent> {
+    let mut parser = LogParser::new();
+    log.lines().filter_map(|line| parser.feed(line)).collect()
+}
+
+// "-" は値なしとして扱う
+fn parse_optional_f64(token: &str) -> Result<Option<f64>, ()> {
+    if token == "-" {
+        return Ok(None);
+    }
+    token.parse::<f64>().map(Some).map_err(|_| ())
+}
+
+// "12s" -> 12.0
+fn parse_time(token: &str) -> Option<f64> {
+    token.strip_suffix('s')?.parse::<f64>().ok()
+}
+
+// "1.82%" -> Some(1.82), "-" -> None
+fn parse_gap(token: &str) -> Result<Option<f64>, ()> {
+    if token == "-" {
+        return Ok(None);
+    }
+    let value = token.strip_suffix('%').ok_or(())?;
+    value.parse::<f64>().map(Some).map_err(|_| ())
+}
+
+// バリア法では "12*" のように反復番号に印が付くことがある
+fn parse_iteration(token: &str) -> Option<u64> {
+    token.trim_end_matches('*').parse::<u64>().ok()
+}
+
+fn parse_mip_line(line: &str) -> Option<MipProgress> {
+    // 行頭のマーカーを取り除く ("H    0" や "*12345" の両方に対応)
+    let first = line.chars().next()?;
+    let (marker, rest) = if first.is_ascii_alphabetic() || first == '*' {
+        (Some(first), &line[first.len_utf8()..])
+    } else {
+        (None, line)
+    };
+
+    let tokens: Vec<&str> = rest.split_whitespace().collect();
+    // Expl Unexpl + (Obj Depth IntInf のうち0〜3個) + Incumbent BestBd Gap It/Node Time
+    if tokens.len() < 7 || tokens.len() > 10 {
+        return None;
+    }
+    let n = tokens.len();
+
+    let explored = tokens[0].parse::<u64>().ok()?;
+    let unexplored = tokens[1].parse::<u64>().ok()?;
+    let time = parse_time(tokens[n - 1])?;
+    let iterations_per_node = parse_optional_f64(tokens[n - 2]).ok()?;
+    let gap = parse_gap(tokens[n - 3]).ok()?;
+    let best_bound = parse_optional_f64(tokens[n - 4]).ok()?;
+    let incumbent = parse_optional_f64(tokens[n - 5]).ok()?;
+
+    let middle = &tokens[2..n - 5];
+    let mut objective = None;
+    let mut node_status = None;
+    let mut depth = None;
+    let mut int_inf = None;
+
+    let mut set_objective = |token: &str| {
+        if let Ok(v) = token.parse::<f64>() {
+            objective = Some(v);
+        } else if token.chars().all(|c| c.is_ascii_alphabetic()) {
+            node_status = Some(token.to_string());
+        } else {
+            return false;
+        }
+        true
+    };
+
+    match middle.len() {
+        0 => {}
+        // '*' 行などは深さのみ
+        1 => depth = Some(middle[0].parse::<u64>().ok()?),
+        // "cutoff 25" のように状態と深さ
+        2 => {
+            if !set_objective(middle[0]) {
+                return None;
+            }
+            depth = Some(middle[1].parse::<u64>().ok()?);
+        }
+        _ => {
+            if !set_objective(middle[0]) {
+                return None;
+            }
+            depth = Some(middle[1].parse::<u64>().ok()?);
+            int_inf = Some(middle[2].parse::<u64>().ok()?);
+        }
+    }
+
+    Some(MipProgress {
+        marker,
+        explored,
+        unexplored,
+        objective,
+        node_status,
+        depth,
+        int_inf,
+        incumbent,
+        best_bound,
+        gap,
+        iterations_per_node,
+        time,
+    })
+}
+
+fn parse_barrier_line(line: &str) -> Option<BarrierProgress> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 7 {
+        return None;
+    }
+    Some(BarrierProgress {
+        iteration: parse_iteration(tokens[0])?,
+        primal_objective: tokens[1].parse().ok()?,
+        dual_objective: tokens[2].parse().ok()?,
+        primal_residual: tokens[3].parse().ok()?,
+        dual_residual: tokens[4].parse().ok()?,
+        complementarity: tokens[5].parse().ok()?,
+        time: parse_time(tokens[6])?,
+    })
+}
+
+fn parse_simplex_line(line: &str) -> Option<SimplexProgress> {
+    let tokens: Vec<&str> = line.split_whitespace().collect();
+    if tokens.len() != 5 {
+        return None;
+    }
+    Some(SimplexProgress {
+        iteration: parse_iteration(tokens[0])?,
+        objective: tokens[1].parse().ok()?,
+        primal_infeasibility: tokens[2].parse().ok()?,
+        dual_infeasibility: tokens[3].parse().ok()?,
+        time: parse_time(tokens[4])?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIP_LOG: &str = "\
+Root relaxation: objective 1.234500e+03, 512 iterations, 0.02 seconds (0.01 work units)
+
+    Nodes    |    Current Node    |     Objective Bounds      |     Work
+ Expl Unexpl |  Obj  Depth IntInf | Incumbent    BestBd   Gap | It/Node Time
+
+     0     0 1234.50000    0   45          - 1234.50000      -     -    0s
+H    0     0                    1500.0000000 1234.50000  17.7%     -    0s
+     0     2 1236.10000    0   43 1500.00000 1236.10000  17.6%     -    1s
+*  812   640              37    1290.0000000 1240.20000  3.86%  21.4    5s
+  1024   700    infeasible   42      1290.00000 1241.00000  3.80%  20.1    6s
+  2048   812 1250.30000   31   12 1290.00000 1242.50000  3.68%  19.8   10s
+
+Cutting planes:
+  Gomory: 12
+";
+
+    #[test]
+    fn parses_branch_and_bound_rows() {
+        let events = parse_progress(MIP_LOG);
+        let rows: Vec<&MipProgress> = events
+            .iter()
+            .map(|e| match e {
+                ProgressEvent::Mip(p) => p,
+                other => panic!("{:?}", other),
+            })
+            .collect();
+        assert_eq!(rows.len(), 6);
+
+        assert_eq!(rows[0].objective, Some(1234.5));
+        assert_eq!(rows[0].depth, Some(0));
+        assert_eq!(rows[0].int_inf, Some(45));
+        assert_eq!(rows[0].incumbent, None);
+        assert_eq!(rows[0].gap, None);
+
+        assert_eq!(rows[1].marker, Some('H'));
+        assert_eq!(rows[1].incumbent, Some(1500.0));
+        assert_eq!(rows[1].gap, Some(17.7));
+
+        assert_eq!(rows[3].marker, Some('*'));
+        assert_eq!(rows[3].explored, 812);
+        assert_eq!(rows[3].depth, Some(37));
+        assert_eq!(rows[3].objective, None);
+        assert_eq!(rows[3].iterations_per_node, Some(21.4));
+
+        assert_eq!(rows[4].node_status.as_deref(), Some("infeasible"));
+        assert_eq!(rows[4].depth, Some(42));
+
+        assert_eq!(rows[5].best_bound, Some(1242.5));
+        assert_eq!(rows[5].time, 10.0);
+    }
+
+    #[test]
+    fn parses_barrier_rows() {
+        let log = "\
+Barrier statistics:
+ AA' NZ     : 1.234e+05
+                  Objective                Residual
+Iter       Primal          Dual         Primal    Dual     Compl     Time
+   0   1.23456789e+05 -2.34567890e+04  1.23e+03 4.56e+01  1.23e+02     0s
+   1   9.87654321e+04 -1.00000000e+04  2.00e+02 3.00e+00  5.00e+01     0s
+  12*  1.00000000e+03  1.00000000e+03  1.00e-09 1.00e-10  1.00e-08     2s
+
+Barrier solved model in 12 iterations and 2.01 seconds (1.50 work units)
+";
+        let events = parse_progress(log);
+        assert_eq!(events.len(), 3);
+        let ProgressEvent::Barrier(last) = &events[2] else {
+            panic!("{:?}", events[2]);
+        };
+        assert_eq!(last.iteration, 12);
+        assert_eq!(last.primal_objective, 1000.0);
+        assert_eq!(last.complementarity, 1e-8);
+        assert_eq!(last.time, 2.0);
+    }
+
+    #[test]
+    fn parses_simplex_rows() {
+        let log = "\
+Iteration    Objective       Primal Inf.    Dual Inf.      Time
+       0   -1.2345000e+02   1.234000e+01   0.000000e+00      0s
+     120   -1.0000000e+02   0.000000e+00   0.000000e+00      1s
+
+Solved in 120 iterations and 0.52 seconds (0.10 work units)
+Optimal objective -1.000000000e+02
+";
+        let events = parse_progress(log);
+        assert_eq!(
+            events,
+            vec![
+                ProgressEvent::Simplex(SimplexProgress {
+                    iteration: 0,
+                    objective: -123.45,
+                    primal_infeasibility: 12.34,
+                    dual_infeasibility: 0.0,
+                    time: 0.0,
+                }),
+                ProgressEvent::Simplex(SimplexProgress {
+                    iteration: 120,
+                    objective: -100.0,
+                    primal_infeasibility: 0.0,
+                    dual_infeasibility: 0.0,
+                    time: 1.0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_lines_outside_tables() {
+        let log = "\
+Gurobi Optimizer version 11.0.0 build v11.0.0rc2 (linux64)
+Optimize a model with 100 rows, 200 columns and 400 nonzeros
+     0     0 1234.50000    0   45          - 1234.50000      -     -    0s
+";
+        assert!(parse_progress(log).is_empty());
+    }
+}
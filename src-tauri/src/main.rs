@@ -2,5 +2,25 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // --headless が付いていれば GUI を起動せずに実行する
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if gurobi_optimizer_desktop_lib::is_headless(&args) {
+        attach_parent_console();
+        std::process::exit(gurobi_optimizer_desktop_lib::run_headless(&args));
+    }
     gurobi_optimizer_desktop_lib::run()
 }
+
+// リリース版は GUI のサブシステムでビルドされ、コンソールを持たない
+// ヘッドレスモードでは起動元のコンソールにつなぎ、ログと終了コードを呼び出し側に返す
+#[cfg(windows)]
+fn attach_parent_console() {
+    use windows_sys::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+    // コンソールから起動されていない場合 (リダイレクトのみ等) は失敗するが、そのままでよい
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+#[cfg(not(windows))]
+fn attach_parent_console() {}
@@ -0,0 +1,1004 @@
+use flate2::read::MultiGzDecoder;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+// 解く前にモデルファイル (.lp / .mps、gzip 圧縮も可) を読み、
+// 行数・列数・非ゼロ数・変数の種類と、Gurobi の "Coefficient statistics" と同じ範囲を集計する
+//
+//   Matrix range     [1e+00, 1e+06]
+//   Objective range  [1e+00, 1e+00]
+//   Bounds range     [1e+00, 1e+04]
+//   RHS range        [1e+00, 1e+10]
+
+// これ以上の値は無限大として扱う (GRB.INFINITY は 1e100、1e30 以上は無限大とみなされる)
+const INFINITE_VALUE: f64 = 1e30;
+// 数値的に危ういとみなす目安
+const LARGE_VALUE: f64 = 1e9;
+const SMALL_VALUE: f64 = 1e-9;
+const MAX_RANGE_RATIO: f64 = 1e9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ModelFormat {
+    Lp,
+    Mps,
+}
+
+// 0 と無限大を除いた絶対値の範囲
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CoefficientRange {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RangeTarget {
+    Matrix,
+    Objective,
+    Bounds,
+    Rhs,
+}
+
+impl RangeTarget {
+    fn label(self) -> &'static str {
+        match self {
+            RangeTarget::Matrix => "制約行列の係数",
+            RangeTarget::Objective => "目的関数の係数",
+            RangeTarget::Bounds => "変数の上下限",
+            RangeTarget::Rhs => "右辺",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RangeWarning {
+    pub target: RangeTarget,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelStats {
+    pub format: ModelFormat,
+    pub name: Option<String>,
+    pub rows: u64,
+    pub columns: u64,
+    pub nonzeros: u64,
+    // Gurobi の "Variable types" と同じく integer は binary を含む
+    pub continuous: u64,
+    pub integer: u64,
+    pub binary: u64,
+    pub semi_continuous: u64,
+    pub sos: u64,
+    // 指示制約などの一般制約
+    pub general_constraints: u64,
+    // 目的関数と制約の2次の項
+    pub quadratic_terms: u64,
+    pub matrix_range: Option<CoefficientRange>,
+    pub objective_range: Option<CoefficientRange>,
+    pub bounds_range: Option<CoefficientRange>,
+    pub rhs_range: Option<CoefficientRange>,
+    pub warnings: Vec<RangeWarning>,
+}
+
+#[derive(Default)]
+struct RangeAcc(Option<CoefficientRange>);
+
+impl RangeAcc {
+    fn add(&mut self, value: f64) {
+        let v = value.abs();
+        if v == 0.0 || v >= INFINITE_VALUE || v.is_nan() {
+            return;
+        }
+        self.0 = Some(match self.0 {
+            Some(r) => CoefficientRange {
+                min: r.min.min(v),
+                max: r.max.max(v),
+            },
+            None => CoefficientRange { min: v, max: v },
+        });
+    }
+}
+
+struct VarInfo {
+    integer: bool,
+    binary: bool,
+    semi: bool,
+    lower: f64,
+    upper: f64,
+}
+
+impl Default for VarInfo {
+    fn default() -> Self {
+        VarInfo {
+            integer: false,
+            binary: false,
+            semi: false,
+            lower: 0.0,
+            upper: f64::INFINITY,
+        }
+    }
+}
+
+// 読み取り中の集計 (LP と MPS で共通)
+#[derive(Default)]
+struct Builder {
+    name: Option<String>,
+    rows: u64,
+    nonzeros: u64,
+    sos: u64,
+    general_constraints: u64,
+    quadratic_terms: u64,
+    vars: HashMap<String, VarInfo>,
+    matrix: RangeAcc,
+    objective: RangeAcc,
+    rhs: RangeAcc,
+}
+
+impl Builder {
+    fn var(&mut self, name: &str) -> &mut VarInfo {
+        if !self.vars.contains_key(name) {
+            self.vars.insert(name.to_string(), VarInfo::default());
+        }
+        self.vars.get_mut(name).expect("inserted above")
+    }
+
+    fn finish(self, format: ModelFormat) -> ModelStats {
+        let mut bounds = RangeAcc::default();
+        let (mut integer, mut binary, mut semi) = (0, 0, 0);
+        for v in self.vars.values() {
+            bounds.add(v.lower);
+            bounds.add(v.upper);
+            if v.integer || v.binary {
+                integer += 1;
+                if v.binary || (v.lower == 0.0 && v.upper == 1.0) {
+                    binary += 1;
+                }
+            }
+            if v.semi {
+                semi += 1;
+            }
+        }
+        let columns = self.vars.len() as u64;
+        let mut stats = ModelStats {
+            format,
+            name: self.name,
+            rows: self.rows,
+            columns,
+            nonzeros: self.nonzeros,
+            continuous: columns - integer,
+            integer,
+            binary,
+            semi_continuous: semi,
+            sos: self.sos,
+            general_constraints: self.general_constraints,
+            quadratic_terms: self.quadratic_terms,
+            matrix_range: self.matrix.0,
+            objective_range: self.objective.0,
+            bounds_range: bounds.0,
+            rhs_range: self.rhs.0,
+            warnings: Vec::new(),
+        };
+        stats.warnings = [
+            (RangeTarget::Matrix, stats.matrix_range),
+            (RangeTarget::Objective, stats.objective_range),
+            (RangeTarget::Bounds, stats.bounds_range),
+            (RangeTarget::Rhs, stats.rhs_range),
+        ]
+        .into_iter()
+        .filter_map(|(target, range)| Some(range_warnings(target, &range?)))
+        .flatten()
+        .collect();
+        stats
+    }
+}
+
+// Gurobi のログと同じ表記 (1e+06)
+pub fn format_value(v: f64) -> String {
+    let s = format!("{:.0e}", v);
+    match s.split_once('e') {
+        Some((mantissa, exp)) => {
+            let exp: i32 = exp.parse().unwrap_or(0);
+            format!(
+                "{}e{}{:02}",
+                mantissa,
+                if exp < 0 { '-' } else { '+' },
+                exp.abs()
+            )
+        }
+        None => s,
+    }
+}
+
+// 範囲が大きすぎる・小さすぎる・広すぎる場合の警告
+pub fn range_warnings(target: RangeTarget, range: &CoefficientRange) -> Vec<RangeWarning> {
+    let mut warnings = Vec::new();
+    let mut warn = |message: String| {
+        warnings.push(RangeWarning {
+            target,
+            message: format!("{}{}", target.label(), message),
+        })
+    };
+    if range.max >= LARGE_VALUE {
+        warn(format!(
+            "に大きな値があります (最大 {})。Big-M などの定数を見直してください。",
+            format_value(range.max)
+        ));
+    }
+    // 上下限と右辺は小さな値があっても問題になりにくい
+    if matches!(target, RangeTarget::Matrix | RangeTarget::Objective) {
+        if range.min <= SMALL_VALUE {
+            warn(format!(
+                "に非常に小さな値があります (最小 {})。",
+                format_value(range.min)
+            ));
+        }
+        if range.max / range.min >= MAX_RANGE_RATIO {
+            warn(format!(
+                "の範囲が広すぎます ([{}, {}])。スケーリングを見直すか NumericFocus の設定を検討してください。",
+                format_value(range.min),
+                format_value(range.max)
+            ));
+        }
+    }
+    warnings
+}
+
+// --- LP 形式 ---
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(&'static str),
+}
+
+fn is_ident_end(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '+' | '-' | '*' | '/' | '^' | ':' | '<' | '>' | '=')
+}
+
+fn tokenize(line: &str) -> Vec<Token> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+        i += 1;
+        let op = match (c, next) {
+            (c, _) if c.is_whitespace() => continue,
+            ('-', Some('>')) => {
+                i += 1;
+                "->"
+            }
+            ('<', Some('=')) | ('=', Some('<')) => {
+                i += 1;
+                "<="
+            }
+            ('>', Some('=')) | ('=', Some('>')) => {
+                i += 1;
+                ">="
+            }
+            ('<', _) => "<=",
+            ('>', _) => ">=",
+            ('=', _) => "=",
+            ('+', _) => "+",
+            ('-', _) => "-",
+            ('*', _) => "*",
+            ('/', _) => "/",
+            ('^', _) => "^",
+            (':', _) => ":",
+            ('[', _) => "[",
+            (']', _) => "]",
+            (c, next)
+                if c.is_ascii_digit() || (c == '.' && next.is_some_and(|n| n.is_ascii_digit())) =>
+            {
+                let start = i - 1;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                // 指数部 (1e-05)
+                if i < chars.len() && matches!(chars[i], 'e' | 'E') {
+                    let mut j = i + 1;
+                    if j < chars.len() && matches!(chars[j], '+' | '-') {
+                        j += 1;
+                    }
+                    if j < chars.len() && chars[j].is_ascii_digit() {
+                        while j < chars.len() && chars[j].is_ascii_digit() {
+                            j += 1;
+                        }
+                        i = j;
+                    }
+                }
+                let text: String = chars[start..i].iter().collect();
+                tokens.push(Token::Num(text.parse().unwrap_or(f64::NAN)));
+                continue;
+            }
+            _ => {
+                // 変数名 (x[1,2] のように角括弧を含むこともある)
+                let start = i - 1;
+                while i < chars.len() && !is_ident_end(chars[i]) {
+                    i += 1;
+                }
+                let text: String = chars[start..i].iter().collect();
+                let lower = text.to_ascii_lowercase();
+                tokens.push(if lower == "inf" || lower == "infinity" {
+                    Token::Num(f64::INFINITY)
+                } else {
+                    Token::Ident(text)
+                });
+                continue;
+            }
+        };
+        tokens.push(Token::Op(op));
+    }
+    tokens
+}
+
+// 行頭の "名前:" を取り除く
+fn strip_label(tokens: &[Token]) -> (bool, &[Token]) {
+    match tokens {
+        [Token::Ident(_), Token::Op(":"), rest @ ..] => (true, rest),
+        _ => (false, tokens),
+    }
+}
+
+// 目的関数や制約の式を1トークンずつ読む (複数行にまたがってもよい)
+struct Expr {
+    sign: f64,
+    coef: Option<f64>,
+    in_quad: bool,
+    skip_number: bool,
+    after_sense: bool,
+    rhs_seen: bool,
+    indicator: bool,
+    coefs: Vec<f64>,
+    lhs_constant: f64,
+    rhs_constant: f64,
+    quadratic_terms: u64,
+}
+
+impl Expr {
+    fn new() -> Self {
+        Expr {
+            sign: 1.0,
+            coef: None,
+            in_quad: false,
+            skip_number: false,
+            after_sense: false,
+            rhs_seen: false,
+            indicator: false,
+            coefs: Vec::new(),
+            lhs_constant: 0.0,
+            rhs_constant: 0.0,
+            quadratic_terms: 0,
+        }
+    }
+
+    // 変数の付かない数値は定数
+    fn flush_constant(&mut self) {
+        if let Some(c) = self.coef.take() {
+            if self.after_sense {
+                self.rhs_constant += self.sign * c;
+                self.rhs_seen = true;
+            } else {
+                self.lhs_constant += self.sign * c;
+            }
+        }
+        self.sign = 1.0;
+    }
+
+    fn feed(&mut self, token: &Token, builder: &mut Builder) {
+        if self.in_quad {
+            match token {
+                Token::Op("]") => self.in_quad = false,
+                Token::Op("*") | Token::Op("^") => self.quadratic_terms += 1,
+                Token::Ident(name) => {
+                    builder.var(name);
+                }
+                _ => {}
+            }
+            return;
+        }
+        match token {
+            Token::Num(v) => {
+                // "[ ... ] / 2" の 2
+                if std::mem::take(&mut self.skip_number) {
+                    return;
+                }
+                if self.coef.is_some() {
+                    self.flush_constant();
+                }
+                self.coef = Some(*v);
+            }
+            Token::Ident(name) => {
+                builder.var(name);
+                let value = self.sign * self.coef.take().unwrap_or(1.0);
+                self.sign = 1.0;
+                self.coefs.push(value);
+            }
+            Token::Op("+") => self.flush_constant(),
+            Token::Op("-") => {
+                let sign = self.sign;
+                self.flush_constant();
+                self.sign = -sign;
+            }
+            Token::Op("[") => {
+                self.flush_constant();
+                self.in_quad = true;
+            }
+            Token::Op("/") => self.skip_number = true,
+            Token::Op("<=") | Token::Op(">=") | Token::Op("=") => {
+                self.flush_constant();
+                self.after_sense = true;
+            }
+            // 指示制約 "b = 1 -> x + y <= 3" は条件部分を読み捨てる
+            Token::Op("->") => {
+                *self = Expr {
+                    indicator: true,
+                    ..Expr::new()
+                };
+            }
+            _ => {}
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.after_sense && (self.rhs_seen || self.coef.is_some())
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LpSection {
+    Objective,
+    Constraints,
+    Bounds,
+    Integers,
+    Binaries,
+    Semi,
+    Sos,
+    GeneralConstraints,
+    Other,
+}
+
+fn lp_section(line: &str) -> Option<LpSection> {
+    let key = line.trim().to_ascii_lowercase();
+    let key = key.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(match key.as_str() {
+        "minimize" | "maximize" | "minimum" | "maximum" | "min" | "max" => LpSection::Objective,
+        "subject to" | "such that" | "st" | "s.t." | "lazy constraints" | "user cuts" => {
+            LpSection::Constraints
+        }
+        "bounds" | "bound" => LpSection::Bounds,
+        "generals" | "general" | "gen" | "integers" => LpSection::Integers,
+        "binaries" | "binary" | "bin" => LpSection::Binaries,
+        "semi-continuous" | "semis" | "semi" => LpSection::Semi,
+        "sos" => LpSection::Sos,
+        "general constraints" | "general constraint" | "gencons" => LpSection::GeneralConstraints,
+        "pwlobj" | "scenarios" => LpSection::Other,
+        _ if key.starts_with("minimize multi-objectives")
+            || key.starts_with("maximize multi-objectives") =>
+        {
+            LpSection::Objective
+        }
+        _ => return None,
+    })
+}
+
+impl Builder {
+    fn finish_row(&mut self, mut row: Expr) {
+        row.flush_constant();
+        self.quadratic_terms += row.quadratic_terms;
+        if row.indicator {
+            self.general_constraints += 1;
+            return;
+        }
+        self.rows += 1;
+        for c in &row.coefs {
+            self.nonzeros += 1;
+            self.matrix.add(*c);
+        }
+        self.rhs.add(row.rhs_constant - row.lhs_constant);
+    }
+
+    fn finish_objective(&mut self, objective: Expr) {
+        self.quadratic_terms += objective.quadratic_terms;
+        for c in &objective.coefs {
+            self.objective.add(*c);
+        }
+    }
+
+    fn apply_lp_bound(&mut self, tokens: &[Token]) {
+        // 符号と数値をまとめる ("- 5" -> -5)
+        let mut merged: Vec<Token> = Vec::new();
+        let mut negate = false;
+        for token in tokens {
+            match token {
+                Token::Op("-") => negate = !negate,
+                Token::Op("+") => {}
+                Token::Num(v) => {
+                    merged.push(Token::Num(if negate { -v } else { *v }));
+                    negate = false;
+                }
+                other => merged.push(other.clone()),
+            }
+        }
+        match merged.as_slice() {
+            [Token::Ident(name), Token::Ident(free)] if free.eq_ignore_ascii_case("free") => {
+                let v = self.var(name);
+                v.lower = f64::NEG_INFINITY;
+                v.upper = f64::INFINITY;
+            }
+            [Token::Num(lo), Token::Op(_), Token::Ident(name), Token::Op(_), Token::Num(hi)] => {
+                let v = self.var(name);
+                v.lower = *lo;
+                v.upper = *hi;
+            }
+            [Token::Ident(name), Token::Op(op), Token::Num(n)] => {
+                let v = self.var(name);
+                match *op {
+                    "<=" => v.upper = *n,
+                    ">=" => v.lower = *n,
+                    _ => (v.lower, v.upper) = (*n, *n),
+                }
+            }
+            [Token::Num(n), Token::Op(op), Token::Ident(name)] => {
+                let v = self.var(name);
+                match *op {
+                    "<=" => v.lower = *n,
+                    ">=" => v.upper = *n,
+                    _ => (v.lower, v.upper) = (*n, *n),
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn parse_lp(reader: impl BufRead) -> Result<ModelStats, String> {
+    let mut builder = Builder::default();
+    let mut section = LpSection::Other;
+    let mut objective = Expr::new();
+    let mut row: Option<Expr> = None;
+
+    for line in reader.lines() {
+        let line = line.map_err(|e| format!("モデルファイルを読めません: {}", e))?;
+        let text = line.trim();
+        if let Some(comment) = text.strip_prefix('\\') {
+            // "\ Model name" の行からモデル名を取る
+            if let Some(name) = comment.trim().strip_prefix("Model ") {
+                builder.name.get_or_insert_with(|| name.trim().to_string());
+            }
+            continue;
+        }
+        if text.is_empty() {
+            continue;
+        }
+        if text.eq_ignore_ascii_case("end") {
+            break;
+        }
+        if let Some(next) = lp_section(text) {
+            if let Some(r) = row.take() {
+                builder.finish_row(r);
+            }
+            section = next;
+            continue;
+        }
+
+        match section {
+            LpSection::Objective => {
+                let tokens = tokenize(text);
+                for token in strip_label(&tokens).1 {
+                    objective.feed(token, &mut builder);
+                }
+            }
+            LpSection::Constraints => {
+                let tokens = tokenize(text);
+                let (labeled, body) = strip_label(&tokens);
+                // 名前付きの行、または前の制約が右辺まで読めていれば新しい制約
+                let continues_indicator = body.first() == Some(&Token::Op("->"));
+                let starts_new =
+                    labeled || (!continues_indicator && row.as_ref().is_none_or(Expr::is_complete));
+                if starts_new {
+                    if let Some(r) = row.take() {
+                        builder.finish_row(r);
+                    }
+                }
+                let current = row.get_or_insert_with(Expr::new);
+                for token in body {
+                    current.feed(token, &mut builder);
+                }
+            }
+            LpSection::Bounds => builder.apply_lp_bound(&tokenize(text)),
+            LpSection::Integers | LpSection::Binaries | LpSection::Semi => {
+                for name in text.split_whitespace() {
+                    let v = builder.var(name);
+                    match section {
+                        LpSection::Integers => v.integer = true,
+                        LpSection::Binaries => {
+                            v.binary = true;
+                            v.lower = 0.0;
+                            v.upper = 1.0;
+                        }
+                        _ => v.semi = true,
+                    }
+                }
+            }
+            // "s1: S1 :: x:1 y:2"
+            LpSection::Sos => {
+                if text.contains("::") {
+                    builder.sos += 1;
+                }
+            }
+            LpSection::GeneralConstraints => {
+                if strip_label(&tokenize(text)).0 {
+                    builder.general_constraints += 1;
+                }
+            }
+            LpSection::Other => {}
+        }
+    }
+    if let Some(r) = row.take() {
+        builder.finish_row(r);
+    }
+    builder.finish_objective(objective);
+    Ok(builder.finish(ModelFormat::Lp))
+}
+
+// --- MPS 形式 (固定・自由形式とも空白区切りで読む) ---
+
+#[derive(Clone, Copy, PartialEq)]
+enum RowKind {
+    Objective,
+    Free,
+    Constraint,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum MpsSection {
+    Rows,
+    Columns,
+    Rhs,
+    Bounds,
+    Sos,
+    Quadratic,
+    Indicators,
+    Other,
+}
+
+fn parse_number(text: &str, line_no: usize) -> Result<f64, String> {
+    let lower = text.to_ascii_lowercase();
+    match lower.trim_start_matches(['+', '-']) {
+        "inf" | "infinity" => Ok(if lower.starts_with('-') {
+            f64::NEG_INFINITY
+        } else {
+            f64::INFINITY
+        }),
+        _ => text
+            .parse()
+            .map_err(|_| format!("{} 行目の数値を読み取れません: {}", line_no, text)),
+    }
+}
+
+fn parse_mps(reader: impl BufRead) -> Result<ModelStats, String> {
+    let mut builder = Builder::default();
+    let mut section = MpsSection::Other;
+    let mut rows: HashMap<String, RowKind> = HashMap::new();
+    let mut has_objective = false;
+    let mut in_integer_block = false;
+
+    for (i, line) in reader.lines().enumerate() {
+        let line_no = i + 1;
+        let line = line.map_err(|e| format!("モデルファイルを読めません: {}", e))?;
+        if line.trim().is_empty() || line.starts_with('*') {
+            continue;
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+
+        // セクション名は行頭から始まる
+        if !line.starts_with(char::is_whitespace) {
+            section = match tokens[0].to_ascii_uppercase().as_str() {
+                "NAME" => {
+                    builder.name = tokens.get(1).map(|s| s.to_string());
+                    MpsSection::Other
+                }
+                "ROWS" => MpsSection::Rows,
+                "COLUMNS" => MpsSection::Columns,
+                "RHS" => MpsSection::Rhs,
+                "BOUNDS" => MpsSection::Bounds,
+                "SOS" => MpsSection::Sos,
+                "QUADOBJ" | "QMATRIX" | "QCMATRIX" => MpsSection::Quadratic,
+                "INDICATORS" => MpsSection::Indicators,
+                "ENDATA" => break,
+                _ => MpsSection::Other,
+            };
+            continue;
+        }
+
+        match section {
+            MpsSection::Rows => {
+                let [kind, name, ..] = tokens[..] else {
+                    continue;
+                };
+                let kind = match kind.to_ascii_uppercase().as_str() {
+                    // 最初の N 行が目的関数、それ以降の N 行は自由行
+                    "N" if !has_objective => {
+                        has_objective = true;
+                        RowKind::Objective
+                    }
+                    "N" => RowKind::Free,
+                    _ => {
+                        builder.rows += 1;
+                        RowKind::Constraint
+                    }
+                };
+                rows.insert(name.to_string(), kind);
+            }
+            MpsSection::Columns => {
+                if tokens.iter().any(|t| t.eq_ignore_ascii_case("'MARKER'")) {
+                    if tokens.iter().any(|t| t.eq_ignore_ascii_case("'INTORG'")) {
+                        in_integer_block = true;
+                    } else if tokens.iter().any(|t| t.eq_ignore_ascii_case("'INTEND'")) {
+                        in_integer_block = false;
+                    }
+                    continue;
+                }
+                let column = tokens[0];
+                if in_integer_block {
+                    builder.var(column).integer = true;
+                } else {
+                    builder.var(column);
+                }
+                for pair in tokens[1..].chunks(2) {
+                    let [row, value] = pair else {
+                        continue;
+                    };
+                    let value = parse_number(value, line_no)?;
+                    match rows.get(*row) {
+                        Some(RowKind::Objective) => builder.objective.add(value),
+                        Some(RowKind::Constraint) => {
+                            builder.nonzeros += 1;
+                            builder.matrix.add(value);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            MpsSection::Rhs => {
+                // 先頭の RHS セット名は省略されることがある
+                let pairs = if tokens.len() % 2 == 1 {
+                    &tokens[1..]
+                } else {
+                    &tokens[..]
+                };
+                for pair in pairs.chunks(2) {
+                    let [row, value] = pair else {
+                        continue;
+                    };
+                    // 目的関数行の右辺は定数項
+                    if rows.get(*row) == Some(&RowKind::Constraint) {
+                        builder.rhs.add(parse_number(value, line_no)?);
+                    }
+                }
+            }
+            MpsSection::Bounds => {
+                let kind = tokens[0].to_ascii_uppercase();
+                let needs_value = matches!(kind.as_str(), "UP" | "LO" | "FX" | "LI" | "UI" | "SC");
+                let (column, value) = if needs_value {
+                    let [.., column, value] = tokens[..] else {
+                        continue;
+                    };
+                    (column, Some(parse_number(value, line_no)?))
+                } else {
+                    // FR / MI / PL / BV (BV は値付きのこともある)
+                    match tokens[..] {
+                        [_, _, column, value] => (column, value.parse().ok()),
+                        [_, column, value] if value.parse::<f64>().is_ok() => {
+                            (column, value.parse().ok())
+                        }
+                        [.., column] => (column, None),
+                        _ => continue,
+                    }
+                };
+                let v = builder.var(column);
+                let value = value.unwrap_or(0.0);
+                match kind.as_str() {
+                    "UP" => v.upper = value,
+                    "LO" => v.lower = value,
+                    "FX" => (v.lower, v.upper) = (value, value),
+                    "FR" => (v.lower, v.upper) = (f64::NEG_INFINITY, f64::INFINITY),
+                    "MI" => v.lower = f64::NEG_INFINITY,
+                    "PL" => v.upper = f64::INFINITY,
+                    "BV" => {
+                        v.binary = true;
+                        (v.lower, v.upper) = (0.0, 1.0);
+                    }
+                    "LI" => {
+                        v.integer = true;
+                        v.lower = value;
+                    }
+                    "UI" => {
+                        v.integer = true;
+                        v.upper = value;
+                    }
+                    "SC" => {
+                        v.semi = true;
+                        v.upper = value;
+                    }
+                    _ => {}
+                }
+            }
+            // " S1 SOS s1 1" の見出し行ごとに1つ
+            MpsSection::Sos => {
+                if matches!(tokens[0].to_ascii_uppercase().as_str(), "S1" | "S2") {
+                    builder.sos += 1;
+                }
+            }
+            MpsSection::Quadratic => builder.quadratic_terms += 1,
+            MpsSection::Indicators => builder.general_constraints += 1,
+            MpsSection::Other => {}
+        }
+    }
+    Ok(builder.finish(ModelFormat::Mps))
+}
+
+// 拡張子から形式を決め、gzip なら展開しながら読む
+pub fn read_model_stats(path: &str) -> Result<ModelStats, String> {
+    let p = Path::new(path);
+    let lower_ext = |p: &Path| {
+        p.extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase())
+    };
+    let mut ext = lower_ext(p);
+    let gzipped = ext.as_deref() == Some("gz");
+    if gzipped {
+        ext = p.file_stem().map(Path::new).and_then(lower_ext);
+    } else if matches!(ext.as_deref(), Some("bz2" | "zip" | "7z")) {
+        return Err("bz2 / zip / 7z で圧縮されたモデルファイルには対応していません。gzip で圧縮してください。".to_string());
+    }
+    let format = match ext.as_deref() {
+        Some("lp" | "rlp") => ModelFormat::Lp,
+        Some("mps" | "rew") => ModelFormat::Mps,
+        _ => {
+            return Err(format!(
+                "統計を取れるのは .lp / .mps ファイル (gzip 圧縮も可) です: {}",
+                path
+            ))
+        }
+    };
+
+    let file =
+        File::open(p).map_err(|e| format!("モデルファイルを開けません: {} ({})", path, e))?;
+    let reader: Box<dyn BufRead> = if gzipped {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+    match format {
+        ModelFormat::Lp => parse_lp(reader),
+        ModelFormat::Mps => parse_mps(reader),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    const LP: &str = "\
+\\ Model sample
+Maximize
+ obj: 3 x + 2 y + 0.001 z + [ x ^ 2 ] / 2
+Subject To
+ c1: x + y + z + b <= 4
+ c2: 2 x + 1e6 y
+     >= 1
+ c3: x - z = 0
+Bounds
+ 0 <= x <= 10
+ y <= 1e4
+ z >= -infinity
+Generals
+ z
+Binaries
+ b
+End
+";
+
+    const MPS: &str = "\
+NAME          sample
+ROWS
+ N  obj
+ L  c1
+ G  c2
+ E  c3
+COLUMNS
+    x         obj       3              c1        1
+    x         c2        2              c3        1
+    MARKER    'MARKER'  'INTORG'
+    z         obj       0.001          c1        1
+    z         c3        -1
+    MARKER    'MARKER'  'INTEND'
+    y         obj       2              c1        1
+    y         c2        1e6
+RHS
+    RHS1      c1        4              c2        1
+BOUNDS
+ UP BND1      x         10
+ UP BND1      y         1e4
+ BV BND1      b
+ENDATA
+";
+
+    fn range(min: f64, max: f64) -> Option<CoefficientRange> {
+        Some(CoefficientRange { min, max })
+    }
+
+    #[test]
+    fn reads_lp_file() {
+        let s = parse_lp(LP.as_bytes()).unwrap();
+        assert_eq!(s.name.as_deref(), Some("sample"));
+        assert_eq!((s.rows, s.columns, s.nonzeros), (3, 4, 8));
+        assert_eq!((s.continuous, s.integer, s.binary), (2, 2, 1));
+        assert_eq!(s.quadratic_terms, 1);
+        assert_eq!(s.matrix_range, range(1.0, 1e6));
+        assert_eq!(s.objective_range, range(1e-3, 3.0));
+        assert_eq!(s.bounds_range, range(1.0, 1e4));
+        assert_eq!(s.rhs_range, range(1.0, 4.0));
+        assert!(s.warnings.is_empty());
+    }
+
+    #[test]
+    fn reads_mps_file() {
+        let s = parse_mps(MPS.as_bytes()).unwrap();
+        assert_eq!(s.format, ModelFormat::Mps);
+        assert_eq!((s.rows, s.columns, s.nonzeros), (3, 4, 7));
+        assert_eq!((s.continuous, s.integer, s.binary), (2, 2, 1));
+        assert_eq!(s.matrix_range, range(1.0, 1e6));
+        assert_eq!(s.objective_range, range(1e-3, 3.0));
+        assert_eq!(s.rhs_range, range(1.0, 4.0));
+    }
+
+    #[test]
+    fn reads_gzipped_file_by_extension() {
+        let dir = std::env::temp_dir().join(format!("gurobilab-stats-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("sample.mps.gz");
+        let mut encoder =
+            flate2::write::GzEncoder::new(File::create(&path).unwrap(), Default::default());
+        encoder.write_all(MPS.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        let s = read_model_stats(&path.to_string_lossy()).unwrap();
+        assert_eq!(s.nonzeros, 7);
+        assert!(read_model_stats(&dir.join("sample.txt").to_string_lossy()).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn warns_about_wide_ranges() {
+        assert_eq!(format_value(1e6), "1e+06");
+        assert_eq!(format_value(0.001), "1e-03");
+        let warnings = range_warnings(
+            RangeTarget::Matrix,
+            &CoefficientRange {
+                min: 1e-10,
+                max: 1e10,
+            },
+        );
+        assert_eq!(warnings.len(), 3);
+        let warnings = range_warnings(
+            RangeTarget::Rhs,
+            &CoefficientRange {
+                min: 1e-10,
+                max: 1.0,
+            },
+        );
+        assert!(warnings.is_empty());
+    }
+}
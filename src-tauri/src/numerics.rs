@@ -0,0 +1,459 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::model_stats::{self, RangeTarget};
+
+// Gurobi のログ全体から数値的な問題 (係数の範囲、許容誤差を超える違反、数値的な困難など) を拾い出す
+// AI 向けの圧縮で間引かれても見落とさないよう、圧縮前のログに対して行う
+
+// プロンプトに載せる件数の上限
+const MAX_PROMPT_ISSUES: usize = 20;
+// 元の尺度での違反がこれ以上なら深刻とみなす
+const CRITICAL_UNSCALED_VIOLATION: f64 = 1e-4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    fn as_str(self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NumericIssueKind {
+    CoefficientRange,
+    ConstraintViolation,
+    BoundViolation,
+    IntegralityViolation,
+    UnscaledViolation,
+    MarkowitzTolerance,
+    NumericalTrouble,
+    DroppedFromBasis,
+    SuboptimalTermination,
+}
+
+impl NumericIssueKind {
+    fn hint(self) -> &'static str {
+        match self {
+            NumericIssueKind::CoefficientRange => {
+                "単位の見直しや Big-M の縮小で係数の大きさを揃える。すぐに直せない場合は NumericFocus=1〜3 や ScaleFlag=2 を試す。"
+            }
+            NumericIssueKind::ConstraintViolation | NumericIssueKind::BoundViolation => {
+                "解が許容誤差を満たしていない。NumericFocus=2〜3 を設定し、Big-M や極端な係数を見直す。FeasibilityTol を緩めるのは最後の手段。"
+            }
+            NumericIssueKind::IntegralityViolation => {
+                "整数変数の値が整数から外れている。IntegralityFocus=1 を設定し、整数変数に掛かる大きな係数 (Big-M) を見直す。"
+            }
+            NumericIssueKind::UnscaledViolation => {
+                "スケーリング後は許容範囲でも元の尺度では違反している。ScaleFlag=0 または 2、NumericFocus=2 を試し、係数の範囲を狭める。"
+            }
+            NumericIssueKind::MarkowitzTolerance => {
+                "基底行列の条件が悪く、ピボットの安定性を上げている。NumericFocus=2〜3 を設定し、ほぼ従属な制約がないか確認する。"
+            }
+            NumericIssueKind::NumericalTrouble => {
+                "数値的な困難で計算が正しく進まなかった可能性が高い。NumericFocus=3、Presolve=0 または Aggregate=0、バリア法なら BarHomogeneous=1 を試す。"
+            }
+            NumericIssueKind::DroppedFromBasis => {
+                "特異に近い基底から変数が外された。NumericFocus=2 を設定し、冗長・ほぼ平行な制約を見直す。"
+            }
+            NumericIssueKind::SuboptimalTermination => {
+                "数値的な理由で最適性を確認できずに終了した。NumericFocus を上げるか、Method を変えて解き直す。"
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NumericIssue {
+    pub kind: NumericIssueKind,
+    pub severity: Severity,
+    // 同じ種類の中での区別 (matrix / primal など)
+    pub subject: Option<String>,
+    pub message: String,
+    // 違反量や係数など、問題の大きさを表す値 (複数回出た場合は最大のもの)
+    pub value: Option<f64>,
+    // 最初に現れた行 (1 始まり) とその内容
+    pub line: usize,
+    pub text: String,
+    pub count: usize,
+    pub hint: String,
+}
+
+struct Patterns {
+    range: Regex,
+    large: Regex,
+    max_violation: Regex,
+    unscaled: Regex,
+    markowitz: Regex,
+    trouble: Regex,
+    dropped: Regex,
+    suboptimal: Regex,
+}
+
+const NUM: &str = r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?";
+
+impl Patterns {
+    fn new() -> Self {
+        let re = |s: &str| Regex::new(&s.replace("{NUM}", NUM)).unwrap();
+        Patterns {
+            range: re(r"^\s*(Matrix|Objective|Bounds|RHS) range\s+\[\s*({NUM})\s*,\s*({NUM})\s*\]"),
+            large: re(
+                r"(?i)^Warning: Model contains large (matrix coefficient range|matrix coefficients|objective coefficients|bounds|rhs)",
+            ),
+            max_violation: re(
+                r"(?i)^Warning: max (constraint|bound|integrality) violation \(({NUM})\) exceeds tolerance",
+            ),
+            unscaled: re(r"(?i)^Warning: unscaled (primal|dual) violation = ({NUM})"),
+            markowitz: re(r"(?i)Markowitz tolerance tightened to ({NUM})"),
+            // "... to avoid numerical issues." という助言の行は拾わない
+            trouble: re(r"(?i)numerical (trouble|error)|numeric error"),
+            dropped: re(r"(?i)(\d+) variables? dropped from basis"),
+            suboptimal: re(r"^Sub-optimal termination"),
+        }
+    }
+}
+
+fn num(caps: &regex::Captures, i: usize) -> Option<f64> {
+    caps.get(i).and_then(|m| m.as_str().parse().ok())
+}
+
+fn range_target(name: &str) -> RangeTarget {
+    match name {
+        "Matrix" => RangeTarget::Matrix,
+        "Objective" => RangeTarget::Objective,
+        "Bounds" => RangeTarget::Bounds,
+        _ => RangeTarget::Rhs,
+    }
+}
+
+fn target_subject(target: RangeTarget) -> &'static str {
+    match target {
+        RangeTarget::Matrix => "matrix",
+        RangeTarget::Objective => "objective",
+        RangeTarget::Bounds => "bounds",
+        RangeTarget::Rhs => "rhs",
+    }
+}
+
+// 1行から見つかった問題 (種類, 深刻度, 区別, 説明, 値)
+type Finding = (
+    NumericIssueKind,
+    Severity,
+    Option<String>,
+    String,
+    Option<f64>,
+);
+
+fn findings_of(p: &Patterns, line: &str) -> Vec<Finding> {
+    use NumericIssueKind::*;
+
+    if let Some(c) = p.range.captures(line) {
+        let (Some(min), Some(max)) = (num(&c, 2), num(&c, 3)) else {
+            return Vec::new();
+        };
+        let target = range_target(&c[1]);
+        return model_stats::range_warnings(target, &model_stats::CoefficientRange { min, max })
+            .into_iter()
+            .map(|w| {
+                let subject = target_subject(target).to_string();
+                (
+                    CoefficientRange,
+                    Severity::Warning,
+                    Some(subject),
+                    w.message,
+                    Some(max),
+                )
+            })
+            .collect();
+    }
+    if let Some(c) = p.large.captures(line) {
+        let subject = match c[1].to_ascii_lowercase().as_str() {
+            "matrix coefficient range" | "matrix coefficients" => "matrix",
+            "objective coefficients" => "objective",
+            "bounds" => "bounds",
+            _ => "rhs",
+        };
+        return vec![(
+            CoefficientRange,
+            Severity::Warning,
+            Some(subject.to_string()),
+            format!("Gurobi が係数の範囲について警告しています ({})", &c[1]),
+            None,
+        )];
+    }
+    if let Some(c) = p.max_violation.captures(line) {
+        let (kind, label) = match c[1].to_ascii_lowercase().as_str() {
+            "constraint" => (ConstraintViolation, "制約"),
+            "bound" => (BoundViolation, "変数の上下限"),
+            _ => (IntegralityViolation, "整数性"),
+        };
+        let value = num(&c, 2);
+        return vec![(
+            kind,
+            Severity::Critical,
+            None,
+            format!("解の{}の違反が許容誤差を超えています ({})", label, &c[2]),
+            value,
+        )];
+    }
+    if let Some(c) = p.unscaled.captures(line) {
+        let value = num(&c, 2);
+        let severity = if value.is_some_and(|v| v.abs() >= CRITICAL_UNSCALED_VIOLATION) {
+            Severity::Critical
+        } else {
+            Severity::Warning
+        };
+        let side = c[1].to_ascii_lowercase();
+        return vec![(
+            UnscaledViolation,
+            severity,
+            Some(side.clone()),
+            format!(
+                "元の尺度での{}違反が残っています ({})",
+                if side == "primal" { "主" } else { "双対" },
+                &c[2]
+            ),
+            value,
+        )];
+    }
+    if let Some(c) = p.markowitz.captures(line) {
+        // 単独では害は少ないが、他の問題と重なっていれば原因の手がかりになる
+        return vec![(
+            MarkowitzTolerance,
+            Severity::Info,
+            None,
+            format!("Markowitz tolerance が {} に引き上げられました", &c[1]),
+            num(&c, 1),
+        )];
+    }
+    if let Some(c) = p.dropped.captures(line) {
+        return vec![(
+            DroppedFromBasis,
+            Severity::Warning,
+            None,
+            format!("{} 個の変数が基底から外されました", &c[1]),
+            num(&c, 1),
+        )];
+    }
+    if p.suboptimal.is_match(line) {
+        return vec![(
+            SuboptimalTermination,
+            Severity::Warning,
+            None,
+            "最適性を確認できずに終了しました (Sub-optimal termination)".to_string(),
+            None,
+        )];
+    }
+    if p.trouble.is_match(line) {
+        return vec![(
+            NumericalTrouble,
+            Severity::Critical,
+            None,
+            "数値的な困難が報告されています".to_string(),
+            None,
+        )];
+    }
+    Vec::new()
+}
+
+// 同じ種類・区別の問題は1件にまとめ、深刻なものから順に返す
+pub fn detect(log: &str) -> Vec<NumericIssue> {
+    let patterns = Patterns::new();
+    let mut issues: Vec<NumericIssue> = Vec::new();
+
+    for (i, raw) in log.lines().enumerate() {
+        for (kind, severity, subject, message, value) in findings_of(&patterns, raw.trim_end()) {
+            // 同じ区別でも説明が違う警告 (大きな値 / 範囲が広い) は別に残す
+            let existing = issues.iter_mut().find(|issue| {
+                issue.kind == kind
+                    && issue.subject == subject
+                    && (kind != NumericIssueKind::CoefficientRange || issue.message == message)
+            });
+            match existing {
+                Some(issue) => {
+                    issue.count += 1;
+                    issue.severity = issue.severity.max(severity);
+                    // 説明は最も大きな値のものにする
+                    let larger = match (issue.value, value) {
+                        (Some(a), Some(b)) => b.abs() > a.abs(),
+                        (None, Some(_)) => true,
+                        _ => false,
+                    };
+                    if larger {
+                        issue.value = value;
+                        issue.message = message;
+                    }
+                }
+                None => issues.push(NumericIssue {
+                    kind,
+                    severity,
+                    subject,
+                    message,
+                    value,
+                    line: i + 1,
+                    text: raw.trim().to_string(),
+                    count: 1,
+                    hint: kind.hint().to_string(),
+                }),
+            }
+        }
+    }
+
+    // Gurobi 自身の範囲の警告は、係数の統計から出した警告と重なるなら省く
+    let ranged: Vec<Option<String>> = issues
+        .iter()
+        .filter(|i| i.kind == NumericIssueKind::CoefficientRange && i.value.is_some())
+        .map(|i| i.subject.clone())
+        .collect();
+    issues.retain(|i| {
+        !(i.kind == NumericIssueKind::CoefficientRange
+            && i.value.is_none()
+            && ranged.contains(&i.subject))
+    });
+
+    issues.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.line.cmp(&b.line)));
+    issues
+}
+
+// AI プロンプトに加える [NUMERICS] セクション (問題が無ければ空)
+pub fn prompt_section(issues: &[NumericIssue]) -> String {
+    if issues.is_empty() {
+        return String::new();
+    }
+    let mut section = String::from("[NUMERICS] ログ全体から検出した数値的な問題\n");
+    for issue in issues.iter().take(MAX_PROMPT_ISSUES) {
+        section.push_str(&format!(
+            "- [{}] {} (x{}, {} 行目: {})\n  対策: {}\n",
+            issue.severity.as_str(),
+            issue.message,
+            issue.count,
+            issue.line,
+            issue.text,
+            issue.hint
+        ));
+    }
+    if issues.len() > MAX_PROMPT_ISSUES {
+        section.push_str(&format!(
+            "... (残り {} 件を省略)\n",
+            issues.len() - MAX_PROMPT_ISSUES
+        ));
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use NumericIssueKind::*;
+
+    const LOG: &str = "\
+Coefficient statistics:
+  Matrix range     [1e-02, 1e+10]
+  Objective range  [1e+00, 1e+02]
+  Bounds range     [1e+00, 1e+00]
+  RHS range        [1e+00, 1e+04]
+Warning: Model contains large matrix coefficients
+Warning: Model contains large rhs
+Markowitz tolerance tightened to 0.5
+Warning: 2 variables dropped from basis
+Warning: 1 variables dropped from basis
+Numerical trouble encountered
+Warning: max constraint violation (1.2e-03) exceeds tolerance
+Warning: max integrality violation (3e-05) exceeds tolerance
+Warning: unscaled primal violation = 2e-03 and residual = 1e-05
+Warning: unscaled dual violation = 1e-06 and residual = 1e-08
+Sub-optimal termination - objective 1.23400000e+03
+Consider reformulating to avoid numerical issues.
+";
+
+    fn summary(issues: &[NumericIssue]) -> Vec<(NumericIssueKind, Severity, Option<&str>, usize)> {
+        issues
+            .iter()
+            .map(|i| (i.kind, i.severity, i.subject.as_deref(), i.line))
+            .collect()
+    }
+
+    #[test]
+    fn detects_each_rule_sorted_by_severity() {
+        let issues = detect(LOG);
+        assert_eq!(
+            summary(&issues),
+            vec![
+                (NumericalTrouble, Severity::Critical, None, 11),
+                (ConstraintViolation, Severity::Critical, None, 12),
+                (IntegralityViolation, Severity::Critical, None, 13),
+                (UnscaledViolation, Severity::Critical, Some("primal"), 14),
+                (CoefficientRange, Severity::Warning, Some("matrix"), 2),
+                (CoefficientRange, Severity::Warning, Some("matrix"), 2),
+                (CoefficientRange, Severity::Warning, Some("rhs"), 7),
+                (DroppedFromBasis, Severity::Warning, None, 9),
+                (UnscaledViolation, Severity::Warning, Some("dual"), 15),
+                (SuboptimalTermination, Severity::Warning, None, 16),
+                (MarkowitzTolerance, Severity::Info, None, 8),
+            ]
+        );
+        assert_eq!(issues[1].value, Some(1.2e-3));
+        assert_eq!(issues[4].value, Some(1e10));
+        assert_eq!(issues[10].value, Some(0.5));
+    }
+
+    #[test]
+    fn merges_repeated_findings() {
+        let issues = detect(LOG);
+        let dropped = issues.iter().find(|i| i.kind == DroppedFromBasis).unwrap();
+        assert_eq!(dropped.count, 2);
+        assert_eq!(dropped.value, Some(2.0));
+        assert_eq!(dropped.text, "Warning: 2 variables dropped from basis");
+
+        // 値の大きい方の説明を残し、深刻度は最も高いものにする
+        let log = "\
+Warning: unscaled primal violation = 1e-06 and residual = 1e-08
+Warning: unscaled primal violation = 5e-03 and residual = 1e-08
+";
+        let issues = detect(log);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].count, 2);
+        assert_eq!(issues[0].severity, Severity::Critical);
+        assert_eq!(issues[0].value, Some(5e-3));
+        assert!(issues[0].message.contains("5e-03"));
+        assert_eq!(issues[0].line, 1);
+    }
+
+    #[test]
+    fn keeps_gurobi_range_warning_without_statistics() {
+        let issues = detect("Warning: Model contains large matrix coefficient range\n");
+        assert_eq!(
+            summary(&issues),
+            vec![(CoefficientRange, Severity::Warning, Some("matrix"), 1)]
+        );
+        assert!(detect("Optimal solution found (tolerance 1.00e-04)\n").is_empty());
+    }
+
+    #[test]
+    fn prompt_section_lists_issues() {
+        assert_eq!(prompt_section(&[]), "");
+        let section = prompt_section(&detect(LOG));
+        assert!(section.starts_with("[NUMERICS]"));
+        assert!(section.contains("- [critical] 数値的な困難が報告されています (x1, 11 行目: Numerical trouble encountered)"));
+        assert!(section.contains(&format!("対策: {}", NumericalTrouble.hint())));
+        assert!(!section.contains("省略"));
+
+        // 上限を超えた分は件数だけ書く
+        let many = vec![detect(LOG)[0].clone(); MAX_PROMPT_ISSUES + 3];
+        let section = prompt_section(&many);
+        assert_eq!(section.matches("- [critical]").count(), MAX_PROMPT_ISSUES);
+        assert!(section.ends_with("... (残り 3 件を省略)\n"));
+    }
+}
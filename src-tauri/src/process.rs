@@ -0,0 +1,94 @@
+use std::process::Command;
+
+// 子プロセスをプロセスグループ単位で止めるための処理
+// (uv run python のように孫プロセスが Gurobi を動かす場合もまとめて止める)
+
+// 停止時に送るシグナルの段階
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopSignal {
+    // Ctrl+C 相当。Gurobi は最終サマリを出力し、解を書き出してから終了する
+    Interrupt,
+    Terminate,
+    Kill,
+}
+
+// SIGINT -> SIGTERM -> SIGKILL の順に送る (Windows では CTRL_BREAK -> 強制終了)
+pub const STOP_SEQUENCE: [StopSignal; 3] = [
+    StopSignal::Interrupt,
+    StopSignal::Terminate,
+    StopSignal::Kill,
+];
+
+// 各段階の間に待つ秒数の既定値
+pub const DEFAULT_GRACE_PERIOD_SECS: f64 = 10.0;
+
+// 子プロセスを新しいプロセスグループの先頭として起動する設定
+#[cfg(unix)]
+pub fn configure_process_group(cmd: &mut Command) {
+    use std::os::unix::process::CommandExt;
+    cmd.process_group(0);
+}
+
+// Windows では CTRL_BREAK をこのプロセスグループだけに送れるようにする
+#[cfg(windows)]
+pub fn configure_process_group(cmd: &mut Command) {
+    use std::os::windows::process::CommandExt;
+    use windows_sys::Win32::System::Threading::CREATE_NEW_PROCESS_GROUP;
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn configure_process_group(_cmd: &mut Command) {}
+
+#[cfg(unix)]
+pub fn signal_process_tree(pid: u32, signal: StopSignal) -> Result<(), String> {
+    let sig = match signal {
+        StopSignal::Interrupt => libc::SIGINT,
+        StopSignal::Terminate => libc::SIGTERM,
+        StopSignal::Kill => libc::SIGKILL,
+    };
+    // 負のPIDでプロセスグループ全体に送る
+    let ret = unsafe { libc::kill(-(pid as libc::pid_t), sig) };
+    if ret == 0 {
+        return Ok(());
+    }
+    let err = std::io::Error::last_os_error();
+    // 既に終了している場合はエラーにしない
+    if err.raw_os_error() == Some(libc::ESRCH) {
+        Ok(())
+    } else {
+        Err(format!("シグナル送信エラー: {}", err))
+    }
+}
+
+#[cfg(windows)]
+pub fn signal_process_tree(pid: u32, signal: StopSignal) -> Result<(), String> {
+    use windows_sys::Win32::System::Console::{GenerateConsoleCtrlEvent, CTRL_BREAK_EVENT};
+
+    // Windows にはシグナルが無い。Interrupt はプロセスグループへの CTRL_BREAK で代用し
+    // (Python は KeyboardInterrupt、Gurobi は中断として扱う)、それ以降は taskkill /F で強制終了する
+    // (/F なしの taskkill はコンソールアプリには何もしないため使わない)
+    if signal == StopSignal::Interrupt {
+        if unsafe { GenerateConsoleCtrlEvent(CTRL_BREAK_EVENT, pid) } != 0 {
+            return Ok(());
+        }
+        // コンソールを共有していない場合などは送れないので、強制終了に進む
+        let err = std::io::Error::last_os_error();
+        eprintln!("CTRL_BREAK を送れませんでした ({})。強制終了します。", err);
+    }
+
+    let output = Command::new("taskkill")
+        .args(["/PID", &pid.to_string(), "/T", "/F"])
+        .output()
+        .map_err(|e| e.to_string())?;
+    // 128 は対象が見つからない (既に終了している) 場合なのでエラーにしない
+    if output.status.success() || output.status.code() == Some(128) {
+        Ok(())
+    } else {
+        Err(format!(
+            "taskkill が失敗しました ({}): {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
@@ -0,0 +1,634 @@
+use regex::Regex;
+use serde::Serialize;
+
+use crate::compression::CompressionConfig;
+use crate::result_json;
+
+// AI に渡すプロンプトを、モデルごとのおおよそのトークン数で予算内に収める
+// ログは区間 (ヘッダ / 前処理 / 分枝限定法の表 / 最終結果 / 警告 / 結果JSON) に分け、
+// 予算を配分したうえで各区間から情報量の多い行を残す (文字の途中では切らない)
+
+// ログに最低限割り当てるトークン数 (指示や IIS が長くてもログは必ず渡す)
+const MIN_LOG_TOKENS: usize = 1000;
+// 行の途中で切ってでも残す最小の残り予算
+const MIN_TRUNCATED_TOKENS: usize = 32;
+
+// 文字の種類ごとのおおよそのトークン数
+#[derive(Debug, Clone, Copy)]
+pub struct TokenCounter {
+    // ASCII は何文字で1トークンか
+    ascii_chars_per_token: f64,
+    // 日本語などの文字1つあたりのトークン数
+    tokens_per_wide_char: f64,
+}
+
+impl TokenCounter {
+    // 正確なトークナイザは持たないので、モデル名から系統を推定して係数を選ぶ
+    pub fn for_model(model: &str) -> Self {
+        let m = model.to_ascii_lowercase();
+        let (ascii, wide) = if m.contains("gemini") || m.contains("gemma") {
+            (4.0, 1.0)
+        } else if ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"]
+            .iter()
+            .any(|p| m.starts_with(p))
+        {
+            // o200k 系は日本語を少し効率よく扱う
+            (4.0, 0.9)
+        } else if m.starts_with("gpt-") {
+            (3.8, 1.3)
+        } else {
+            // ローカルモデル (Llama / Qwen など) は語彙が様々なので多めに見積もる
+            (3.5, 1.5)
+        };
+        TokenCounter {
+            ascii_chars_per_token: ascii,
+            tokens_per_wide_char: wide,
+        }
+    }
+
+    fn char_cost(&self, c: char) -> f64 {
+        if c.is_ascii() {
+            1.0 / self.ascii_chars_per_token
+        } else {
+            self.tokens_per_wide_char
+        }
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        text.chars().map(|c| self.char_cost(c)).sum::<f64>().ceil() as usize
+    }
+
+    // max_tokens に収まるよう文字単位で切る (末尾に付ける "…" の分も含める)
+    fn truncate(&self, text: &str, max_tokens: usize) -> String {
+        if self.count(text) <= max_tokens {
+            return text.to_string();
+        }
+        let limit = max_tokens as f64;
+        let ellipsis = self.char_cost('…');
+        if ellipsis > limit {
+            return String::new();
+        }
+        // count と同じ順に足し合わせ、"…" を付けた後の合計で判定する
+        let mut cost = 0.0;
+        let mut end = 0;
+        for (i, c) in text.char_indices() {
+            cost += self.char_cost(c);
+            if cost + ellipsis > limit {
+                break;
+            }
+            end = i + c.len_utf8();
+        }
+        format!("{}…", &text[..end])
+    }
+}
+
+// モデルを指定して予算を決めない場合のトークン数
+pub fn default_budget(model: &str) -> usize {
+    let m = model.to_ascii_lowercase();
+    if m.contains("gemini") {
+        32_000
+    } else if m.starts_with("gpt-") || ["o1", "o3", "o4"].iter().any(|p| m.starts_with(p)) {
+        16_000
+    } else {
+        // ローカルサーバーはコンテキスト長が短いことが多い
+        6_000
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SectionKind {
+    // 以下3つは常にそのまま載せる
+    Instructions,
+    Numerics,
+    Iis,
+    // 以下はログを分けたもの
+    Header,
+    Presolve,
+    Tree,
+    Summary,
+    Warnings,
+    Json,
+}
+
+impl SectionKind {
+    fn label(self) -> &'static str {
+        match self {
+            SectionKind::Instructions => "instructions",
+            SectionKind::Numerics => "numerics",
+            SectionKind::Iis => "iis",
+            SectionKind::Header => "header",
+            SectionKind::Presolve => "presolve",
+            SectionKind::Tree => "tree",
+            SectionKind::Summary => "summary",
+            SectionKind::Warnings => "warnings",
+            SectionKind::Json => "json",
+        }
+    }
+
+    // 予算の配分の重み
+    fn weight(self) -> usize {
+        match self {
+            SectionKind::Header => 10,
+            SectionKind::Presolve => 10,
+            SectionKind::Tree => 35,
+            SectionKind::Summary => 20,
+            SectionKind::Warnings => 10,
+            SectionKind::Json => 15,
+            _ => 0,
+        }
+    }
+
+    // 必ず残したい先頭と末尾の行数
+    fn edges(self) -> (usize, usize) {
+        match self {
+            SectionKind::Header => (5, 2),
+            SectionKind::Presolve => (3, 3),
+            SectionKind::Tree => (5, 10),
+            SectionKind::Summary => (3, 10),
+            _ => (2, 2),
+        }
+    }
+}
+
+const LOG_SECTIONS: [SectionKind; 6] = [
+    SectionKind::Header,
+    SectionKind::Presolve,
+    SectionKind::Tree,
+    SectionKind::Summary,
+    SectionKind::Warnings,
+    SectionKind::Json,
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SectionAllocation {
+    pub section: SectionKind,
+    // 省略せずに載せた場合のトークン数
+    pub full_tokens: usize,
+    pub budget_tokens: usize,
+    pub used_tokens: usize,
+    pub lines: usize,
+    // 間引きの方式を通った行数
+    pub sampled_lines: usize,
+    pub kept_lines: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuiltPrompt {
+    pub prompt: String,
+    pub model: String,
+    pub budget_tokens: usize,
+    pub total_tokens: usize,
+    pub sections: Vec<SectionAllocation>,
+}
+
+struct Patterns {
+    warning: Regex,
+    header_start: Regex,
+    presolve_start: Regex,
+    tree_start: Regex,
+    summary_start: Regex,
+    header_key: Regex,
+    presolve_key: Regex,
+    summary_key: Regex,
+}
+
+impl Patterns {
+    fn new() -> Self {
+        let re = |s: &str| Regex::new(s).unwrap();
+        Patterns {
+            warning: re(r"(?i)^warning\b|\bwarning:|numerical trouble"),
+            header_start: re(r"^(Optimize a model|Gurobi Optimizer version|Set parameter)"),
+            presolve_start: re(r"^(Presolve|Presolved|Presolving|Sparsify)"),
+            tree_start: re(
+                r"^(Root relaxation|Root simplex log|Barrier statistics|Concurrent LP optimizer|Iteration Objective|Nodes \||Starting NoRel|Starting sifting)",
+            ),
+            summary_start: re(
+                r"^(Cutting planes:|Explored \d|Solved in|Stopped in|Optimal solution found|Optimal objective|Solution count|Model is infeasible|Infeasible model|Time limit reached|Interrupt request received)",
+            ),
+            header_key: re(
+                r"^(Optimize a model|Set parameter|Model fingerprint)| range |^Model has",
+            ),
+            presolve_key: re(r"^(Presolve removed|Presolved:|Presolve time|Variable types)"),
+            summary_key: re(
+                r"^(Explored|Solved in|Stopped in|Optimal|Best objective|Solution count|Model is infeasible|Infeasible|Time limit|Unbounded|Interrupt)",
+            ),
+        }
+    }
+
+    fn starts_section(&self, line: &str) -> Option<SectionKind> {
+        if self.header_start.is_match(line) {
+            Some(SectionKind::Header)
+        } else if self.presolve_start.is_match(line) {
+            Some(SectionKind::Presolve)
+        } else if self.tree_start.is_match(line) {
+            Some(SectionKind::Tree)
+        } else if self.summary_start.is_match(line) {
+            Some(SectionKind::Summary)
+        } else {
+            None
+        }
+    }
+
+    // 区間の中で優先して残す行
+    fn is_key(&self, kind: SectionKind, line: &str) -> bool {
+        match kind {
+            SectionKind::Header => self.header_key.is_match(line),
+            SectionKind::Presolve => self.presolve_key.is_match(line),
+            // 新しい解 (H / *) と表の見出しなど数字で始まらない行
+            SectionKind::Tree => !line.starts_with(|c: char| c.is_ascii_digit()),
+            SectionKind::Summary => self.summary_key.is_match(line),
+            _ => true,
+        }
+    }
+}
+
+fn json_lines(log: &str, compression: &CompressionConfig) -> Vec<String> {
+    result_json::extract_blocks(log)
+        .into_iter()
+        .map(|block| {
+            let text = match block.value {
+                Some(mut parsed) => {
+                    compression.prune_json(&mut parsed);
+                    parsed.to_string()
+                }
+                None => block.raw.unwrap_or_default(),
+            };
+            if block.name == result_json::DEFAULT_BLOCK_NAME {
+                format!("[JSON_DATA]:{}", text)
+            } else {
+                format!("[JSON_DATA:{}]:{}", block.name, text)
+            }
+        })
+        .collect()
+}
+
+// ログ本文を区間ごとの行に分ける (空白は詰め、空行は捨てる)
+fn split_sections(
+    log: &str,
+    patterns: &Patterns,
+    compression: &CompressionConfig,
+) -> Vec<(SectionKind, Vec<String>)> {
+    let spaces = Regex::new(r" +").unwrap();
+    let mut sections: Vec<(SectionKind, Vec<String>)> =
+        LOG_SECTIONS.iter().map(|&k| (k, Vec::new())).collect();
+    let mut push = |kind: SectionKind, line: String| {
+        if let Some((_, lines)) = sections.iter_mut().find(|(k, _)| *k == kind) {
+            lines.push(line);
+        }
+    };
+
+    let mut current = SectionKind::Header;
+    let mut warnings: Vec<(String, usize)> = Vec::new();
+    for raw in result_json::strip_blocks(log).lines() {
+        let line = spaces.replace_all(raw.trim(), " ").to_string();
+        if line.is_empty() {
+            continue;
+        }
+        if patterns.warning.is_match(&line) {
+            // 同じ警告は1行にまとめる
+            match warnings.iter_mut().find(|(w, _)| *w == line) {
+                Some((_, count)) => *count += 1,
+                None => warnings.push((line, 1)),
+            }
+            continue;
+        }
+        if let Some(kind) = patterns.starts_section(&line) {
+            current = kind;
+        }
+        push(current, line);
+    }
+    for (line, count) in warnings {
+        if count > 1 {
+            push(SectionKind::Warnings, format!("{} (x{})", line, count));
+        } else {
+            push(SectionKind::Warnings, line);
+        }
+    }
+    for line in json_lines(log, compression) {
+        push(SectionKind::Json, line);
+    }
+    sections
+}
+
+// 重みに従って予算を配り、必要量を満たした区間の余りは他の区間へ回す
+fn allocate(needs: &[usize], weights: &[usize], total: usize) -> Vec<usize> {
+    let mut alloc = vec![0; needs.len()];
+    let mut remaining = total;
+    let mut open: Vec<usize> = (0..needs.len())
+        .filter(|&i| needs[i] > 0 && weights[i] > 0)
+        .collect();
+    while !open.is_empty() && remaining > 0 {
+        let weight_sum: usize = open.iter().map(|&i| weights[i]).sum();
+        let share = |i: usize| remaining * weights[i] / weight_sum;
+        let satisfied: Vec<usize> = open
+            .iter()
+            .copied()
+            .filter(|&i| needs[i] - alloc[i] <= share(i))
+            .collect();
+        if satisfied.is_empty() {
+            for &i in &open {
+                alloc[i] += share(i);
+            }
+            break;
+        }
+        for &i in &satisfied {
+            remaining -= needs[i] - alloc[i];
+            alloc[i] = needs[i];
+        }
+        open.retain(|i| !satisfied.contains(i));
+    }
+    alloc
+}
+
+fn omitted_marker(count: usize) -> String {
+    format!("... ({} 行省略) ...", count)
+}
+
+// 選んだ行を元の順に並べ、飛ばした所に省略の印を入れる
+fn render(lines: &[String], kept: &[bool]) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut skipped = 0;
+    for (line, &keep) in lines.iter().zip(kept) {
+        if keep {
+            if skipped > 0 {
+                out.push(omitted_marker(skipped));
+                skipped = 0;
+            }
+            out.push(line.clone());
+        } else {
+            skipped += 1;
+        }
+    }
+    if skipped > 0 {
+        out.push(omitted_marker(skipped));
+    }
+    out
+}
+
+fn lines_tokens(counter: &TokenCounter, lines: &[String]) -> usize {
+    lines.iter().map(|l| counter.count(l) + 1).sum()
+}
+
+// 予算内で残す行を選ぶ (選んだ行と、そのうち元の行の数を返す)
+// allowed が false の行 (間引きの方式で落とした行) は選ばない
+// 優先度: 区間ごとの重要な行 > 先頭と末尾 > 残りを等間隔に (粗い間隔から順に)
+fn select_lines(
+    kind: SectionKind,
+    lines: &[String],
+    allowed: &[bool],
+    budget: usize,
+    counter: &TokenCounter,
+    patterns: &Patterns,
+) -> (Vec<String>, usize) {
+    let n = lines.len();
+    let sampled = render(lines, allowed);
+    if lines_tokens(counter, &sampled) <= budget {
+        return (sampled, allowed.iter().filter(|&&a| a).count());
+    }
+    let (head, tail) = kind.edges();
+    let mut order: Vec<usize> = (0..n).filter(|&i| allowed[i]).collect();
+    order.sort_by_cached_key(|&i| {
+        let tier = if patterns.is_key(kind, &lines[i]) {
+            0
+        } else if i < head || i + tail >= n {
+            1
+        } else {
+            2
+        };
+        (tier, std::cmp::Reverse((i + 1).trailing_zeros()), i)
+    });
+
+    // 省略の印は残した行の数 + 1 を超えないので、その分を行ごとに見込んでおく
+    let marker = counter.count(&omitted_marker(n)) + 1;
+    let budget = budget.saturating_sub(marker);
+    let mut selected = lines.to_vec();
+    let mut kept = vec![false; n];
+    let mut used = 0;
+    for &i in &order {
+        let cost = counter.count(&lines[i]) + 1 + marker;
+        if used + cost <= budget {
+            kept[i] = true;
+            used += cost;
+        } else if budget.saturating_sub(used) >= MIN_TRUNCATED_TOKENS + marker {
+            selected[i] = counter.truncate(&lines[i], budget - used - marker - 1);
+            kept[i] = true;
+            used = (used + counter.count(&selected[i]) + 1 + marker).min(budget);
+        }
+    }
+
+    let kept_lines = kept.iter().filter(|&&k| k).count();
+    if kept_lines == 0 {
+        return (vec![omitted_marker(n)], 0);
+    }
+    (render(&selected, &kept), kept_lines)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptOptions {
+    pub model: String,
+    // 省略時はモデルごとの既定値
+    pub token_budget: Option<usize>,
+    pub compression: CompressionConfig,
+}
+
+// 固定の部分 (指示・数値的な問題・IIS) とログから、予算内のプロンプトを組み立てる
+pub fn build(fixed: &[(SectionKind, String)], log: &str, options: &PromptOptions) -> BuiltPrompt {
+    let model = options.model.as_str();
+    let counter = TokenCounter::for_model(model);
+    let patterns = Patterns::new();
+    let budget = options
+        .token_budget
+        .unwrap_or_else(|| default_budget(model));
+
+    let mut allocations = Vec::new();
+    let mut fixed_tokens = 0;
+    for (kind, text) in fixed {
+        let tokens = counter.count(text);
+        let lines = text.lines().count();
+        fixed_tokens += tokens;
+        allocations.push(SectionAllocation {
+            section: *kind,
+            full_tokens: tokens,
+            budget_tokens: tokens,
+            used_tokens: tokens,
+            lines,
+            sampled_lines: lines,
+            kept_lines: lines,
+        });
+    }
+
+    let sections = split_sections(log, &patterns, &options.compression);
+    // 区間の見出し行の分を先に差し引く
+    let heading_tokens: usize = sections
+        .iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .map(|(kind, _)| counter.count(&section_heading(*kind)) + 1)
+        .sum();
+    let log_budget = budget
+        .saturating_sub(fixed_tokens + counter.count("[LOG]") + 1 + heading_tokens)
+        .max(MIN_LOG_TOKENS);
+
+    // 進捗表の行は、予算を配る前に指定の方式で間引く
+    let masks: Vec<Vec<bool>> = sections
+        .iter()
+        .map(|(kind, lines)| match kind {
+            SectionKind::Tree => options.compression.table_mask(lines),
+            _ => vec![true; lines.len()],
+        })
+        .collect();
+    let needs: Vec<usize> = sections
+        .iter()
+        .zip(&masks)
+        .map(|((_, lines), allowed)| lines_tokens(&counter, &render(lines, allowed)))
+        .collect();
+    let weights: Vec<usize> = sections.iter().map(|(kind, _)| kind.weight()).collect();
+    let budgets = allocate(&needs, &weights, log_budget);
+
+    let mut log_text = String::new();
+    for (((kind, lines), allowed), section_budget) in sections.iter().zip(&masks).zip(&budgets) {
+        if lines.is_empty() {
+            continue;
+        }
+        let (selected, kept_lines) =
+            select_lines(*kind, lines, allowed, *section_budget, &counter, &patterns);
+        allocations.push(SectionAllocation {
+            section: *kind,
+            full_tokens: lines_tokens(&counter, lines),
+            budget_tokens: *section_budget,
+            used_tokens: lines_tokens(&counter, &selected),
+            lines: lines.len(),
+            sampled_lines: allowed.iter().filter(|&&a| a).count(),
+            kept_lines,
+        });
+        log_text.push_str(&section_heading(*kind));
+        log_text.push('\n');
+        for line in selected {
+            log_text.push_str(&line);
+            log_text.push('\n');
+        }
+    }
+
+    let fixed_text: String = fixed.iter().map(|(_, text)| text.as_str()).collect();
+    let prompt = format!("{}[LOG]\n{}", fixed_text, log_text);
+    BuiltPrompt {
+        total_tokens: counter.count(&prompt),
+        prompt,
+        model: model.to_string(),
+        budget_tokens: budget,
+        sections: allocations,
+    }
+}
+
+fn section_heading(kind: SectionKind) -> String {
+    format!("--- {} ---", kind.label())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODELS: [&str; 4] = ["gemini-2.5-flash", "gpt-4o", "gpt-4-turbo", "llama3.1"];
+
+    fn mixed_line(n: usize) -> String {
+        (0..n)
+            .map(|i| {
+                if i % 3 == 0 {
+                    "解の更新 "
+                } else {
+                    "H 12 0 1.5e+03 "
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn truncate_stays_within_budget() {
+        for model in MODELS {
+            let counter = TokenCounter::for_model(model);
+            for text in [mixed_line(20), "あ".repeat(80), "x".repeat(400)] {
+                for max in 0..60 {
+                    let cut = counter.truncate(&text, max);
+                    assert!(
+                        counter.count(&cut) <= max,
+                        "{} max={} count={}",
+                        model,
+                        max,
+                        counter.count(&cut)
+                    );
+                    let body = cut.strip_suffix('…').unwrap_or(&cut);
+                    assert!(text.starts_with(body));
+                    assert!(text.is_char_boundary(body.len()));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn truncate_keeps_short_text() {
+        let counter = TokenCounter::for_model("llama3.1");
+        assert_eq!(
+            counter.truncate("Optimal solution found", 100),
+            "Optimal solution found"
+        );
+        // ローカルモデルの係数では "…" が 1.5 トークンになる
+        let cut = counter.truncate(&"最適化".repeat(30), 33);
+        assert!(cut.ends_with('…'));
+        assert!(counter.count(&cut) <= 33);
+    }
+
+    #[test]
+    fn select_lines_stays_within_budget() {
+        let patterns = Patterns::new();
+        let lines: Vec<String> = (0..40).map(|i| mixed_line(i % 7 + 4)).collect();
+        let allowed = vec![true; lines.len()];
+        for model in MODELS {
+            let counter = TokenCounter::for_model(model);
+            for budget in (0..400).step_by(7) {
+                let (selected, kept) = select_lines(
+                    SectionKind::Tree,
+                    &lines,
+                    &allowed,
+                    budget,
+                    &counter,
+                    &patterns,
+                );
+                assert!(kept <= lines.len());
+                if kept > 0 {
+                    assert!(
+                        lines_tokens(&counter, &selected) <= budget,
+                        "{} budget={}",
+                        model,
+                        budget
+                    );
+                }
+                for line in &selected {
+                    let body = line.strip_suffix('…').unwrap_or(line);
+                    assert!(body.starts_with("...") || lines.iter().any(|l| l.starts_with(body)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn select_lines_keeps_everything_within_budget() {
+        let patterns = Patterns::new();
+        let counter = TokenCounter::for_model("gpt-4o");
+        let lines = vec![
+            "Presolve removed 10 rows and 5 columns".to_string(),
+            "Presolve time: 0.01s".to_string(),
+        ];
+        let (selected, kept) = select_lines(
+            SectionKind::Presolve,
+            &lines,
+            &[true, true],
+            1000,
+            &counter,
+            &patterns,
+        );
+        assert_eq!(selected, lines);
+        assert_eq!(kept, 2);
+    }
+}
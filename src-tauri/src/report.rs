@@ -0,0 +1,692 @@
+use regex::Regex;
+use std::collections::BTreeMap;
+
+use crate::iis::IisReport;
+use crate::log_parser::{self, MipProgress, ProgressEvent};
+use crate::numerics::{self, NumericIssue, Severity};
+use crate::summary::{self, ModelSize, SolveStatus, SolveSummary};
+
+// API キーやネットワークが無い環境向けに、ログだけから決まった形の Markdown レポートを作る
+// (AI解析と同じ見出しの構成で、同じログからは常に同じレポートになる)
+
+// 履歴の解析結果に記録するモデル名
+pub const OFFLINE_MODEL: &str = "offline";
+
+// ギャップの推移の表に載せる暫定解の更新の上限
+const MAX_IMPROVEMENT_ROWS: usize = 20;
+// この割合より前に止まった改善は「停滞」とみなす
+const STALL_RATIO: f64 = 0.5;
+// ギャップがこれ以下になった時刻を示す (%)
+const GAP_MILESTONES: [f64; 4] = [10.0, 5.0, 1.0, 0.1];
+
+// 推奨事項の判定に使う、ログから読み取った事実
+struct Facts<'a> {
+    summary: SolveSummary,
+    params: BTreeMap<String, String>,
+    mip: Vec<MipProgress>,
+    issues: Vec<NumericIssue>,
+    iis: Option<&'a IisReport>,
+    root_time: Option<f64>,
+}
+
+impl Facts<'_> {
+    fn status(&self) -> Option<SolveStatus> {
+        self.summary.status
+    }
+
+    fn runtime(&self) -> f64 {
+        self.summary
+            .runtime
+            .or_else(|| self.mip.last().map(|p| p.time))
+            .unwrap_or(0.0)
+    }
+
+    fn has_incumbent(&self) -> bool {
+        self.summary.best_objective.is_some() || self.mip.iter().any(|p| p.incumbent.is_some())
+    }
+
+    fn open_gap(&self) -> Option<f64> {
+        self.summary
+            .gap
+            .or_else(|| self.mip.last().and_then(|p| p.gap))
+            .filter(|g| *g > 0.01)
+    }
+
+    // 最終的な暫定解が見つかった時刻
+    fn best_incumbent_time(&self) -> Option<f64> {
+        let last = self.mip.iter().rev().find_map(|p| p.incumbent)?;
+        self.mip
+            .iter()
+            .find(|p| p.incumbent == Some(last))
+            .map(|p| p.time)
+    }
+
+    // 最良の境界が最後に動いた時刻
+    fn last_bound_change(&self) -> Option<f64> {
+        let mut last = None;
+        let mut prev: Option<f64> = None;
+        for p in &self.mip {
+            if let Some(b) = p.best_bound {
+                if prev.is_some_and(|v| v != b) {
+                    last = Some(p.time);
+                }
+                prev = Some(b);
+            }
+        }
+        last
+    }
+
+    fn stalled(&self, at: Option<f64>) -> bool {
+        let runtime = self.runtime();
+        runtime > 0.0 && at.is_some_and(|t| t < runtime * STALL_RATIO)
+    }
+
+    fn presolve_reduction(&self) -> Option<f64> {
+        let (orig, pre) = (
+            self.summary.original_model.as_ref()?,
+            self.summary.presolved_model.as_ref()?,
+        );
+        let before = (orig.rows + orig.columns) as f64;
+        (before > 0.0).then(|| 1.0 - (pre.rows + pre.columns) as f64 / before)
+    }
+}
+
+// 推奨事項の表 (当てはまるものを上から順に載せる)
+type Rule = fn(&Facts) -> Option<String>;
+
+const RULES: &[Rule] = &[
+    |f| {
+        (f.status() == Some(SolveStatus::Infeasible)).then(|| match f.iis {
+            Some(iis) => format!(
+                "モデルが実行不可能です。IIS に含まれる {} 本の制約と {} 個の上下限が同時に満たせない原因です。該当する制約の右辺や上下限を見直してください。",
+                iis.constraints.len(),
+                iis.bounds.len()
+            ),
+            None => "モデルが実行不可能です。`model.computeIIS()` と `model.write(\"model.ilp\")` で IIS を求め、矛盾する制約を特定してください。".to_string(),
+        })
+    },
+    |f| {
+        (f.status() == Some(SolveStatus::InfeasibleOrUnbounded)).then(|| {
+            "実行不可能か非有界かが区別できていません。`DualReductions=0` を設定して解き直すと判定できます。".to_string()
+        })
+    },
+    |f| {
+        (f.status() == Some(SolveStatus::Unbounded)).then(|| {
+            "目的関数が非有界です。上下限の付け忘れや、目的関数の符号 (最小化 / 最大化) を確認してください。".to_string()
+        })
+    },
+    |f| {
+        matches!(
+            f.status(),
+            Some(SolveStatus::Numeric) | Some(SolveStatus::Suboptimal)
+        )
+        .then(|| {
+            "数値的な理由で最適性を確認できませんでした。`NumericFocus=2` 以上を設定し、係数の範囲を見直してください。".to_string()
+        })
+    },
+    |f| {
+        f.issues
+            .iter()
+            .any(|i| i.severity == Severity::Critical)
+            .then(|| {
+                "深刻な数値的な問題が検出されています (下の「数値的な警告」を参照)。解の信頼性を確かめるため `NumericFocus` を上げて解き直してください。".to_string()
+            })
+    },
+    |f| {
+        (f.status() == Some(SolveStatus::Interrupted))
+            .then(|| "計算が途中で中断されたため、結果は暫定的なものです。".to_string())
+    },
+    |f| {
+        (!f.mip.is_empty() && !f.has_incumbent()).then(|| {
+            "実行可能解が1つも見つかっていません。`MIPFocus=1` や `NoRelHeurTime` で解の発見を優先するか、初期解 (MIP start) を与えてください。".to_string()
+        })
+    },
+    |f| {
+        (f.open_gap().is_some() && f.stalled(f.last_bound_change())).then(|| {
+            format!(
+                "最良の境界 (BestBd) は {} 秒以降ほとんど動いていません。`MIPFocus=3` や `Cuts=2` で境界の改善を優先するか、定式化 (Big-M の縮小、対称性の除去) を見直してください。",
+                fmt_secs(f.last_bound_change().unwrap_or(0.0))
+            )
+        })
+    },
+    |f| {
+        (f.open_gap().is_some() && f.stalled(f.best_incumbent_time())).then(|| {
+            format!(
+                "暫定解は {} 秒以降更新されていません。`MIPFocus=1` や `Heuristics` を上げて、より良い解の探索を優先してください。",
+                fmt_secs(f.best_incumbent_time().unwrap_or(0.0))
+            )
+        })
+    },
+    |f| {
+        (f.status() == Some(SolveStatus::TimeLimit) && f.open_gap().is_some()).then(|| {
+            let limit = f
+                .params
+                .get("TimeLimit")
+                .map(|v| format!(" (TimeLimit={})", v))
+                .unwrap_or_default();
+            format!(
+                "時間制限{}でギャップ {:.2}% が残っています。許容できるギャップなら `MIPGap` を緩め、そうでなければ制限時間を延ばしてください。",
+                limit,
+                f.open_gap().unwrap_or(0.0)
+            )
+        })
+    },
+    |f| {
+        let runtime = f.runtime();
+        let presolve = f.summary.presolve_time?;
+        (runtime > 10.0 && presolve > runtime * 0.3).then(|| {
+            format!(
+                "前処理に全体の {:.0}% の時間が掛かっています。`Presolve=1` で前処理を軽くすることを検討してください。",
+                presolve / runtime * 100.0
+            )
+        })
+    },
+    |f| {
+        let rows = f.summary.original_model.as_ref()?.rows;
+        (rows >= 10_000 && f.presolve_reduction()? < 0.05).then(|| {
+            "前処理でモデルがほとんど小さくなっていません。`Presolve=2` で強い前処理を試してください。".to_string()
+        })
+    },
+    |f| {
+        let runtime = f.runtime();
+        let root = f.root_time?;
+        (runtime > 10.0 && root > runtime * 0.5).then(|| {
+            format!(
+                "ルート緩和 (LP) の求解に全体の {:.0}% の時間が掛かっています。`Method=2` (バリア法) や `Method=3` (並行) を試してください。",
+                root / runtime * 100.0
+            )
+        })
+    },
+];
+
+// 1e9 以上や小さな値は指数表記、それ以外は小数点以下6桁まで (末尾の 0 は省く)
+fn fmt_num(v: f64) -> String {
+    if v != 0.0 && (v.abs() >= 1e9 || v.abs() < 1e-4) {
+        return format!("{:.6e}", v);
+    }
+    let s = format!("{:.6}", v);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+fn fmt_secs(v: f64) -> String {
+    format!("{:.1}", v)
+}
+
+fn fmt_opt<T: ToString>(v: Option<T>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_else(|| "-".to_string())
+}
+
+fn status_text(status: Option<SolveStatus>) -> (&'static str, &'static str) {
+    match status {
+        Some(SolveStatus::Optimal) => (
+            "最適解",
+            "指定された許容ギャップの範囲で最適解が得られました。",
+        ),
+        Some(SolveStatus::Suboptimal) => {
+            ("準最適", "数値的な理由で最適性を確認できずに終了しました。")
+        }
+        Some(SolveStatus::Infeasible) => {
+            ("実行不可能", "すべての制約を同時に満たす解が存在しません。")
+        }
+        Some(SolveStatus::Unbounded) => ("非有界", "目的関数がいくらでも改善できる状態です。"),
+        Some(SolveStatus::InfeasibleOrUnbounded) => (
+            "実行不可能または非有界",
+            "前処理の段階で実行不可能か非有界のどちらかと判定されました。",
+        ),
+        Some(SolveStatus::TimeLimit) => ("時間制限", "制限時間に達したため打ち切られました。"),
+        Some(SolveStatus::NodeLimit) => ("ノード数制限", "探索ノード数の上限に達しました。"),
+        Some(SolveStatus::SolutionLimit) => (
+            "解の数の制限",
+            "指定した数の解が見つかった時点で終了しました。",
+        ),
+        Some(SolveStatus::IterationLimit) => ("反復回数制限", "反復回数の上限に達しました。"),
+        Some(SolveStatus::WorkLimit) => ("作業量制限", "作業量 (work units) の上限に達しました。"),
+        Some(SolveStatus::MemoryLimit) => ("メモリ制限", "メモリの上限に達しました。"),
+        Some(SolveStatus::Interrupted) => ("中断", "ユーザーの操作などで途中で中断されました。"),
+        Some(SolveStatus::Numeric) => (
+            "数値的な困難",
+            "数値的な困難により計算を続けられませんでした。",
+        ),
+        None => (
+            "不明",
+            "ログから終了状態を読み取れませんでした (途中で終了した可能性があります)。",
+        ),
+    }
+}
+
+// 複数回の最適化を含むログでは、最後の1回だけを対象にする
+fn last_solve(log: &str) -> &str {
+    match log.rfind("Optimize a model") {
+        Some(pos) => &log[log[..pos].rfind('\n').map_or(0, |p| p + 1)..],
+        None => log,
+    }
+}
+
+fn parse_root_time(log: &str) -> Option<f64> {
+    let re = Regex::new(
+        r"^Root relaxation: .*?, \d+ iterations, ([-+]?\d+\.?\d*(?:[eE][-+]?\d+)?) seconds",
+    )
+    .unwrap();
+    log.lines()
+        .rev()
+        .find_map(|l| re.captures(l.trim()))
+        .and_then(|c| c[1].parse().ok())
+}
+
+fn size_row(label: &str, size: &ModelSize) -> String {
+    format!(
+        "| {} | {} | {} | {} | {} / {} / {} |\n",
+        label,
+        size.rows,
+        size.columns,
+        size.nonzeros,
+        fmt_opt(size.continuous),
+        fmt_opt(size.integer),
+        fmt_opt(size.binary)
+    )
+}
+
+fn summary_section(f: &Facts, out: &mut String) {
+    let s = &f.summary;
+    out.push_str("## 結果サマリ\n\n| 項目 | 値 |\n| --- | --- |\n");
+    let rows = [
+        ("終了状態", status_text(s.status).0.to_string()),
+        ("目的関数値", fmt_opt(s.best_objective.map(fmt_num))),
+        ("最良の境界", fmt_opt(s.best_bound.map(fmt_num))),
+        ("ギャップ", fmt_opt(s.gap.map(|g| format!("{:.4}%", g)))),
+        ("実行時間 (秒)", fmt_opt(s.runtime.map(fmt_secs))),
+        ("探索ノード数", fmt_opt(s.explored_nodes)),
+        ("単体法の反復回数", fmt_opt(s.simplex_iterations)),
+        ("見つかった解の数", fmt_opt(s.solution_count)),
+    ];
+    for (label, value) in rows {
+        out.push_str(&format!("| {} | {} |\n", label, value));
+    }
+    out.push('\n');
+}
+
+fn status_section(f: &Facts, out: &mut String) {
+    let (label, text) = status_text(f.status());
+    out.push_str(&format!("## 終了状態\n\n**{}**: {}\n\n", label, text));
+    if let Some(iis) = f.iis {
+        out.push_str(&format!(
+            "IIS には {} 本の制約と {} 個の上下限が含まれています。\n\n",
+            iis.constraints.len(),
+            iis.bounds.len()
+        ));
+        for c in iis.constraints.iter().take(10) {
+            let name = c.name.as_deref().unwrap_or("(名前なし)");
+            out.push_str(&format!("- `{}`: `{}`\n", name, c.expression));
+        }
+        if iis.constraints.len() > 10 {
+            out.push_str(&format!("- ... (残り {} 本)\n", iis.constraints.len() - 10));
+        }
+        out.push('\n');
+    }
+}
+
+fn gap_section(f: &Facts, out: &mut String) {
+    out.push_str("## ギャップの推移\n\n");
+    if f.mip.is_empty() {
+        out.push_str(
+            "分枝限定法の進捗表がありません (LP として解かれたか、ルートで終了しました)。\n\n",
+        );
+        return;
+    }
+
+    // 暫定解が更新された行
+    let mut improvements: Vec<&MipProgress> = Vec::new();
+    let mut last: Option<f64> = None;
+    for p in &f.mip {
+        if p.incumbent.is_some() && p.incumbent != last {
+            improvements.push(p);
+            last = p.incumbent;
+        }
+    }
+    if improvements.is_empty() {
+        out.push_str("暫定解は見つかっていません。\n\n");
+    } else {
+        out.push_str("| 時刻 (秒) | 暫定解 | 境界 | ギャップ |\n| --- | --- | --- | --- |\n");
+        let n = improvements.len();
+        // 多い場合は最初の5件と最後の件を載せる
+        let head = if n > MAX_IMPROVEMENT_ROWS { 5 } else { n };
+        let tail_start = n.saturating_sub(MAX_IMPROVEMENT_ROWS - head).max(head);
+        for (i, p) in improvements.iter().enumerate() {
+            if i == head && tail_start > head {
+                out.push_str(&format!("| ... | ({} 件省略) | | |\n", tail_start - head));
+            }
+            if i < head || i >= tail_start {
+                out.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    fmt_secs(p.time),
+                    fmt_opt(p.incumbent.map(fmt_num)),
+                    fmt_opt(p.best_bound.map(fmt_num)),
+                    fmt_opt(p.gap.map(|g| format!("{:.2}%", g)))
+                ));
+            }
+        }
+        out.push('\n');
+    }
+
+    let milestones: Vec<String> = GAP_MILESTONES
+        .iter()
+        .filter_map(|&m| {
+            let p = f.mip.iter().find(|p| p.gap.is_some_and(|g| g <= m))?;
+            Some(format!("{}% 以下: {} 秒", m, fmt_secs(p.time)))
+        })
+        .collect();
+    if !milestones.is_empty() {
+        out.push_str(&format!(
+            "ギャップの到達時刻: {}\n\n",
+            milestones.join(" / ")
+        ));
+    }
+}
+
+fn presolve_section(f: &Facts, out: &mut String) {
+    out.push_str("## 前処理の効果\n\n");
+    let (Some(orig), Some(pre)) = (&f.summary.original_model, &f.summary.presolved_model) else {
+        out.push_str("ログに前処理前後のモデル規模がありません。\n\n");
+        return;
+    };
+    out.push_str(
+        "| | 行 | 列 | 非ゼロ | 連続 / 整数 / バイナリ |\n| --- | --- | --- | --- | --- |\n",
+    );
+    out.push_str(&size_row("前処理前", orig));
+    out.push_str(&size_row("前処理後", pre));
+    let ratio = |a: u64, b: u64| {
+        if a == 0 {
+            0.0
+        } else {
+            (1.0 - b as f64 / a as f64) * 100.0
+        }
+    };
+    out.push_str(&format!(
+        "\n行が {:.1}%、列が {:.1}%、非ゼロが {:.1}% 減りました",
+        ratio(orig.rows, pre.rows),
+        ratio(orig.columns, pre.columns),
+        ratio(orig.nonzeros, pre.nonzeros)
+    ));
+    if let Some(t) = f.summary.presolve_time {
+        out.push_str(&format!(" (前処理 {} 秒)", fmt_secs(t)));
+    }
+    out.push_str("。\n\n");
+}
+
+fn numerics_section(f: &Facts, out: &mut String) {
+    out.push_str("## 数値的な警告\n\n");
+    if f.issues.is_empty() {
+        out.push_str("数値的な問題は検出されませんでした。\n\n");
+        return;
+    }
+    for issue in &f.issues {
+        let severity = match issue.severity {
+            Severity::Critical => "重大",
+            Severity::Warning => "警告",
+            Severity::Info => "情報",
+        };
+        out.push_str(&format!(
+            "- **[{}]** {} (x{})\n  - 対策: {}\n",
+            severity, issue.message, issue.count, issue.hint
+        ));
+    }
+    out.push('\n');
+}
+
+fn time_section(f: &Facts, out: &mut String) {
+    out.push_str("## 時間の内訳\n\n| 段階 | 秒 |\n| --- | --- |\n");
+    let runtime = f.runtime();
+    let presolve = f.summary.presolve_time;
+    let mut rows: Vec<(&str, Option<f64>)> =
+        vec![("前処理", presolve), ("ルート緩和", f.root_time)];
+    if !f.mip.is_empty() {
+        let tree = runtime - presolve.unwrap_or(0.0) - f.root_time.unwrap_or(0.0);
+        rows.push(("分枝限定法 (残り)", Some(tree.max(0.0))));
+        rows.push((
+            "最初の解が見つかった時刻",
+            f.mip.iter().find(|p| p.incumbent.is_some()).map(|p| p.time),
+        ));
+        rows.push(("最良の解が見つかった時刻", f.best_incumbent_time()));
+        rows.push(("境界が最後に改善した時刻", f.last_bound_change()));
+    }
+    rows.push(("合計", f.summary.runtime));
+    for (label, value) in rows {
+        out.push_str(&format!(
+            "| {} | {} |\n",
+            label,
+            fmt_opt(value.map(fmt_secs))
+        ));
+    }
+    out.push('\n');
+}
+
+fn recommendation_section(f: &Facts, out: &mut String) {
+    out.push_str("## 推奨事項\n\n");
+    let items: Vec<String> = RULES.iter().filter_map(|rule| rule(f)).collect();
+    if items.is_empty() {
+        out.push_str("- 特に問題は見つかりませんでした。現在の設定のままで問題ありません。\n");
+    }
+    for item in items {
+        out.push_str(&format!("- {}\n", item));
+    }
+    if !f.params.is_empty() {
+        let params: Vec<String> = f
+            .params
+            .iter()
+            .map(|(k, v)| format!("`{}={}`", k, v))
+            .collect();
+        out.push_str(&format!(
+            "\n今回の実行で変更されていたパラメータ: {}\n",
+            params.join(", ")
+        ));
+    }
+}
+
+fn facts<'a>(log: &str, iis: Option<&'a IisReport>) -> Facts<'a> {
+    let solve = last_solve(log);
+    Facts {
+        summary: summary::parse_solve_summary(log),
+        params: summary::parse_parameter_changes(log),
+        mip: log_parser::parse_progress(solve)
+            .into_iter()
+            .filter_map(|e| match e {
+                ProgressEvent::Mip(p) => Some(p),
+                _ => None,
+            })
+            .collect(),
+        issues: numerics::detect(log),
+        iis,
+        root_time: parse_root_time(solve),
+    }
+}
+
+pub fn offline_report(log: &str, iis: Option<&IisReport>) -> String {
+    let facts = facts(log, iis);
+    let mut out = String::from(
+        "# 最適化計算レポート (オフライン)\n\n> AI を使わずに、ログからルールに基づいて作成したレポートです。\n\n",
+    );
+    summary_section(&facts, &mut out);
+    status_section(&facts, &mut out);
+    gap_section(&facts, &mut out);
+    presolve_section(&facts, &mut out);
+    numerics_section(&facts, &mut out);
+    time_section(&facts, &mut out);
+    recommendation_section(&facts, &mut out);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::iis::IisConstraint;
+
+    const MIP_HEADER: &str = "\
+    Nodes    |    Current Node    |     Objective Bounds      |     Work
+ Expl Unexpl |  Obj  Depth IntInf | Incumbent    BestBd   Gap | It/Node Time
+
+";
+
+    // 分枝限定法の表 (時刻, 暫定解, 境界) と、時間制限で終わった最終行
+    fn mip_log(rows: &[(u32, Option<f64>, f64)]) -> String {
+        let mut log = String::from("Set parameter TimeLimit to value 100\n");
+        log.push_str("Optimize a model with 100 rows, 50 columns and 400 nonzeros\n");
+        log.push_str(MIP_HEADER);
+        for (i, (time, incumbent, bound)) in rows.iter().enumerate() {
+            let (incumbent, gap) = match incumbent {
+                Some(v) => (
+                    format!("{:.5}", v),
+                    format!("{:.2}%", (v - bound) / v * 100.0),
+                ),
+                None => ("-".to_string(), "-".to_string()),
+            };
+            log.push_str(&format!(
+                "{:>6} {:>5} {:.5}   10   5 {:>10} {:.5} {:>6}  10.0 {:>4}s\n",
+                i * 100,
+                50,
+                bound,
+                incumbent,
+                bound,
+                gap,
+                time
+            ));
+        }
+        let (_, incumbent, bound) = rows.last().unwrap();
+        log.push_str("\nExplored 500 nodes (5000 simplex iterations) in 100.00 seconds\n");
+        log.push_str("Time limit reached\n");
+        match incumbent {
+            Some(v) => log.push_str(&format!(
+                "Best objective {:e}, best bound {:e}, gap {:.4}%\n",
+                v,
+                bound,
+                (v - bound) / v * 100.0
+            )),
+            None => log.push_str(&format!(
+                "Best objective -, best bound {:e}, gap -\n",
+                bound
+            )),
+        }
+        log
+    }
+
+    fn recommendations(log: &str) -> Vec<String> {
+        let facts = facts(log, None);
+        RULES.iter().filter_map(|rule| rule(&facts)).collect()
+    }
+
+    fn single(log: &str, expected: &str) {
+        let items = recommendations(log);
+        assert_eq!(items.len(), 1, "{:?}", items);
+        assert!(items[0].contains(expected), "{}", items[0]);
+    }
+
+    #[test]
+    fn status_rules() {
+        single("Infeasible model\n", "computeIIS");
+        single("Model is infeasible or unbounded\n", "DualReductions=0");
+        single("Unbounded model\n", "非有界");
+        single(
+            "Sub-optimal termination - objective 1.0e+00\n",
+            "NumericFocus=2",
+        );
+        single(
+            "Optimal solution found (tolerance 1.00e-04)\nWarning: max constraint violation (1.0e-03) exceeds tolerance\n",
+            "深刻な数値的な問題",
+        );
+        single("Interrupt request received\n", "中断");
+        assert!(recommendations("Optimal solution found (tolerance 1.00e-04)\n").is_empty());
+    }
+
+    #[test]
+    fn infeasible_rule_uses_iis() {
+        let iis = IisReport {
+            constraints: vec![IisConstraint {
+                name: Some("c1".to_string()),
+                expression: "x + y".to_string(),
+                sense: Some(">=".to_string()),
+                rhs: Some(3.0),
+                variables: vec!["x".to_string(), "y".to_string()],
+            }],
+            ..Default::default()
+        };
+        let facts = facts("Infeasible model\n", Some(&iis));
+        let items: Vec<String> = RULES.iter().filter_map(|rule| rule(&facts)).collect();
+        assert_eq!(items.len(), 1);
+        assert!(items[0].contains("1 本の制約と 0 個の上下限"));
+    }
+
+    #[test]
+    fn no_incumbent_rule() {
+        let items = recommendations(&mip_log(&[(0, None, 1000.0), (100, None, 1010.0)]));
+        assert!(items
+            .iter()
+            .any(|i| i.contains("実行可能解が1つも見つかっていません")));
+    }
+
+    #[test]
+    fn stalled_bound_rule() {
+        // 境界は 10 秒以降動かず、暫定解は 90 秒に更新された
+        let items = recommendations(&mip_log(&[
+            (0, Some(1500.0), 1000.0),
+            (10, Some(1500.0), 1100.0),
+            (50, Some(1500.0), 1100.0),
+            (90, Some(1300.0), 1100.0),
+            (100, Some(1300.0), 1100.0),
+        ]));
+        assert_eq!(items.len(), 2, "{:?}", items);
+        assert!(items[0].contains("最良の境界 (BestBd) は 10.0 秒以降"));
+        assert!(items[1].contains("時間制限 (TimeLimit=100)でギャップ 15.38%"));
+    }
+
+    #[test]
+    fn stalled_incumbent_rule() {
+        // 暫定解は 10 秒以降更新されず、境界は最後まで動いている
+        let items = recommendations(&mip_log(&[
+            (0, None, 1000.0),
+            (10, Some(1300.0), 1050.0),
+            (50, Some(1300.0), 1100.0),
+            (100, Some(1300.0), 1150.0),
+        ]));
+        assert_eq!(items.len(), 2, "{:?}", items);
+        assert!(items[0].contains("暫定解は 10.0 秒以降更新されていません"));
+        assert!(items[1].contains("TimeLimit=100"));
+    }
+
+    #[test]
+    fn presolve_and_root_rules() {
+        single(
+            "Presolve time: 5.00s\nSolved in 100 iterations and 12.00 seconds\nOptimal objective 1.0e+00\n",
+            "前処理に全体の 42%",
+        );
+        single(
+            "Optimize a model with 20000 rows, 10000 columns and 80000 nonzeros\nPresolved: 19900 rows, 9950 columns, 79000 nonzeros\nOptimal solution found (tolerance 1.00e-04)\n",
+            "Presolve=2",
+        );
+        single(
+            "Root relaxation: objective 1.0e+03, 5000 iterations, 8.00 seconds (2.00 work units)\nExplored 1 nodes (5000 simplex iterations) in 12.00 seconds\nOptimal solution found (tolerance 1.00e-04)\n",
+            "ルート緩和 (LP) の求解に全体の 67%",
+        );
+    }
+
+    #[test]
+    fn report_has_every_section() {
+        let log = mip_log(&[(0, Some(1500.0), 1000.0), (100, Some(1300.0), 1100.0)]);
+        let report = offline_report(&log, None);
+        for heading in [
+            "# 最適化計算レポート (オフライン)",
+            "## 結果サマリ",
+            "## 終了状態",
+            "## ギャップの推移",
+            "## 前処理の効果",
+            "## 数値的な警告",
+            "## 時間の内訳",
+            "## 推奨事項",
+        ] {
+            assert!(report.contains(heading), "{}", heading);
+        }
+        assert!(report.contains("**時間制限**"));
+        assert!(report.contains("| 100.0 | 1300 | 1100 | 15.38% |"));
+        assert!(report.contains("`TimeLimit=100`"));
+        // 同じログからは常に同じレポートになる
+        assert_eq!(report, offline_report(&log, None));
+    }
+}
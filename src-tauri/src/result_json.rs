@@ -0,0 +1,289 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// スクリプトが標準出力に書き出す結果JSONの取り出し
+//
+// プロトコル:
+//   ---JSON_START---            名前なしのブロック (名前は "default" として扱う)
+//   {"obj": 12.0, "x": [...]}
+//   ---JSON_END---
+//
+//   ---JSON_START:solution---   名前付きブロック (英数字と _ - . のみ)
+//   {...}
+//   ---JSON_END---              (---JSON_END:solution--- でもよい)
+//
+// 1回の実行で複数のブロックを出力できる。開始行と終了行の間は1つのJSON値でなければならない。
+// マーカーと同じ行にJSONを書いてもよい (例: print("---JSON_START---" + json.dumps(r) + "---JSON_END---"))。
+
+pub const DEFAULT_BLOCK_NAME: &str = "default";
+
+const START_MARKER: &str = "---JSON_START";
+const END_MARKER: &str = "---JSON_END";
+const MARKER_TAIL: &str = "---";
+
+// 1つの結果ブロック (不正な場合は value が None で error に理由が入る)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResultBlock {
+    pub name: String,
+    pub value: Option<Value>,
+    pub error: Option<String>,
+    // 解析できなかった場合のみ元の文字列を残す
+    pub raw: Option<String>,
+}
+
+impl ResultBlock {
+    fn from_raw(name: String, raw: &str) -> Self {
+        if !is_valid_name(&name) {
+            return ResultBlock {
+                error: Some(format!("ブロック名が不正です: \"{}\"", name)),
+                name,
+                value: None,
+                raw: Some(raw.trim().to_string()),
+            };
+        }
+        match serde_json::from_str::<Value>(raw.trim()) {
+            Ok(value) => ResultBlock {
+                name,
+                value: Some(value),
+                error: None,
+                raw: None,
+            },
+            Err(e) => ResultBlock {
+                name,
+                value: None,
+                error: Some(format!("JSONとして解析できません: {}", e)),
+                raw: Some(raw.trim().to_string()),
+            },
+        }
+    }
+}
+
+fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))
+}
+
+// "---JSON_START---" / "---JSON_START:name---" を探し、(開始位置, 名前, マーカー直後の位置) を返す
+fn find_marker(text: &str, marker: &str) -> Option<(usize, Option<String>, usize)> {
+    let mut search_from = 0;
+    while let Some(pos) = text[search_from..].find(marker) {
+        let start = search_from + pos;
+        let after = &text[start + marker.len()..];
+        if let Some(rest) = after.strip_prefix(MARKER_TAIL) {
+            let end = text.len() - rest.len();
+            return Some((start, None, end));
+        }
+        if let Some(rest) = after.strip_prefix(':') {
+            if let Some(close) = rest.find(MARKER_TAIL) {
+                let name = rest[..close].to_string();
+                let end = text.len() - rest.len() + close + MARKER_TAIL.len();
+                return Some((start, Some(name), end));
+            }
+        }
+        search_from = start + marker.len();
+    }
+    None
+}
+
+// 標準出力を1行ずつ受け取り、ブロックが閉じたら返す
+#[derive(Default)]
+pub struct ResultCollector {
+    // 読み取り中のブロック (名前, 本文)
+    current: Option<(String, String)>,
+}
+
+impl ResultCollector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn feed(&mut self, line: &str) -> Vec<ResultBlock> {
+        let mut done = Vec::new();
+        let mut rest = line;
+
+        loop {
+            match self.current.as_mut() {
+                Some((name, body)) => {
+                    if let Some((pos, end_name, end)) = find_marker(rest, END_MARKER) {
+                        body.push_str(&rest[..pos]);
+                        let mut block = ResultBlock::from_raw(name.clone(), body);
+                        if let Some(end_name) = end_name {
+                            if end_name != *name && block.error.is_none() {
+                                block.error = Some(format!(
+                                    "終了マーカーの名前が一致しません: {} / {}",
+                                    name, end_name
+                                ));
+                            }
+                        }
+                        done.push(block);
+                        self.current = None;
+                        rest = &rest[end..];
+                    } else {
+                        body.push_str(rest);
+                        body.push('\n');
+                        break;
+                    }
+                }
+                None => {
+                    if let Some((_, name, end)) = find_marker(rest, START_MARKER) {
+                        let name = name.unwrap_or_else(|| DEFAULT_BLOCK_NAME.to_string());
+                        self.current = Some((name, String::new()));
+                        rest = &rest[end..];
+                    } else {
+                        break;
+                    }
+                }
+            }
+        }
+        done
+    }
+
+    // 出力の終わりに達しても閉じていないブロックをエラーとして返す
+    pub fn finish(&mut self) -> Option<ResultBlock> {
+        let (name, body) = self.current.take()?;
+        Some(ResultBlock {
+            name,
+            value: None,
+            error: Some("---JSON_END--- が見つかりません".to_string()),
+            raw: Some(body.trim().to_string()),
+        })
+    }
+}
+
+// ログ全体から結果ブロックをすべて取り出す
+pub fn extract_blocks(log: &str) -> Vec<ResultBlock> {
+    let mut collector = ResultCollector::new();
+    let mut blocks: Vec<ResultBlock> = log.lines().flat_map(|l| collector.feed(l)).collect();
+    blocks.extend(collector.finish());
+    blocks
+}
+
+// 名前なしブロックの値 (従来の ---JSON_START--- 形式との互換用)
+pub fn default_payload(blocks: &[ResultBlock]) -> Option<Value> {
+    blocks
+        .iter()
+        .rev()
+        .find(|b| b.name == DEFAULT_BLOCK_NAME)
+        .and_then(|b| b.value.clone())
+}
+
+// 結果ブロックを取り除いたログ本文を返す
+pub fn strip_blocks(log: &str) -> String {
+    let mut out = String::new();
+    let mut in_block = false;
+    for line in log.lines() {
+        let mut rest = line;
+        let mut kept = String::new();
+        loop {
+            if in_block {
+                match find_marker(rest, END_MARKER) {
+                    Some((_, _, end)) => {
+                        in_block = false;
+                        rest = &rest[end..];
+                    }
+                    None => break,
+                }
+            } else {
+                match find_marker(rest, START_MARKER) {
+                    Some((pos, _, end)) => {
+                        kept.push_str(&rest[..pos]);
+                        in_block = true;
+                        rest = &rest[end..];
+                    }
+                    None => {
+                        kept.push_str(rest);
+                        break;
+                    }
+                }
+            }
+        }
+        if !kept.trim().is_empty() || (!in_block && kept == line) {
+            out.push_str(&kept);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn extracts_named_and_default_blocks() {
+        let log = "\
+Optimal objective 1.0e+01
+---JSON_START---
+{\"obj\": 10,
+ \"x\": [1, 0]}
+---JSON_END---
+---JSON_START:solution---
+{\"x[0]\": 1}
+---JSON_END:solution---
+done ---JSON_START:inline---[1, 2]---JSON_END--- tail
+";
+        let blocks = extract_blocks(log);
+        assert_eq!(blocks.len(), 3);
+        assert_eq!(blocks[0].name, DEFAULT_BLOCK_NAME);
+        assert_eq!(blocks[0].value, Some(json!({"obj": 10, "x": [1, 0]})));
+        assert_eq!(blocks[1].name, "solution");
+        assert_eq!(blocks[2].value, Some(json!([1, 2])));
+        assert_eq!(
+            default_payload(&blocks),
+            Some(json!({"obj": 10, "x": [1, 0]}))
+        );
+        assert_eq!(
+            strip_blocks(log).trim(),
+            "Optimal objective 1.0e+01\ndone  tail"
+        );
+    }
+
+    #[test]
+    fn reports_unterminated_and_invalid_blocks() {
+        let log = "\
+---JSON_START:bad---
+{not json}
+---JSON_END:other---
+---JSON_START:partial---
+{\"x\": [1,
+Traceback (most recent call last):
+";
+        let blocks = extract_blocks(log);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .starts_with("JSONとして解析できません"));
+        assert_eq!(blocks[0].raw.as_deref(), Some("{not json}"));
+        assert_eq!(blocks[1].name, "partial");
+        assert_eq!(blocks[1].value, None);
+        assert_eq!(
+            blocks[1].error.as_deref(),
+            Some("---JSON_END--- が見つかりません")
+        );
+        assert_eq!(
+            blocks[1].raw.as_deref(),
+            Some("{\"x\": [1,\nTraceback (most recent call last):")
+        );
+        assert_eq!(default_payload(&blocks), None);
+    }
+
+    #[test]
+    fn rejects_invalid_names_and_mismatched_end() {
+        let blocks = extract_blocks(
+            "---JSON_START:a b---\n1\n---JSON_END---\n---JSON_START:a---\n2\n---JSON_END:b---\n",
+        );
+        assert!(blocks[0]
+            .error
+            .as_deref()
+            .unwrap()
+            .contains("ブロック名が不正"));
+        assert_eq!(blocks[1].value, Some(json!(2)));
+        assert!(blocks[1].error.as_deref().unwrap().contains("一致しません"));
+    }
+}
@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+use crate::argv;
+use crate::gurobi_cl;
+use crate::gurobi_params::{self, EnvFileGuard, ParamCheck};
+use crate::history::{self, NewRun};
+use crate::iis::{self, IisFile};
+use crate::log_parser::{LogParser, ProgressEvent};
+use crate::process;
+use crate::result_json::{self, ResultBlock, ResultCollector};
+use crate::solution::{self, SolutionFile};
+use crate::summary::{self, SolveSummary};
+
+// スクリプト実行の共通部分 (GUI のコマンドとヘッドレスモードの両方から使う)
+
+// 何を実行するか
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunMode {
+    // command_prefix + Python スクリプト
+    #[default]
+    Script,
+    // script_path のモデルファイルを gurobi_cl で解く (command_prefix は gurobi_cl のパス、空なら PATH 上のもの)
+    GurobiCl,
+}
+
+impl RunMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RunMode::Script => "script",
+            RunMode::GurobiCl => "gurobiCl",
+        }
+    }
+
+    pub fn parse(s: &str) -> Self {
+        match s {
+            "gurobiCl" => RunMode::GurobiCl,
+            _ => RunMode::Script,
+        }
+    }
+}
+
+// 1回の実行内容
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunRequest {
+    pub script_path: String,
+    // シェル風に分割される (クォート・エスケープ可)
+    #[serde(default)]
+    pub args_str: String,
+    // 分割済みの引数。指定された場合は args_str より優先する
+    #[serde(default)]
+    pub args: Option<Vec<String>>,
+    pub command_prefix: String,
+    // 作業ディレクトリ (省略時はスクリプトのあるフォルダ、相対パスもそこからの位置)
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    // 追加・上書きする環境変数 (GRB_LICENSE_FILE, PYTHONPATH など)
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    // 適用する環境プロファイル名 (GUI から実行する場合のみ)
+    #[serde(default)]
+    pub env_profile: Option<String>,
+    // 作業ディレクトリの gurobi.env 経由で渡す Gurobi パラメータ (名前 -> 値)
+    #[serde(default)]
+    pub gurobi_params: BTreeMap<String, Value>,
+    #[serde(default)]
+    pub mode: RunMode,
+    // gurobi_cl の ResultFile (省略時は "<モデル名>.sol"、相対パスは作業ディレクトリから)
+    #[serde(default)]
+    pub result_file: Option<String>,
+}
+
+impl RunRequest {
+    // スクリプトに渡す引数
+    pub fn script_args(&self) -> Result<Vec<String>, String> {
+        match &self.args {
+            Some(args) => Ok(args.clone()),
+            None => argv::split(&self.args_str).map_err(|e| format!("Args: {}", e)),
+        }
+    }
+
+    // 実際に使う作業ディレクトリ
+    pub fn resolved_working_dir(&self) -> Option<PathBuf> {
+        let script_dir = Path::new(&self.script_path)
+            .parent()
+            .filter(|p| !p.as_os_str().is_empty());
+        match self.working_dir.as_deref().map(str::trim) {
+            Some(dir) if !dir.is_empty() => Some(match script_dir {
+                Some(base) => base.join(dir),
+                None => PathBuf::from(dir),
+            }),
+            _ => script_dir.map(Path::to_path_buf),
+        }
+    }
+
+    // 検証済みのパラメータ (gurobi_cl では ResultFile も含める)
+    pub fn validated_params(&self) -> Result<BTreeMap<String, String>, String> {
+        let mut params = gurobi_params::validate(&self.gurobi_params)
+            .map_err(|issues| gurobi_params::issues_to_string(&issues))?;
+        if self.mode == RunMode::GurobiCl && !params.contains_key("ResultFile") {
+            let file = self
+                .result_file
+                .clone()
+                .filter(|f| !f.trim().is_empty())
+                .unwrap_or_else(|| gurobi_cl::default_result_file(&self.script_path));
+            params.insert("ResultFile".to_string(), file);
+        }
+        Ok(params)
+    }
+
+    // 解ファイルの出力先 (ResultFile を指定した場合のみ)
+    pub fn resolved_result_file(&self) -> Option<PathBuf> {
+        let params = self.validated_params().ok()?;
+        let file = Path::new(params.get("ResultFile")?);
+        Some(match self.resolved_working_dir() {
+            Some(dir) => dir.join(file),
+            None => file.to_path_buf(),
+        })
+    }
+
+    // 履歴に残す引数文字列 (args_str として渡し直せば同じ引数になる)
+    pub fn display_args(&self) -> String {
+        match &self.args {
+            Some(args) => argv::join(args),
+            None => self.args_str.clone(),
+        }
+    }
+}
+
+// 実行中の出力の受け取り先 (GUI ならウィンドウへのイベント送信、CLI なら標準出力)
+pub trait RunSink: Send + Sync + 'static {
+    fn on_stdout(&self, line: &str);
+    fn on_stderr(&self, line: &str);
+    fn on_progress(&self, _progress: &ProgressEvent) {}
+    fn on_result(&self, _block: &ResultBlock) {}
+}
+
+// 終了した実行の結果
+#[derive(Debug, Clone)]
+pub struct RunOutcome {
+    pub exit_code: Option<i32>,
+    pub success: bool,
+    pub started_at: i64,
+    pub finished_at: i64,
+    pub stdout: String,
+    pub stderr: String,
+    pub summary: SolveSummary,
+    pub results: Vec<ResultBlock>,
+    // 指定した Gurobi パラメータと、ログ上で実際に適用されたかの照合結果
+    pub parameters: BTreeMap<String, String>,
+    pub parameter_checks: Vec<ParamCheck>,
+    // ResultFile に書き出された解ファイル (.sol / .json のみ)
+    pub solution_file: Option<SolutionFile>,
+    // 実行不可能だった場合に作業ディレクトリへ書き出された IIS ファイル
+    pub iis_file: Option<IisFile>,
+}
+
+impl RunOutcome {
+    // 履歴に保存する形へ変換する
+    pub fn to_new_run(&self, request: &RunRequest) -> NewRun {
+        NewRun {
+            script_path: request.script_path.clone(),
+            args: request.display_args(),
+            command_prefix: request.command_prefix.clone(),
+            exit_code: self.exit_code,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+            raw_log: self.stdout.clone(),
+            stderr_log: self.stderr.clone(),
+            json_payload: result_json::default_payload(&self.results),
+            results: self.results.clone(),
+            summary: Some(self.summary.clone()),
+            tags: Vec::new(),
+            working_dir: request
+                .resolved_working_dir()
+                .map(|d| d.to_string_lossy().to_string()),
+            env: request.env.clone(),
+            parameters: self.parameters.clone(),
+            mode: request.mode,
+            result_file: request
+                .resolved_result_file()
+                .map(|f| f.to_string_lossy().to_string()),
+            solution_files: self.solution_file.iter().cloned().collect(),
+            iis_file: self.iis_file.clone(),
+        }
+    }
+}
+
+// 起動済みプロセスの出力読み取りスレッド
+pub struct RunningProcess {
+    started_at: i64,
+    stdout_handle: JoinHandle<String>,
+    stderr_handle: JoinHandle<String>,
+    parameters: BTreeMap<String, String>,
+    // 実行が終わるまで gurobi.env を書き換えたままにする
+    env_file: Option<EnvFileGuard>,
+    result_file: Option<PathBuf>,
+    working_dir: Option<PathBuf>,
+}
+
+impl RunningProcess {
+    // プロセス終了後に出力を回収し、サマリと結果JSONをまとめる
+    pub fn finish(self, status: ExitStatus) -> RunOutcome {
+        let stdout = self.stdout_handle.join().unwrap_or_default();
+        let stderr = self.stderr_handle.join().unwrap_or_default();
+        drop(self.env_file);
+        let applied = summary::parse_parameter_changes(&stdout);
+        let summary = summary::parse_solve_summary(&stdout);
+        let iis_file = match &self.working_dir {
+            Some(dir) if iis::is_infeasible(&summary) => {
+                iis::find_written_between(dir, self.started_at, None)
+            }
+            _ => None,
+        };
+        RunOutcome {
+            exit_code: status.code(),
+            success: status.success(),
+            started_at: self.started_at,
+            finished_at: history::now_millis(),
+            summary,
+            iis_file,
+            results: result_json::extract_blocks(&stdout),
+            parameter_checks: gurobi_params::check_applied(&self.parameters, &applied),
+            parameters: self.parameters,
+            solution_file: self
+                .result_file
+                .filter(|f| is_solution_path(f))
+                .and_then(|f| solution::read_if_written_since(&f, self.started_at)),
+            stdout,
+            stderr,
+        }
+    }
+}
+
+// ResultFile には .mst や .bas なども指定できるので、解ファイルの形式だけを添付する
+fn is_solution_path(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|e| e.eq_ignore_ascii_case("sol") || e.eq_ignore_ascii_case("json"))
+}
+
+// 1行ずつ読み出す (UTF-8 でない出力も読み飛ばさず、置換文字にして渡す)
+fn for_each_line(stream: impl Read, mut f: impl FnMut(&str)) {
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+        let line = String::from_utf8_lossy(&buf);
+        f(line.trim_end_matches(['\n', '\r']));
+    }
+}
+
+// 実際に実行される argv (先頭がプログラム名) を組み立てる
+pub fn build_argv(request: &RunRequest) -> Result<Vec<String>, String> {
+    // 1. プレフィックスをシェル風に分割 (空白を含むパスはクォートする)
+    let mut argv =
+        argv::split(&request.command_prefix).map_err(|e| format!("Command prefix: {}", e))?;
+
+    if request.mode == RunMode::GurobiCl {
+        if argv.is_empty() {
+            argv.push(gurobi_cl::DEFAULT_PROGRAM.to_string());
+        }
+        let model = Path::new(&request.script_path);
+        let model = std::path::absolute(model).unwrap_or_else(|_| model.to_path_buf());
+        argv.extend(gurobi_cl::build_args(
+            &request.validated_params()?,
+            request.script_args()?,
+            model.to_string_lossy().to_string(),
+        )?);
+        return Ok(argv);
+    }
+
+    // 2. 最初の単語がプログラム名 (例: "uv" や "python")
+    if argv.is_empty() {
+        return Err("Command prefix is empty".to_string());
+    }
+
+    // 3. スクリプトパスはそのまま1つの引数として追加
+    //    (作業ディレクトリを変えても同じファイルを指すよう、絶対パスにする)
+    let script = Path::new(&request.script_path);
+    let script = std::path::absolute(script).unwrap_or_else(|_| script.to_path_buf());
+    argv.push(script.to_string_lossy().to_string());
+
+    // 4. ユーザー引数を追加
+    argv.extend(request.script_args()?);
+
+    Ok(argv)
+}
+
+// プレフィックス・スクリプト・引数から実行するコマンドを組み立てる
+pub fn build_command(request: &RunRequest) -> Result<Command, String> {
+    let argv = build_argv(request)?;
+
+    // ★重要: stdinをnullにする
+    let mut cmd = Command::new(&argv[0]);
+    cmd.args(&argv[1..])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .stdin(Stdio::null()); // ★追加: 入力待ちフリーズを防止
+
+    if let Some(dir) = request.resolved_working_dir() {
+        if !dir.is_dir() {
+            return Err(format!(
+                "作業ディレクトリが見つかりません: {}",
+                dir.display()
+            ));
+        }
+        cmd.current_dir(dir);
+    }
+
+    for key in request.env.keys() {
+        if key.is_empty() || key.contains(['=', '\0']) {
+            return Err(format!("環境変数名が不正です: \"{}\"", key));
+        }
+    }
+    cmd.envs(&request.env);
+
+    // 停止時に孫プロセスまでまとめてシグナルを送れるようにする
+    process::configure_process_group(&mut cmd);
+
+    Ok(cmd)
+}
+
+// プロセスを起動し、標準出力・標準エラーを sink に流すスレッドを立ち上げる
+pub fn start(
+    request: &RunRequest,
+    sink: Arc<dyn RunSink>,
+) -> Result<(Child, RunningProcess), String> {
+    let mut cmd = build_command(request)?;
+
+    // gurobi_cl にはコマンドライン引数で渡すので gurobi.env は使わない
+    let parameters = request.validated_params()?;
+    let env_file = match request.mode {
+        RunMode::Script => {
+            let env_dir = match request.resolved_working_dir() {
+                Some(dir) => dir,
+                None => std::env::current_dir().map_err(|e| e.to_string())?,
+            };
+            EnvFileGuard::write(&env_dir, &parameters)?
+        }
+        RunMode::GurobiCl => None,
+    };
+
+    let started_at = history::now_millis();
+    let mut child = cmd.spawn().map_err(|e| {
+        format!(
+            "コマンド起動エラー: {}\n(設定のCommand Prefixを確認してください)",
+            e
+        )
+    })?;
+
+    let stdout = child.stdout.take().ok_or("stdout取得失敗")?;
+    let stderr = child.stderr.take().ok_or("stderr取得失敗")?;
+
+    let stdout_sink = sink.clone();
+    let stdout_handle = thread::spawn(move || {
+        let mut full_log = String::new();
+        let mut parser = LogParser::new();
+        let mut collector = ResultCollector::new();
+        for_each_line(stdout, |l| {
+            stdout_sink.on_stdout(l);
+            // 表の行であれば型付きの進捗イベントも送る
+            if let Some(progress) = parser.feed(l) {
+                stdout_sink.on_progress(&progress);
+            }
+            // 結果JSONブロックが閉じたら送る
+            for block in collector.feed(l) {
+                stdout_sink.on_result(&block);
+            }
+            full_log.push_str(l);
+            full_log.push('\n');
+        });
+        // 閉じる前に出力が終わったブロックも (エラー付きで) 送る
+        if let Some(block) = collector.finish() {
+            stdout_sink.on_result(&block);
+        }
+        full_log
+    });
+
+    let stderr_handle = thread::spawn(move || {
+        let mut full_err = String::new();
+        for_each_line(stderr, |l| {
+            sink.on_stderr(l);
+            full_err.push_str(l);
+            full_err.push('\n');
+        });
+        full_err
+    });
+
+    Ok((
+        child,
+        RunningProcess {
+            started_at,
+            stdout_handle,
+            stderr_handle,
+            parameters,
+            env_file,
+            result_file: request.resolved_result_file(),
+            working_dir: request.resolved_working_dir(),
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct Collect {
+        results: Mutex<Vec<ResultBlock>>,
+    }
+
+    impl RunSink for Collect {
+        fn on_stdout(&self, _line: &str) {}
+        fn on_stderr(&self, _line: &str) {}
+        fn on_result(&self, block: &ResultBlock) {
+            self.results.lock().unwrap().push(block.clone());
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn unterminated_block_is_sent_when_the_script_crashes() {
+        let dir = std::env::temp_dir().join(format!("gurobilab-runner-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("crash.sh");
+        std::fs::write(
+            &script,
+            "echo '---JSON_START:solution---'\necho '{\"x\": [1,'\nexit 3\n",
+        )
+        .unwrap();
+        let request = RunRequest {
+            script_path: script.to_string_lossy().to_string(),
+            command_prefix: "sh".to_string(),
+            ..Default::default()
+        };
+        let sink = Arc::new(Collect::default());
+        let (mut child, running) = start(&request, sink.clone()).unwrap();
+        let outcome = running.finish(child.wait().unwrap());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(outcome.exit_code, Some(3));
+        let results = sink.results.lock().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].name, "solution");
+        assert!(results[0].value.is_none());
+        assert!(results[0].error.is_some());
+        assert_eq!(results[0].raw.as_deref(), Some("{\"x\": [1,"));
+        assert_eq!(outcome.results, results.clone());
+    }
+}
@@ -0,0 +1,319 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use crate::argv;
+use crate::compression::{CompressionConfig, CompressionStrategy};
+use crate::llm::ProviderKind;
+
+// アプリの設定 (モデル・コマンドプレフィックス・システム指示など)
+// アプリの設定ディレクトリに TOML で保存し、チームで共有できるようファイルへの書き出し・読み込みもできる
+// APIキーは secrets に保存するので、ここには含めない
+
+pub const DEFAULT_MODEL: &str = "gemini-2.5-flash";
+
+// システム指示が空のときも、解析のプロンプトはこれを使う
+pub const DEFAULT_SYSTEM_PROMPT: &str = "あなたはデータサイエンティストです。以下の最適化計算ログを解析し、Markdown形式のレポートを作成してください。\n# 制約\n- 挨拶や前置きは不可。即座に見出し(#)から開始すること。\n- ログの引用は不可。";
+
+// 古い形式から順に適用する (i 番目は version i から i+1 への変換)
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v0];
+
+pub const SETTINGS_VERSION: u32 = MIGRATIONS.len() as u32;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct Settings {
+    pub version: u32,
+    pub provider: ProviderKind,
+    pub model: String,
+    // 空なら各プロバイダの既定URL
+    pub base_url: Option<String>,
+    pub command_prefix: String,
+    pub system_prompt: String,
+    // 省略時はモデルごとの既定値
+    pub token_budget: Option<usize>,
+    pub compression: CompressionConfig,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            version: SETTINGS_VERSION,
+            provider: ProviderKind::default(),
+            model: DEFAULT_MODEL.to_string(),
+            base_url: None,
+            command_prefix: "uv run python -u".to_string(),
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            token_budget: None,
+            compression: CompressionConfig::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.provider != ProviderKind::Offline && self.model.trim().is_empty() {
+            return Err("モデル名を入力してください。".to_string());
+        }
+        if let Some(url) = self.base_url.as_deref().filter(|u| !u.trim().is_empty()) {
+            let url = url.trim();
+            if !url.starts_with("http://") && !url.starts_with("https://") {
+                return Err(format!(
+                    "Base URL は http:// または https:// で始めてください: {}",
+                    url
+                ));
+            }
+        }
+        if argv::split(&self.command_prefix)
+            .map_err(|e| format!("コマンドプレフィックスを解釈できません: {}", e))?
+            .is_empty()
+        {
+            return Err("コマンドプレフィックスを入力してください。".to_string());
+        }
+        if self.token_budget == Some(0) {
+            return Err("トークン数の上限は 1 以上にしてください。".to_string());
+        }
+        self.compression.validate()
+    }
+
+    // 空欄は未設定として扱う
+    fn normalize(mut self) -> Self {
+        self.version = SETTINGS_VERSION;
+        self.model = self.model.trim().to_string();
+        self.base_url = self
+            .base_url
+            .map(|u| u.trim().trim_end_matches('/').to_string())
+            .filter(|u| !u.is_empty());
+        self.command_prefix = self.command_prefix.trim().to_string();
+        self
+    }
+}
+
+fn rename(map: &mut Map<String, Value>, from: &str, to: &str) {
+    if let Some(v) = map.remove(from) {
+        map.insert(to.to_string(), v);
+    }
+}
+
+// v0: 旧バージョンが localStorage に保存していた値をそのまま並べたもの
+fn migrate_v0(map: &mut Map<String, Value>) {
+    rename(map, "command", "commandPrefix");
+    rename(map, "prompt", "systemPrompt");
+    // 空欄は既定値に任せる
+    map.retain(|_, v| v.as_str().is_none_or(|s| !s.trim().is_empty()));
+    // 間引き方式は名前だけだったので、既定のパラメータで補う
+    if let Some(Value::String(kind)) = map.get("compression") {
+        let name = match kind.as_str() {
+            "incumbentChanges" => "incumbent",
+            "timeBuckets" => "time",
+            "gapImprovement" => "gap",
+            "headTail" => "head-tail",
+            _ => "fixed",
+        };
+        let strategy = CompressionStrategy::from_name(name).unwrap_or_default();
+        map.insert("compression".to_string(), json!({ "strategy": strategy }));
+    }
+}
+
+// 古い版の設定を現在の版に揃えてから読み込む
+fn from_value(value: Value) -> Result<Settings, String> {
+    let Value::Object(mut map) = value else {
+        return Err("設定ファイルの形式が正しくありません。".to_string());
+    };
+    let version = map.get("version").and_then(Value::as_u64).unwrap_or(0) as usize;
+    if version > MIGRATIONS.len() {
+        return Err(format!(
+            "新しいバージョンのアプリで作成された設定です (version {})。アプリを更新してください。",
+            version
+        ));
+    }
+    for migrate in &MIGRATIONS[version..] {
+        migrate(&mut map);
+    }
+    map.insert("version".to_string(), json!(SETTINGS_VERSION));
+    let settings: Settings = serde_json::from_value(Value::Object(map))
+        .map_err(|e| format!("設定を読み込めません: {}", e))?;
+    let settings = settings.normalize();
+    settings.validate()?;
+    Ok(settings)
+}
+
+// 拡張子が .json なら JSON、それ以外は TOML として読む
+pub fn parse(text: &str, path: &Path) -> Result<Settings, String> {
+    let is_json = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    let value = if is_json {
+        serde_json::from_str(text).map_err(|e| format!("JSON を解釈できません: {}", e))?
+    } else {
+        let table: toml::Table =
+            toml::from_str(text).map_err(|e| format!("TOML を解釈できません: {}", e))?;
+        serde_json::to_value(table).map_err(|e| e.to_string())?
+    };
+    from_value(value)
+}
+
+pub fn to_text(settings: &Settings, path: &Path) -> Result<String, String> {
+    if path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    {
+        serde_json::to_string_pretty(settings).map_err(|e| e.to_string())
+    } else {
+        toml::to_string_pretty(settings).map_err(|e| e.to_string())
+    }
+}
+
+// settings.toml -> settings.toml.bak
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".bak");
+    path.with_file_name(name)
+}
+
+pub struct SettingsStore {
+    path: PathBuf,
+    settings: Settings,
+    // まだファイルに保存したことが無い (旧バージョンからの移行の判定用)
+    fresh: bool,
+}
+
+impl SettingsStore {
+    // 読めない設定ファイル (手で書き換えて壊れたもの・新しい版のもの) では起動を止めず、
+    // ファイルを .bak に退避して既定値から始める
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let (settings, fresh) = match std::fs::read_to_string(path) {
+            Ok(text) => match parse(&text, path) {
+                Ok(settings) => (settings, false),
+                Err(e) => {
+                    let backup = backup_path(path);
+                    eprintln!(
+                        "設定ファイル {} を読み込めません: {}\n{} に退避し、既定の設定で起動します。",
+                        path.display(),
+                        e,
+                        backup.display()
+                    );
+                    if let Err(e) = std::fs::rename(path, &backup) {
+                        eprintln!("設定ファイルを退避できません: {}", e);
+                    }
+                    (Settings::default(), false)
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => (Settings::default(), true),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(SettingsStore {
+            path: path.to_path_buf(),
+            settings,
+            fresh,
+        })
+    }
+
+    pub fn get(&self) -> Settings {
+        self.settings.clone()
+    }
+
+    pub fn update(&mut self, settings: Settings) -> Result<Settings, String> {
+        let settings = settings.normalize();
+        settings.validate()?;
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&self.path, to_text(&settings, &self.path)?).map_err(|e| e.to_string())?;
+        self.settings = settings;
+        self.fresh = false;
+        Ok(self.get())
+    }
+
+    pub fn import_file(&mut self, path: &Path) -> Result<Settings, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("{} を読み込めません: {}", path.display(), e))?;
+        let settings = parse(&text, path)?;
+        self.update(settings)
+    }
+
+    pub fn export_file(&self, path: &Path) -> Result<(), String> {
+        std::fs::write(path, to_text(&self.settings, path)?)
+            .map_err(|e| format!("{} に書き込めません: {}", path.display(), e))
+    }
+
+    // 旧バージョンの localStorage の値 (v0) を取り込む (設定ファイルが無い場合のみ)
+    pub fn import_legacy(&mut self, values: BTreeMap<String, String>) -> Result<Settings, String> {
+        if !self.fresh || values.is_empty() {
+            return Ok(self.get());
+        }
+        let map: Map<String, Value> = values
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        let settings = from_value(Value::Object(map))?;
+        self.update(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "gurobilab-settings-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn open_moves_unreadable_file_aside() {
+        for (name, text) in [
+            ("syntax", "model = \"gemini\"\ncommandPrefix = "),
+            ("invalid", "version = 1\ncommandPrefix = \"\"\n"),
+            ("newer", "version = 99\nmodel = \"x\"\n"),
+        ] {
+            let dir = temp_dir(name);
+            let path = dir.join("settings.toml");
+            std::fs::write(&path, text).unwrap();
+            let store = SettingsStore::open(&path).unwrap();
+            assert_eq!(store.get(), Settings::default());
+            assert!(!path.exists());
+            assert_eq!(
+                std::fs::read_to_string(dir.join("settings.toml.bak")).unwrap(),
+                text
+            );
+            std::fs::remove_dir_all(&dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn update_and_import_reject_invalid_settings() {
+        let dir = temp_dir("reject");
+        let path = dir.join("settings.toml");
+        let mut store = SettingsStore::open(&path).unwrap();
+        let invalid = Settings {
+            command_prefix: String::new(),
+            ..Settings::default()
+        };
+        assert!(store.update(invalid).is_err());
+        let import = dir.join("shared.toml");
+        std::fs::write(&import, "baseUrl = \"ftp://example.com\"\n").unwrap();
+        assert!(store.import_file(&import).is_err());
+        assert!(!path.exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn migrates_legacy_values() {
+        let mut map = Map::new();
+        map.insert("command".to_string(), json!("python -u"));
+        map.insert("prompt".to_string(), json!(""));
+        map.insert("compression".to_string(), json!("headTail"));
+        let settings = from_value(Value::Object(map)).unwrap();
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.command_prefix, "python -u");
+        assert_eq!(settings.system_prompt, DEFAULT_SYSTEM_PROMPT);
+    }
+}
@@ -0,0 +1,383 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+// Gurobi が書き出す解ファイル (.sol / JSON) を読み、変数の値を一覧・集計する
+//
+// .sol:
+//   # Objective value = 1.5e+01
+//   x[1] 1
+//   y 0
+// JSON (ResultFile=model.json):
+//   {"SolutionInfo": {"ObjVal": 15, ...}, "Vars": [{"VarName": "x[1]", "X": 1}, ...]}
+
+// 履歴に保存できる解ファイルの大きさの上限
+pub const MAX_SOLUTION_BYTES: u64 = 64 * 1024 * 1024;
+// 1回に返す変数の数
+const DEFAULT_LIMIT: usize = 500;
+const MAX_LIMIT: usize = 10_000;
+// 0 / 1 / 整数とみなす許容誤差 (Gurobi の IntFeasTol の既定値)
+const DEFAULT_TOLERANCE: f64 = 1e-5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SolutionFormat {
+    Sol,
+    Json,
+}
+
+impl SolutionFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SolutionFormat::Sol => "sol",
+            SolutionFormat::Json => "json",
+        }
+    }
+
+    // 拡張子で判定し、分からなければ中身の先頭で判定する
+    pub fn detect(path: &str, content: &str) -> Self {
+        let ext = Path::new(path)
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match ext.as_deref() {
+            Some("json") => SolutionFormat::Json,
+            Some("sol") => SolutionFormat::Sol,
+            _ if content.trim_start().starts_with('{') => SolutionFormat::Json,
+            _ => SolutionFormat::Sol,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionVar {
+    pub name: String,
+    pub value: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Solution {
+    pub format: SolutionFormat,
+    pub objective: Option<f64>,
+    // JSON の SolutionInfo (Status, Runtime, MIPGap など) をそのまま
+    pub info: Option<Value>,
+    pub vars: Vec<SolutionVar>,
+}
+
+// 履歴に添付する解ファイル
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionFile {
+    pub path: String,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SolutionQuery {
+    // 変数名のパターン (* と ? が使えるワイルドカード、regex が true なら正規表現)
+    pub pattern: Option<String>,
+    pub regex: bool,
+    // 0 でない値の変数だけ
+    pub nonzero_only: bool,
+    pub tolerance: Option<f64>,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+// パターンに一致した変数の集計
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionStats {
+    pub count: usize,
+    pub nonzero: usize,
+    // 値が 1 の変数 (バイナリ変数で立っているもの)
+    pub ones: usize,
+    // 整数でない値の変数
+    pub fractional: usize,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub sum: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolutionView {
+    pub format: SolutionFormat,
+    pub objective: Option<f64>,
+    pub info: Option<Value>,
+    // ファイル内の変数の総数
+    pub total: usize,
+    pub stats: SolutionStats,
+    // すべての条件に一致した数 (items はそのうち offset から limit 件)
+    pub matched: usize,
+    pub items: Vec<SolutionVar>,
+}
+
+fn parse_sol(content: &str) -> Result<Solution, String> {
+    let mut objective = None;
+    let mut vars = Vec::new();
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some((key, value)) = comment.split_once('=') {
+                if key.trim().eq_ignore_ascii_case("objective value") {
+                    objective = value.trim().parse().ok();
+                }
+            }
+            continue;
+        }
+        // 変数名に空白は含まれないので、最後の空白で区切る
+        let parsed = line
+            .rsplit_once(char::is_whitespace)
+            .and_then(|(name, value)| Some((name.trim(), value.parse::<f64>().ok()?)));
+        match parsed {
+            Some((name, value)) if !name.is_empty() => vars.push(SolutionVar {
+                name: name.to_string(),
+                value,
+            }),
+            _ => {
+                return Err(format!(
+                    "解ファイルの {} 行目を読み取れません: {}",
+                    i + 1,
+                    line
+                ))
+            }
+        }
+    }
+    Ok(Solution {
+        format: SolutionFormat::Sol,
+        objective,
+        info: None,
+        vars,
+    })
+}
+
+// JSONSolDetail=0 (既定) では 0 の変数が省略される
+fn parse_json(content: &str) -> Result<Solution, String> {
+    let root: Value = serde_json::from_str(content)
+        .map_err(|e| format!("JSON の解ファイルを読み取れません: {}", e))?;
+    let info = root.get("SolutionInfo").cloned();
+    let objective = info
+        .as_ref()
+        .and_then(|i| i.get("ObjVal"))
+        .and_then(Value::as_f64);
+    let vars = root
+        .get("Vars")
+        .and_then(Value::as_array)
+        .ok_or("JSON の解ファイルに Vars がありません。")?
+        .iter()
+        .enumerate()
+        .filter_map(|(i, var)| {
+            let value = var.get("X").and_then(Value::as_f64)?;
+            let name = var
+                .get("VarName")
+                .and_then(Value::as_str)
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("#{}", i));
+            Some(SolutionVar { name, value })
+        })
+        .collect();
+    Ok(Solution {
+        format: SolutionFormat::Json,
+        objective,
+        info,
+        vars,
+    })
+}
+
+pub fn parse(path: &str, content: &str) -> Result<Solution, String> {
+    match SolutionFormat::detect(path, content) {
+        SolutionFormat::Sol => parse_sol(content),
+        SolutionFormat::Json => parse_json(content),
+    }
+}
+
+pub fn read_file(path: &str) -> Result<SolutionFile, String> {
+    let size = std::fs::metadata(path)
+        .map_err(|e| format!("解ファイルを開けません: {} ({})", path, e))?
+        .len();
+    if size > MAX_SOLUTION_BYTES {
+        return Err(format!(
+            "解ファイルが大きすぎます ({} MB まで): {}",
+            MAX_SOLUTION_BYTES / 1024 / 1024,
+            path
+        ));
+    }
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("解ファイルを読めません: {} ({})", path, e))?;
+    Ok(SolutionFile {
+        path: path.to_string(),
+        content,
+    })
+}
+
+// 実行の開始後に書き出された解ファイルだけを読む (前回の実行の古いファイルを添付しない)
+pub fn read_if_written_since(path: &Path, since_millis: i64) -> Option<SolutionFile> {
+    let modified = std::fs::metadata(path).ok()?.modified().ok()?;
+    let modified_millis = modified.duration_since(UNIX_EPOCH).ok()?.as_millis() as i64;
+    // ファイルシステムの時刻の粒度 (秒単位のものもある) を見込む
+    if modified_millis + 1000 < since_millis {
+        return None;
+    }
+    read_file(&path.to_string_lossy()).ok()
+}
+
+fn glob_to_regex(pattern: &str) -> String {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    re
+}
+
+fn is_near(value: f64, target: f64, tol: f64) -> bool {
+    (value - target).abs() <= tol
+}
+
+pub fn query(solution: &Solution, query: &SolutionQuery) -> Result<SolutionView, String> {
+    let tol = query.tolerance.unwrap_or(DEFAULT_TOLERANCE).abs();
+    let matcher = match query.pattern.as_deref().map(str::trim) {
+        Some(p) if !p.is_empty() => {
+            let source = if query.regex {
+                p.to_string()
+            } else {
+                glob_to_regex(p)
+            };
+            Some(Regex::new(&source).map_err(|e| format!("パターンが不正です: {}", e))?)
+        }
+        _ => None,
+    };
+
+    let mut stats = SolutionStats::default();
+    let mut matched = Vec::new();
+    for var in &solution.vars {
+        if matcher.as_ref().is_some_and(|re| !re.is_match(&var.name)) {
+            continue;
+        }
+        let v = var.value;
+        stats.count += 1;
+        stats.sum += v;
+        stats.min = Some(stats.min.map_or(v, |m| m.min(v)));
+        stats.max = Some(stats.max.map_or(v, |m| m.max(v)));
+        if is_near(v, 1.0, tol) {
+            stats.ones += 1;
+        }
+        if !is_near(v, v.round(), tol) {
+            stats.fractional += 1;
+        }
+        let nonzero = !is_near(v, 0.0, tol);
+        if nonzero {
+            stats.nonzero += 1;
+        }
+        if nonzero || !query.nonzero_only {
+            matched.push(var);
+        }
+    }
+
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    Ok(SolutionView {
+        format: solution.format,
+        objective: solution.objective,
+        info: solution.info.clone(),
+        total: solution.vars.len(),
+        stats,
+        matched: matched.len(),
+        items: matched
+            .into_iter()
+            .skip(query.offset)
+            .take(limit)
+            .cloned()
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOL: &str = "\
+# Solution for model assign
+# Objective value = 1.5000000000000000e+01
+assign[1,3] 1
+assign[2,3] 0
+assign[2,1] 1
+flow[a,b] 2.5
+slack -0
+";
+
+    #[test]
+    fn parses_sol_file() {
+        let sol = parse("model.sol", SOL).unwrap();
+        assert_eq!(sol.format, SolutionFormat::Sol);
+        assert_eq!(sol.objective, Some(15.0));
+        assert_eq!(sol.vars.len(), 5);
+        assert_eq!(sol.vars[3].name, "flow[a,b]");
+        assert_eq!(sol.vars[3].value, 2.5);
+        let err = parse("model.sol", "x 1\nbroken\n").unwrap_err();
+        assert!(err.contains("2 行目"), "{}", err);
+    }
+
+    #[test]
+    fn parses_json_file() {
+        let content = r#"{
+  "SolutionInfo": {"Status": 2, "ObjVal": 15, "MIPGap": 0},
+  "Vars": [{"VarName": "x[1]", "X": 1}, {"VarName": "y", "X": 0.25}, {"X": 3}]
+}"#;
+        let sol = parse("result", content).unwrap();
+        assert_eq!(sol.format, SolutionFormat::Json);
+        assert_eq!(sol.objective, Some(15.0));
+        assert_eq!(sol.vars[2].name, "#2");
+        assert!(parse("result.json", "{\"SolutionInfo\": {}}").is_err());
+    }
+
+    #[test]
+    fn queries_with_wildcards_and_stats() {
+        let sol = parse("model.sol", SOL).unwrap();
+        let view = query(
+            &sol,
+            &SolutionQuery {
+                pattern: Some("assign[?,3]".to_string()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(view.total, 5);
+        assert_eq!(view.matched, 2);
+        assert_eq!(view.stats.ones, 1);
+
+        let view = query(
+            &sol,
+            &SolutionQuery {
+                nonzero_only: true,
+                limit: Some(2),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(view.matched, 3);
+        assert_eq!(view.items.len(), 2);
+        assert_eq!(view.stats.fractional, 1);
+        assert_eq!(view.stats.sum, 4.5);
+        assert_eq!(view.stats.min, Some(0.0));
+        assert_eq!(view.stats.max, Some(2.5));
+
+        let bad = SolutionQuery {
+            pattern: Some("(".to_string()),
+            regex: true,
+            ..Default::default()
+        };
+        assert!(query(&sol, &bad).is_err());
+    }
+}
@@ -0,0 +1,340 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+// 実行終了後のログから、最終結果の要点 (状態・目的関数値・モデル規模など) を抜き出す
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SolveStatus {
+    Optimal,
+    Suboptimal,
+    Infeasible,
+    Unbounded,
+    InfeasibleOrUnbounded,
+    TimeLimit,
+    NodeLimit,
+    SolutionLimit,
+    IterationLimit,
+    WorkLimit,
+    MemoryLimit,
+    Interrupted,
+    Numeric,
+}
+
+// モデル規模 (presolve 前後それぞれ)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelSize {
+    pub rows: u64,
+    pub columns: u64,
+    pub nonzeros: u64,
+    pub continuous: Option<u64>,
+    pub integer: Option<u64>,
+    pub binary: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SolveSummary {
+    pub status: Option<SolveStatus>,
+    pub best_objective: Option<f64>,
+    pub best_bound: Option<f64>,
+    // 単位は %
+    pub gap: Option<f64>,
+    pub explored_nodes: Option<u64>,
+    pub simplex_iterations: Option<u64>,
+    pub barrier_iterations: Option<u64>,
+    // 秒
+    pub runtime: Option<f64>,
+    pub work_units: Option<f64>,
+    pub solution_count: Option<u64>,
+    pub original_model: Option<ModelSize>,
+    pub presolved_model: Option<ModelSize>,
+    pub presolve_removed_rows: Option<u64>,
+    pub presolve_removed_columns: Option<u64>,
+    pub presolve_time: Option<f64>,
+}
+
+struct Patterns {
+    model: Regex,
+    presolved: Regex,
+    var_types: Regex,
+    presolve_removed: Regex,
+    presolve_time: Regex,
+    explored: Regex,
+    solved_in: Regex,
+    barrier_solved: Regex,
+    solution_count: Regex,
+    best: Regex,
+    optimal_objective: Regex,
+}
+
+const NUM: &str = r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?";
+
+impl Patterns {
+    fn new() -> Self {
+        let re = |s: &str| Regex::new(&s.replace("{NUM}", NUM)).unwrap();
+        Patterns {
+            model: re(r"Optimize a model with (\d+) rows, (\d+) columns and (\d+) nonzeros"),
+            presolved: re(r"^Presolved: (\d+) rows, (\d+) columns, (\d+) nonzeros"),
+            var_types: re(
+                r"^Variable types: (\d+) continuous, (\d+) integer(?: \((\d+) binary\))?",
+            ),
+            presolve_removed: re(r"^Presolve removed (\d+) rows and (\d+) columns"),
+            presolve_time: re(r"^Presolve time: ({NUM})s"),
+            explored: re(
+                r"^Explored (\d+) nodes \((\d+) simplex iterations\) in ({NUM}) seconds(?: \(({NUM}) work units\))?",
+            ),
+            solved_in: re(
+                r"^Solved in (\d+) iterations and ({NUM}) seconds(?: \(({NUM}) work units\))?",
+            ),
+            barrier_solved: re(r"^Barrier solved model in (\d+) iterations"),
+            solution_count: re(r"^Solution count (\d+)"),
+            best: re(r"^Best objective ({NUM}|-), best bound ({NUM}|-), gap ({NUM}%|-)"),
+            optimal_objective: re(r"^Optimal objective\s+({NUM})"),
+        }
+    }
+}
+
+fn num<T: std::str::FromStr>(caps: &regex::Captures, i: usize) -> Option<T> {
+    caps.get(i).and_then(|m| m.as_str().parse::<T>().ok())
+}
+
+// 状態を表す行かどうか判定する
+fn detect_status(line: &str) -> Option<SolveStatus> {
+    let status =
+        if line.starts_with("Optimal solution found") || line.starts_with("Optimal objective") {
+            SolveStatus::Optimal
+        } else if line.starts_with("Sub-optimal termination") || line.starts_with("Suboptimal") {
+            SolveStatus::Suboptimal
+        } else if line.starts_with("Model is infeasible or unbounded")
+            || line.starts_with("Infeasible or unbounded model")
+        {
+            SolveStatus::InfeasibleOrUnbounded
+        } else if line.starts_with("Model is infeasible") || line.starts_with("Infeasible model") {
+            SolveStatus::Infeasible
+        } else if line.starts_with("Model is unbounded") || line.starts_with("Unbounded model") {
+            SolveStatus::Unbounded
+        } else if line.starts_with("Time limit reached") {
+            SolveStatus::TimeLimit
+        } else if line.starts_with("Node limit reached") {
+            SolveStatus::NodeLimit
+        } else if line.starts_with("Solution limit reached") {
+            SolveStatus::SolutionLimit
+        } else if line.starts_with("Iteration limit reached") {
+            SolveStatus::IterationLimit
+        } else if line.starts_with("Work limit reached") {
+            SolveStatus::WorkLimit
+        } else if line.starts_with("Memory limit reached") {
+            SolveStatus::MemoryLimit
+        } else if line.starts_with("Solve interrupted")
+            || line.starts_with("Interrupt request received")
+        {
+            SolveStatus::Interrupted
+        } else if line.starts_with("Numerical trouble encountered") {
+            SolveStatus::Numeric
+        } else {
+            return None;
+        };
+    Some(status)
+}
+
+// ログ全体から最後に行われた最適化のサマリを作る
+pub fn parse_solve_summary(log: &str) -> SolveSummary {
+    let p = Patterns::new();
+    let mut s = SolveSummary::default();
+    // "Presolved:" の後の Variable types は presolve 後のモデルのもの
+    let mut after_presolve = false;
+
+    for raw in log.lines() {
+        let line = raw.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(c) = p.model.captures(line) {
+            // 新しい最適化が始まったら集計し直す
+            s = SolveSummary::default();
+            after_presolve = false;
+            s.original_model = Some(ModelSize {
+                rows: num(&c, 1).unwrap_or(0),
+                columns: num(&c, 2).unwrap_or(0),
+                nonzeros: num(&c, 3).unwrap_or(0),
+                ..Default::default()
+            });
+        } else if let Some(c) = p.presolved.captures(line) {
+            after_presolve = true;
+            s.presolved_model = Some(ModelSize {
+                rows: num(&c, 1).unwrap_or(0),
+                columns: num(&c, 2).unwrap_or(0),
+                nonzeros: num(&c, 3).unwrap_or(0),
+                ..Default::default()
+            });
+        } else if let Some(c) = p.var_types.captures(line) {
+            let target = if after_presolve {
+                s.presolved_model.as_mut()
+            } else {
+                s.original_model.as_mut()
+            };
+            if let Some(size) = target {
+                size.continuous = num(&c, 1);
+                size.integer = num(&c, 2);
+                size.binary = num(&c, 3).or(Some(0));
+            }
+        } else if let Some(c) = p.presolve_removed.captures(line) {
+            s.presolve_removed_rows = num(&c, 1);
+            s.presolve_removed_columns = num(&c, 2);
+        } else if let Some(c) = p.presolve_time.captures(line) {
+            s.presolve_time = num(&c, 1);
+        } else if let Some(c) = p.explored.captures(line) {
+            s.explored_nodes = num(&c, 1);
+            s.simplex_iterations = num(&c, 2);
+            s.runtime = num(&c, 3);
+            s.work_units = num(&c, 4);
+        } else if let Some(c) = p.solved_in.captures(line) {
+            s.simplex_iterations = num(&c, 1);
+            s.runtime = num(&c, 2);
+            s.work_units = num(&c, 3);
+        } else if let Some(c) = p.barrier_solved.captures(line) {
+            s.barrier_iterations = num(&c, 1);
+        } else if let Some(c) = p.solution_count.captures(line) {
+            s.solution_count = num(&c, 1);
+        } else if let Some(c) = p.best.captures(line) {
+            s.best_objective = num(&c, 1);
+            s.best_bound = num(&c, 2);
+            s.gap = c
+                .get(3)
+                .and_then(|m| m.as_str().trim_end_matches('%').parse().ok());
+        } else if let Some(c) = p.optimal_objective.captures(line) {
+            // LP の場合は Best objective 行が無い
+            s.best_objective = num(&c, 1);
+        }
+
+        if let Some(status) = detect_status(line) {
+            s.status = Some(status);
+        }
+    }
+
+    s
+}
+
+// "Set parameter TimeLimit to value 60" の行から、既定値から変更されたパラメータを集める
+// (同じパラメータが複数回設定された場合は最後の値)
+pub fn parse_parameter_changes(log: &str) -> BTreeMap<String, String> {
+    let re = Regex::new(r"^Set parameter (\w+) to value (.+)$").unwrap();
+    log.lines()
+        .filter_map(|l| re.captures(l.trim()))
+        .map(|c| (c[1].to_string(), c[2].trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIP_LOG: &str = "\
+Set parameter TimeLimit to value 60
+Set parameter MIPGap to value 0.001
+Gurobi Optimizer version 11.0.0 build v11.0.0rc2 (linux64)
+Optimize a model with 1200 rows, 800 columns and 5600 nonzeros
+Model fingerprint: 0x1a2b3c4d
+Variable types: 200 continuous, 600 integer (600 binary)
+Presolve removed 300 rows and 100 columns
+Presolve time: 0.05s
+Presolved: 900 rows, 700 columns, 4100 nonzeros
+Variable types: 150 continuous, 550 integer (540 binary)
+
+Explored 20480 nodes (512000 simplex iterations) in 60.02 seconds (45.10 work units)
+Thread count was 8 (of 8 available processors)
+
+Solution count 4: 1290 1300 1450 1500
+
+Time limit reached
+Best objective 1.290000000000e+03, best bound 1.242500000000e+03, gap 3.6822%
+";
+
+    #[test]
+    fn parses_mip_summary() {
+        let s = parse_solve_summary(MIP_LOG);
+        assert_eq!(s.status, Some(SolveStatus::TimeLimit));
+        assert_eq!(s.best_objective, Some(1290.0));
+        assert_eq!(s.best_bound, Some(1242.5));
+        assert_eq!(s.gap, Some(3.6822));
+        assert_eq!(s.explored_nodes, Some(20480));
+        assert_eq!(s.simplex_iterations, Some(512000));
+        assert_eq!(s.runtime, Some(60.02));
+        assert_eq!(s.work_units, Some(45.1));
+        assert_eq!(s.solution_count, Some(4));
+        assert_eq!(s.presolve_removed_rows, Some(300));
+        assert_eq!(s.presolve_time, Some(0.05));
+        let original = s.original_model.unwrap();
+        assert_eq!(
+            (original.rows, original.columns, original.nonzeros),
+            (1200, 800, 5600)
+        );
+        assert_eq!(original.binary, Some(600));
+        let presolved = s.presolved_model.unwrap();
+        assert_eq!(presolved.rows, 900);
+        assert_eq!(presolved.continuous, Some(150));
+        assert_eq!(presolved.binary, Some(540));
+    }
+
+    #[test]
+    fn parses_lp_and_infeasible_summaries() {
+        let lp = "\
+Optimize a model with 3 rows, 4 columns and 9 nonzeros
+Barrier solved model in 12 iterations and 0.01 seconds (0.00 work units)
+Solved in 15 iterations and 0.02 seconds (0.00 work units)
+Optimal objective  -1.500000000e+01
+";
+        let s = parse_solve_summary(lp);
+        assert_eq!(s.status, Some(SolveStatus::Optimal));
+        assert_eq!(s.best_objective, Some(-15.0));
+        assert_eq!(s.barrier_iterations, Some(12));
+        assert_eq!(s.simplex_iterations, Some(15));
+        assert_eq!(s.runtime, Some(0.02));
+
+        let infeasible = "\
+Optimize a model with 3 rows, 4 columns and 9 nonzeros
+Presolve time: 0.00s
+
+Solved in 0 iterations and 0.00 seconds (0.00 work units)
+Infeasible model
+";
+        assert_eq!(
+            parse_solve_summary(infeasible).status,
+            Some(SolveStatus::Infeasible)
+        );
+        assert_eq!(
+            parse_solve_summary("Model is infeasible or unbounded\n").status,
+            Some(SolveStatus::InfeasibleOrUnbounded)
+        );
+        let no_solution = "Best objective -, best bound 1.0e+02, gap -\n";
+        let s = parse_solve_summary(no_solution);
+        assert_eq!(s.best_objective, None);
+        assert_eq!(s.best_bound, Some(100.0));
+        assert_eq!(s.gap, None);
+    }
+
+    #[test]
+    fn keeps_only_the_last_optimization() {
+        let log = format!(
+            "{}\nOptimize a model with 10 rows, 20 columns and 30 nonzeros\nInterrupt request received\n",
+            MIP_LOG
+        );
+        let s = parse_solve_summary(&log);
+        assert_eq!(s.status, Some(SolveStatus::Interrupted));
+        assert_eq!(s.best_objective, None);
+        assert_eq!(s.original_model.unwrap().rows, 10);
+    }
+
+    #[test]
+    fn parses_parameter_changes() {
+        let changes = parse_parameter_changes(
+            "Set parameter TimeLimit to value 60\nSet parameter TimeLimit to value 120\nSet parameter LogFile to value \"gurobi.log\"\n",
+        );
+        assert_eq!(changes["TimeLimit"], "120");
+        assert_eq!(changes["LogFile"], "\"gurobi.log\"");
+    }
+}
@@ -0,0 +1,485 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::argv;
+use crate::gurobi_params;
+use crate::process::{self, StopSignal};
+use crate::runner::{self, RunOutcome, RunRequest, RunSink};
+use crate::summary::SolveSummary;
+
+// 引数の組み合わせ (グリッド or リスト) を順番に、または同時実行数の上限付きで実行する
+
+// 1回のスイープで展開できる実行数の上限 (誤って巨大なグリッドを作った場合の安全策)
+pub const MAX_SWEEP_JOBS: usize = 1000;
+
+// グリッドの1軸 (例: flag = "--time-limit", values = ["60", "300"])
+// flag が空なら値をそのまま位置引数として渡す (インスタンスファイルなど)
+// param を指定すると引数ではなく Gurobi パラメータとして渡す (例: param = "TimeLimit")
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepAxis {
+    pub name: String,
+    #[serde(default)]
+    pub flag: String,
+    #[serde(default)]
+    pub param: Option<String>,
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepDefinition {
+    pub script_path: String,
+    pub command_prefix: String,
+    // すべての実行に共通で付ける引数
+    #[serde(default)]
+    pub base_args: String,
+    // 直積を取る軸
+    #[serde(default)]
+    pub grid: Vec<SweepAxis>,
+    // グリッドとは別に、引数の組をそのまま列挙する場合
+    #[serde(default)]
+    pub list: Vec<String>,
+    // 同時実行数 (既定は1 = 順番に実行)
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    // 作業ディレクトリ・環境変数 (RunRequest と同じ)
+    #[serde(default)]
+    pub working_dir: Option<String>,
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    #[serde(default)]
+    pub env_profile: Option<String>,
+}
+
+// 展開後の1回分の実行
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepJob {
+    pub index: usize,
+    // 表示用 (例: "instance=a.lp, TimeLimit=60")
+    pub label: String,
+    pub args_str: String,
+    // パラメータの軸の値 (RunRequest.gurobi_params に渡す)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub gurobi_params: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SweepJobStatus {
+    Queued,
+    Running,
+    Finished,
+    Failed,
+    Cancelled,
+}
+
+// 結果表の1行
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SweepRow {
+    pub index: usize,
+    pub label: String,
+    pub args_str: String,
+    pub status: SweepJobStatus,
+    pub run_id: Option<i64>,
+    pub exit_code: Option<i32>,
+    pub summary: Option<SolveSummary>,
+    pub error: Option<String>,
+}
+
+impl SweepRow {
+    fn new(job: &SweepJob, status: SweepJobStatus) -> Self {
+        SweepRow {
+            index: job.index,
+            label: job.label.clone(),
+            args_str: job.args_str.clone(),
+            status,
+            run_id: None,
+            exit_code: None,
+            summary: None,
+            error: None,
+        }
+    }
+}
+
+fn join_args(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// 定義を実行単位に展開する (グリッドの直積 → リストの順)
+pub fn expand(def: &SweepDefinition) -> Result<Vec<SweepJob>, String> {
+    if def.script_path.trim().is_empty() {
+        return Err("スクリプトが指定されていません。".to_string());
+    }
+    if let Some(axis) = def.grid.iter().find(|a| a.values.is_empty()) {
+        return Err(format!("軸 \"{}\" に値がありません。", axis.name));
+    }
+    // パラメータは作業ディレクトリの gurobi.env で渡すため、同じフォルダで同時には実行できない
+    if def.grid.iter().any(|a| a.param.is_some()) && def.concurrency.unwrap_or(1) > 1 {
+        return Err(
+            "Gurobi パラメータの軸があるスイープは同時実行できません (concurrency は 1 にしてください)。"
+                .to_string(),
+        );
+    }
+
+    let mut jobs = Vec::new();
+
+    if !def.grid.is_empty() {
+        let total: usize = def
+            .grid
+            .iter()
+            .try_fold(1usize, |acc, a| acc.checked_mul(a.values.len()))
+            .unwrap_or(usize::MAX);
+        if total.saturating_add(def.list.len()) > MAX_SWEEP_JOBS {
+            return Err(format!(
+                "実行数が多すぎます ({} 件、上限 {} 件)。",
+                total, MAX_SWEEP_JOBS
+            ));
+        }
+
+        // 各軸の値の位置を桁のように進めて直積を作る (最後の軸が最も速く変わる)
+        let mut pos = vec![0usize; def.grid.len()];
+        for _ in 0..total {
+            let mut labels = Vec::new();
+            let mut args = vec![def.base_args.clone()];
+            let mut gurobi_params = BTreeMap::new();
+            for (axis, &i) in def.grid.iter().zip(&pos) {
+                let value = &axis.values[i];
+                labels.push(format!("{}={}", axis.name, value));
+                match &axis.param {
+                    Some(param) => {
+                        gurobi_params.insert(param.clone(), Value::String(value.clone()));
+                    }
+                    None => args.push(format!("{} {}", axis.flag, argv::quote(value))),
+                }
+            }
+            let label = labels.join(", ");
+            // 単体の実行と同じく、実行前に名前と値の範囲を確かめる
+            gurobi_params::validate(&gurobi_params).map_err(|issues| {
+                format!("{}: {}", label, gurobi_params::issues_to_string(&issues))
+            })?;
+            let args: Vec<&str> = args.iter().map(|s| s.as_str()).collect();
+            jobs.push(SweepJob {
+                index: jobs.len(),
+                label,
+                args_str: join_args(&args),
+                gurobi_params,
+            });
+
+            for d in (0..pos.len()).rev() {
+                pos[d] += 1;
+                if pos[d] < def.grid[d].values.len() {
+                    break;
+                }
+                pos[d] = 0;
+            }
+        }
+    } else if def.list.len() > MAX_SWEEP_JOBS {
+        return Err(format!(
+            "実行数が多すぎます ({} 件、上限 {} 件)。",
+            def.list.len(),
+            MAX_SWEEP_JOBS
+        ));
+    }
+
+    for args in &def.list {
+        jobs.push(SweepJob {
+            index: jobs.len(),
+            label: args.trim().to_string(),
+            args_str: join_args(&[&def.base_args, args]),
+            gurobi_params: BTreeMap::new(),
+        });
+    }
+
+    if jobs.is_empty() {
+        return Err("実行する引数の組がありません。".to_string());
+    }
+    Ok(jobs)
+}
+
+// スイープの進行状況・ログ・結果の受け取り先
+pub trait SweepObserver: Send + Sync + 'static {
+    fn on_update(&self, row: &SweepRow);
+    fn on_line(&self, _index: usize, _line: &str) {}
+    // 終了した実行を記録し、履歴IDを返す
+    fn on_complete(&self, _request: &RunRequest, _outcome: &RunOutcome) -> Option<i64> {
+        None
+    }
+}
+
+// 実行中のスイープを止めるための共有状態
+#[derive(Default)]
+pub struct SweepControl {
+    cancelled: AtomicBool,
+    // 実行中のジョブ番号 -> PID
+    running: Mutex<HashMap<usize, u32>>,
+}
+
+impl SweepControl {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 待機中のジョブを打ち切り、実行中のプロセスにシグナルを送る
+    pub fn cancel(&self, signal: StopSignal) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        let pids: Vec<u32> = self
+            .running
+            .lock()
+            .map(|r| r.values().copied().collect())
+            .unwrap_or_default();
+        for pid in pids {
+            let _ = process::signal_process_tree(pid, signal);
+        }
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn has_running(&self) -> bool {
+        self.running.lock().map(|r| !r.is_empty()).unwrap_or(false)
+    }
+}
+
+// 1ジョブ分のログを observer に流す
+struct JobSink {
+    index: usize,
+    observer: Arc<dyn SweepObserver>,
+}
+
+impl RunSink for JobSink {
+    fn on_stdout(&self, line: &str) {
+        self.observer.on_line(self.index, line);
+    }
+
+    fn on_stderr(&self, line: &str) {
+        self.observer.on_line(self.index, line);
+    }
+}
+
+fn run_job(
+    def: &SweepDefinition,
+    job: &SweepJob,
+    control: &SweepControl,
+    observer: &Arc<dyn SweepObserver>,
+) -> SweepRow {
+    let request = RunRequest {
+        script_path: def.script_path.clone(),
+        args_str: job.args_str.clone(),
+        args: None,
+        command_prefix: def.command_prefix.clone(),
+        working_dir: def.working_dir.clone(),
+        env: def.env.clone(),
+        gurobi_params: job.gurobi_params.clone(),
+        ..Default::default()
+    };
+    let sink = Arc::new(JobSink {
+        index: job.index,
+        observer: observer.clone(),
+    });
+
+    let mut row = SweepRow::new(job, SweepJobStatus::Failed);
+    let (mut child, running) = match runner::start(&request, sink) {
+        Ok(started) => started,
+        Err(e) => {
+            row.error = Some(e);
+            return row;
+        }
+    };
+
+    if let Ok(mut r) = control.running.lock() {
+        r.insert(job.index, child.id());
+    }
+    // 登録前に停止要求が来ていた場合はここで止める
+    if control.is_cancelled() {
+        let _ = process::signal_process_tree(child.id(), StopSignal::Interrupt);
+    }
+    observer.on_update(&SweepRow::new(job, SweepJobStatus::Running));
+
+    let status = child.wait();
+
+    if let Ok(mut r) = control.running.lock() {
+        r.remove(&job.index);
+    }
+
+    let status = match status {
+        Ok(status) => status,
+        Err(e) => {
+            row.error = Some(e.to_string());
+            return row;
+        }
+    };
+
+    let outcome = running.finish(status);
+    row.run_id = observer.on_complete(&request, &outcome);
+    row.exit_code = outcome.exit_code;
+    row.status = if control.is_cancelled() {
+        SweepJobStatus::Cancelled
+    } else if outcome.success {
+        SweepJobStatus::Finished
+    } else {
+        SweepJobStatus::Failed
+    };
+    if !outcome.success {
+        row.error = outcome.stderr.lines().last().map(|l| l.to_string());
+    }
+    row.summary = Some(outcome.summary);
+    row
+}
+
+// すべてのジョブが終わるまでブロックし、ジョブ番号順の結果表を返す
+pub fn run_sweep(
+    def: &SweepDefinition,
+    jobs: Vec<SweepJob>,
+    control: Arc<SweepControl>,
+    observer: Arc<dyn SweepObserver>,
+) -> Vec<SweepRow> {
+    for job in &jobs {
+        observer.on_update(&SweepRow::new(job, SweepJobStatus::Queued));
+    }
+
+    let workers = def.concurrency.unwrap_or(1).clamp(1, jobs.len().max(1));
+    let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+    let rows = Arc::new(Mutex::new(Vec::new()));
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            let queue = queue.clone();
+            let rows = rows.clone();
+            let control = control.clone();
+            let observer = observer.clone();
+            scope.spawn(move || {
+                while let Some(job) = queue.lock().ok().and_then(|mut q| q.pop_front()) {
+                    let row = if control.is_cancelled() {
+                        SweepRow::new(&job, SweepJobStatus::Cancelled)
+                    } else {
+                        run_job(def, &job, &control, &observer)
+                    };
+                    observer.on_update(&row);
+                    if let Ok(mut rows) = rows.lock() {
+                        rows.push(row);
+                    }
+                }
+            });
+        }
+    });
+
+    let mut rows = rows.lock().map(|r| r.clone()).unwrap_or_default();
+    rows.sort_by_key(|r| r.index);
+    rows
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+fn opt<T: ToString>(v: Option<T>) -> String {
+    v.map(|v| v.to_string()).unwrap_or_default()
+}
+
+// 結果表をCSVにする (スプレッドシートでの比較用)
+pub fn rows_to_csv(rows: &[SweepRow]) -> String {
+    let mut out = String::from(
+        "index,label,args,status,exit_code,run_id,solve_status,objective,bound,gap,runtime,nodes\n",
+    );
+    for r in rows {
+        let s = r.summary.clone().unwrap_or_default();
+        let solve_status = s
+            .status
+            .and_then(|st| serde_json::to_value(st).ok())
+            .and_then(|v| v.as_str().map(|s| s.to_string()));
+        let status = serde_json::to_value(r.status)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_string()))
+            .unwrap_or_default();
+        let fields = [
+            r.index.to_string(),
+            csv_field(&r.label),
+            csv_field(&r.args_str),
+            status,
+            opt(r.exit_code),
+            opt(r.run_id),
+            opt(solve_status),
+            opt(s.best_objective),
+            opt(s.best_bound),
+            opt(s.gap),
+            opt(s.runtime),
+            opt(s.explored_nodes),
+        ];
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(grid: Vec<SweepAxis>, concurrency: Option<usize>) -> SweepDefinition {
+        SweepDefinition {
+            script_path: "model.py".to_string(),
+            command_prefix: "python -u".to_string(),
+            base_args: "--verbose".to_string(),
+            grid,
+            list: Vec::new(),
+            concurrency,
+            working_dir: None,
+            env: BTreeMap::new(),
+            env_profile: None,
+        }
+    }
+
+    fn axis(name: &str, flag: &str, param: Option<&str>, values: &[&str]) -> SweepAxis {
+        SweepAxis {
+            name: name.to_string(),
+            flag: flag.to_string(),
+            param: param.map(str::to_string),
+            values: values.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn parameter_axes_go_to_gurobi_params() {
+        let def = definition(
+            vec![
+                axis("instance", "", None, &["a.lp", "b c.lp"]),
+                axis("TimeLimit", "", Some("TimeLimit"), &["60", "300"]),
+            ],
+            None,
+        );
+        let jobs = expand(&def).unwrap();
+        assert_eq!(jobs.len(), 4);
+        assert_eq!(jobs[1].label, "instance=a.lp, TimeLimit=300");
+        assert_eq!(jobs[1].args_str, "--verbose a.lp");
+        assert_eq!(jobs[1].gurobi_params["TimeLimit"], Value::from("300"));
+        assert_eq!(jobs[2].args_str, "--verbose 'b c.lp'");
+    }
+
+    #[test]
+    fn parameter_axes_are_validated() {
+        let def = definition(vec![axis("focus", "", Some("MIPFocus"), &["1", "7"])], None);
+        let err = expand(&def).unwrap_err();
+        assert!(err.starts_with("focus=7: "), "{}", err);
+        let def = definition(vec![axis("x", "", Some("NoSuchParam"), &["1"])], None);
+        assert!(expand(&def).is_err());
+        let def = definition(vec![axis("t", "", Some("TimeLimit"), &["60"])], Some(2));
+        assert!(expand(&def).is_err());
+    }
+}
@@ -0,0 +1,469 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use crate::argv;
+use crate::gurobi_cl;
+use crate::runner::{RunMode, RunRequest};
+use crate::settings::backup_path;
+
+// プロジェクトのフォルダに置く gurobilab.toml で、スクリプト・既定の引数・環境変数・
+// コマンドプレフィックス・パラメータのプリセット・システム指示をまとめて宣言する
+// 実行履歴はワークスペースごとに分ける (既定はフォルダ内の .gurobilab/history.sqlite3)
+//
+// 例:
+//   name = "配送計画"
+//   commandPrefix = "uv run python -u"
+//   [env]
+//   GRB_LICENSE_FILE = "/opt/gurobi/gurobi.lic"
+//   [[scripts]]
+//   name = "main"
+//   path = "models/main.py"
+//   args = "--n 100"
+//   preset = "quick"
+//   [presets.quick]
+//   TimeLimit = 60
+
+pub const WORKSPACE_FILE: &str = "gurobilab.toml";
+const DEFAULT_HISTORY: &str = ".gurobilab/history.sqlite3";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceScript {
+    pub name: String,
+    // ワークスペースのフォルダからの相対パス (.py またはモデルファイル)
+    pub path: String,
+    // シェル風に分割される既定の引数
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub args: String,
+    // 既定で使うパラメータのプリセット
+    pub preset: Option<String>,
+    // ワークスペース全体の env に追加・上書きする
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    pub working_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct WorkspaceFile {
+    // 空ならフォルダ名
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name: String,
+    // 省略時はアプリの設定のもの
+    pub command_prefix: Option<String>,
+    pub system_prompt: Option<String>,
+    // 相対パスはワークスペースのフォルダから
+    pub working_dir: Option<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    pub scripts: Vec<WorkspaceScript>,
+    // プリセット名 -> (Gurobi パラメータ名 -> 値)
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, BTreeMap<String, Value>>,
+    // 実行履歴の SQLite ファイル (相対パスはワークスペースのフォルダから)
+    pub history: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub name: String,
+    pub file: WorkspaceFile,
+}
+
+impl WorkspaceFile {
+    pub fn validate(&self) -> Result<(), String> {
+        let mut names = BTreeSet::new();
+        for script in &self.scripts {
+            if script.name.trim().is_empty() {
+                return Err("scripts の name が空です。".to_string());
+            }
+            if !names.insert(script.name.as_str()) {
+                return Err(format!("スクリプト名が重複しています: {}", script.name));
+            }
+            if script.path.trim().is_empty() {
+                return Err(format!("スクリプト {} の path が空です。", script.name));
+            }
+            argv::split(&script.args).map_err(|e| {
+                format!("スクリプト {} の args を解釈できません: {}", script.name, e)
+            })?;
+            if let Some(preset) = &script.preset {
+                if !self.presets.contains_key(preset) {
+                    return Err(format!(
+                        "スクリプト {} のプリセット {} が presets にありません。",
+                        script.name, preset
+                    ));
+                }
+            }
+        }
+        if let Some(prefix) = &self.command_prefix {
+            argv::split(prefix).map_err(|e| format!("commandPrefix を解釈できません: {}", e))?;
+        }
+        Ok(())
+    }
+}
+
+// フォルダが指定された場合はその中の gurobilab.toml
+fn file_path(path: &Path) -> PathBuf {
+    if path.is_dir() {
+        path.join(WORKSPACE_FILE)
+    } else {
+        path.to_path_buf()
+    }
+}
+
+impl Workspace {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let file_path = file_path(path);
+        let text = std::fs::read_to_string(&file_path)
+            .map_err(|e| format!("{} を読み込めません: {}", file_path.display(), e))?;
+        let file: WorkspaceFile = toml::from_str(&text)
+            .map_err(|e| format!("{} の形式が正しくありません: {}", file_path.display(), e))?;
+        file.validate()?;
+        let root = file_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let root = std::fs::canonicalize(&root).unwrap_or(root);
+        let name = if file.name.trim().is_empty() {
+            root.file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| root.display().to_string())
+        } else {
+            file.name.trim().to_string()
+        };
+        Ok(Workspace { root, name, file })
+    }
+
+    // フォルダ直下の .py とモデルファイルをスクリプトとして並べた雛形を作る (既にあれば読み込むだけ)
+    pub fn init(dir: &Path, name: &str) -> Result<Self, String> {
+        let path = dir.join(WORKSPACE_FILE);
+        if path.exists() {
+            return Self::open(&path);
+        }
+        let mut entries: Vec<String> = std::fs::read_dir(dir)
+            .map_err(|e| format!("{} を開けません: {}", dir.display(), e))?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|f| f.ends_with(".py") || gurobi_cl::is_model_file(f))
+            .collect();
+        entries.sort();
+        let mut file = WorkspaceFile {
+            name: name.trim().to_string(),
+            ..Default::default()
+        };
+        for entry in entries {
+            let stem = entry.split('.').next().unwrap_or(&entry).to_string();
+            // 拡張子違いで同じ名前になる場合はファイル名をそのまま使う
+            let script_name = if file.scripts.iter().any(|s| s.name == stem) {
+                entry.clone()
+            } else {
+                stem
+            };
+            file.scripts.push(WorkspaceScript {
+                name: script_name,
+                path: entry,
+                ..Default::default()
+            });
+        }
+        let text = toml::to_string_pretty(&file).map_err(|e| e.to_string())?;
+        std::fs::write(&path, text)
+            .map_err(|e| format!("{} に書き込めません: {}", path.display(), e))?;
+        Self::open(&path)
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        let path = Path::new(path);
+        if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.root.join(path)
+        }
+    }
+
+    pub fn history_path(&self) -> PathBuf {
+        self.resolve(self.file.history.as_deref().unwrap_or(DEFAULT_HISTORY))
+    }
+
+    pub fn script(&self, name: &str) -> Result<&WorkspaceScript, String> {
+        self.file
+            .scripts
+            .iter()
+            .find(|s| s.name == name)
+            .ok_or_else(|| format!("ワークスペースにスクリプト {} がありません。", name))
+    }
+
+    // スクリプトを実行するためのリクエストを組み立てる
+    // preset を省略するとスクリプトの既定のプリセット、default_prefix はワークスペースに指定が無い場合のもの
+    pub fn run_request(
+        &self,
+        script: &str,
+        preset: Option<&str>,
+        default_prefix: &str,
+    ) -> Result<RunRequest, String> {
+        let script = self.script(script)?;
+        let script_path = self.resolve(&script.path).to_string_lossy().to_string();
+        let mode = if gurobi_cl::is_model_file(&script_path) {
+            RunMode::GurobiCl
+        } else {
+            RunMode::Script
+        };
+        let command_prefix = match mode {
+            // gurobi_cl では command_prefix は gurobi_cl のパス (空なら PATH 上のもの)
+            RunMode::GurobiCl => String::new(),
+            RunMode::Script => self
+                .file
+                .command_prefix
+                .clone()
+                .unwrap_or_else(|| default_prefix.to_string()),
+        };
+        let working_dir = script
+            .working_dir
+            .as_deref()
+            .or(self.file.working_dir.as_deref())
+            .map(|dir| self.resolve(dir).to_string_lossy().to_string());
+        let mut env = self.file.env.clone();
+        env.extend(script.env.clone());
+        let gurobi_params = match preset.or(script.preset.as_deref()) {
+            Some(name) => self
+                .file
+                .presets
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("ワークスペースにプリセット {} がありません。", name))?,
+            None => BTreeMap::new(),
+        };
+        Ok(RunRequest {
+            script_path,
+            args_str: script.args.clone(),
+            command_prefix,
+            working_dir,
+            env,
+            gurobi_params,
+            mode,
+            ..Default::default()
+        })
+    }
+}
+
+// 最近開いたワークスペース (新しい順)
+pub struct RecentWorkspaces {
+    path: PathBuf,
+    items: Vec<String>,
+}
+
+const MAX_RECENT: usize = 10;
+
+impl RecentWorkspaces {
+    // 壊れたファイルでは起動を止めず、.bak に退避して空から始める
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let items = match std::fs::read_to_string(path) {
+            Ok(text) => match serde_json::from_str(&text) {
+                Ok(items) => items,
+                Err(e) => {
+                    let backup = backup_path(path);
+                    eprintln!(
+                        "最近のワークスペース {} を読み込めません: {}\n{} に退避し、空の状態で起動します。",
+                        path.display(),
+                        e,
+                        backup.display()
+                    );
+                    if let Err(e) = std::fs::rename(path, &backup) {
+                        eprintln!("最近のワークスペースを退避できません: {}", e);
+                    }
+                    Vec::new()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e.to_string()),
+        };
+        Ok(RecentWorkspaces {
+            path: path.to_path_buf(),
+            items,
+        })
+    }
+
+    pub fn list(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    pub fn touch(&mut self, root: &Path) -> Result<(), String> {
+        let root = root.to_string_lossy().to_string();
+        self.items.retain(|item| *item != root);
+        self.items.insert(0, root);
+        self.items.truncate(MAX_RECENT);
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        let text = serde_json::to_string_pretty(&self.items).map_err(|e| e.to_string())?;
+        std::fs::write(&self.path, text).map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "gurobilab-workspace-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn init_and_open_round_trip() {
+        let dir = temp_dir("init");
+        for file in ["main.py", "main.lp", "big.mps.gz", "notes.txt"] {
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+
+        let workspace = Workspace::init(&dir, " 配送計画 ").unwrap();
+        assert_eq!(workspace.name, "配送計画");
+        let scripts: Vec<(&str, &str)> = workspace
+            .file
+            .scripts
+            .iter()
+            .map(|s| (s.name.as_str(), s.path.as_str()))
+            .collect();
+        assert_eq!(
+            scripts,
+            vec![
+                ("big", "big.mps.gz"),
+                ("main", "main.lp"),
+                ("main.py", "main.py")
+            ]
+        );
+
+        // 既にあれば書き換えずに読み込むだけ
+        let mut file = workspace.file.clone();
+        file.command_prefix = Some("uv run python -u".to_string());
+        file.presets.insert(
+            "quick".to_string(),
+            BTreeMap::from([("TimeLimit".to_string(), Value::from(60))]),
+        );
+        std::fs::write(
+            dir.join(WORKSPACE_FILE),
+            toml::to_string_pretty(&file).unwrap(),
+        )
+        .unwrap();
+        let reopened = Workspace::init(&dir, "other").unwrap();
+        assert_eq!(reopened.file, file);
+        assert_eq!(Workspace::open(&dir).unwrap().file, file);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn open_rejects_invalid_files() {
+        let dir = temp_dir("invalid");
+        std::fs::write(
+            dir.join(WORKSPACE_FILE),
+            "[[scripts]]\nname = \"a\"\npath = \"a.py\"\npreset = \"missing\"\n",
+        )
+        .unwrap();
+        assert!(Workspace::open(&dir).unwrap_err().contains("missing"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn run_request_resolves_paths_and_presets() {
+        let root = PathBuf::from("/work/routing");
+        let workspace = Workspace {
+            root: root.clone(),
+            name: "routing".to_string(),
+            file: toml::from_str(
+                r#"
+                commandPrefix = "uv run python"
+                workingDir = "data"
+                [env]
+                A = "1"
+                B = "1"
+                [[scripts]]
+                name = "main"
+                path = "models/main.py"
+                args = "--n 100"
+                preset = "quick"
+                [scripts.env]
+                B = "2"
+                [[scripts]]
+                name = "lp"
+                path = "/models/model.lp"
+                workingDir = "/tmp"
+                [presets.quick]
+                TimeLimit = 60
+                [presets.long]
+                TimeLimit = 3600
+                "#,
+            )
+            .unwrap(),
+        };
+
+        let request = workspace.run_request("main", None, "python").unwrap();
+        assert_eq!(
+            PathBuf::from(&request.script_path),
+            root.join("models/main.py")
+        );
+        assert_eq!(request.args_str, "--n 100");
+        assert_eq!(request.command_prefix, "uv run python");
+        assert_eq!(
+            request.working_dir.map(PathBuf::from),
+            Some(root.join("data"))
+        );
+        assert_eq!(request.env["A"], "1");
+        assert_eq!(request.env["B"], "2");
+        assert_eq!(request.gurobi_params["TimeLimit"], 60);
+        assert_eq!(request.mode, RunMode::Script);
+
+        let request = workspace
+            .run_request("main", Some("long"), "python")
+            .unwrap();
+        assert_eq!(request.gurobi_params["TimeLimit"], 3600);
+        assert!(workspace
+            .run_request("main", Some("none"), "python")
+            .is_err());
+
+        let request = workspace.run_request("lp", None, "python").unwrap();
+        assert_eq!(request.script_path, "/models/model.lp");
+        assert_eq!(request.mode, RunMode::GurobiCl);
+        assert_eq!(request.command_prefix, "");
+        assert_eq!(request.working_dir.as_deref(), Some("/tmp"));
+        assert!(request.gurobi_params.is_empty());
+
+        assert!(workspace.run_request("missing", None, "python").is_err());
+        assert_eq!(
+            workspace.history_path(),
+            root.join(".gurobilab/history.sqlite3")
+        );
+    }
+
+    #[test]
+    fn recent_workspaces_move_corrupt_file_aside() {
+        let dir = temp_dir("recent");
+        let path = dir.join("workspaces.json");
+        std::fs::write(&path, "{").unwrap();
+
+        let mut recent = RecentWorkspaces::open(&path).unwrap();
+        assert!(recent.list().is_empty());
+        assert_eq!(
+            std::fs::read_to_string(dir.join("workspaces.json.bak")).unwrap(),
+            "{"
+        );
+
+        recent.touch(Path::new("/a")).unwrap();
+        recent.touch(Path::new("/b")).unwrap();
+        recent.touch(Path::new("/a")).unwrap();
+        assert_eq!(
+            RecentWorkspaces::open(&path).unwrap().list(),
+            vec!["/a", "/b"]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}